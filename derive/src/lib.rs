@@ -0,0 +1,175 @@
+//! Derive macros for `rusty-arena`, re-exported behind its `derive` feature.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Generates a struct-of-arrays arena for a struct with named fields.
+///
+/// For `struct Particle { .. }` this emits:
+///
+/// - `ParticleArena`, which stores each field in its own
+///   `rusty_arena::soa::Column` of fixed-size arena blocks and hands out
+///   `Idx<Particle>` row ids from `alloc`,
+/// - `ParticleRef<'a>` and `ParticleRefMut<'a>`, which borrow one row field by
+///   field, returned by `get` and `get_mut`,
+/// - an implementation of `rusty_arena::soa::Soa` naming the arena type.
+///
+/// Each column is available through a method named after the field with a
+/// `_column` suffix, plus a `_column_mut` variant. The suffix keeps them apart
+/// from the other methods whatever the fields are called. `columns_mut`
+/// returns a `ParticleColumns<'a>` holding every column mutably at once, for
+/// passes that read one field while writing another.
+#[proc_macro_derive(SoaArena)]
+pub fn derive_soa_arena(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "SoaArena does not support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "SoaArena needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "SoaArena can only be derived for structs",
+            ))
+        }
+    };
+
+    if fields.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "SoaArena needs at least one field",
+        ));
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let arena = format_ident!("{}Arena", name);
+    let row_ref = format_ident!("{}Ref", name);
+    let row_mut = format_ident!("{}RefMut", name);
+    let columns = format_ident!("{}Columns", name);
+    let names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let names_column: Vec<_> = names
+        .iter()
+        .map(|n| format_ident!("{}_column", n))
+        .collect();
+    let names_column_mut: Vec<_> = names
+        .iter()
+        .map(|n| format_ident!("{}_column_mut", n))
+        .collect();
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let first = names[0];
+
+    Ok(quote! {
+        /// A struct-of-arrays arena generated by `#[derive(SoaArena)]`.
+        #vis struct #arena {
+            #(#names: ::rusty_arena::soa::Column<#types>,)*
+        }
+
+        /// One row of the arena, borrowed field by field.
+        #[allow(dead_code)]
+        #vis struct #row_ref<'a> {
+            #(#vis #names: &'a #types,)*
+        }
+
+        /// One row of the arena, borrowed mutably field by field.
+        #[allow(dead_code)]
+        #vis struct #row_mut<'a> {
+            #(#vis #names: &'a mut #types,)*
+        }
+
+        /// Every column of the arena, borrowed mutably.
+        #[allow(dead_code)]
+        #vis struct #columns<'a> {
+            #(#vis #names: &'a mut ::rusty_arena::soa::Column<#types>,)*
+        }
+
+        #[allow(dead_code)]
+        impl #arena {
+            #vis fn new() -> Self {
+                Self {
+                    #(#names: ::rusty_arena::soa::Column::new(),)*
+                }
+            }
+
+            /// Splits `value` into the columns and returns the id of its row.
+            #vis fn alloc(&mut self, value: #name) -> ::rusty_arena::id::Idx<#name> {
+                let index = u32::try_from(self.#first.len()).expect("too many rows in the arena");
+                #(self.#names.push(value.#names);)*
+                ::rusty_arena::id::Idx::from_u32(index)
+            }
+
+            #vis fn get(&self, idx: ::rusty_arena::id::Idx<#name>) -> Option<#row_ref<'_>> {
+                let i = idx.index();
+                if i >= self.len() {
+                    return None;
+                }
+                Some(#row_ref {
+                    #(#names: &self.#names[i],)*
+                })
+            }
+
+            #vis fn get_mut(&mut self, idx: ::rusty_arena::id::Idx<#name>) -> Option<#row_mut<'_>> {
+                let i = idx.index();
+                if i >= self.len() {
+                    return None;
+                }
+                Some(#row_mut {
+                    #(#names: &mut self.#names[i],)*
+                })
+            }
+
+            #vis fn len(&self) -> usize {
+                self.#first.len()
+            }
+
+            #vis fn is_empty(&self) -> bool {
+                self.#first.is_empty()
+            }
+
+            #vis fn columns_mut(&mut self) -> #columns<'_> {
+                #columns {
+                    #(#names: &mut self.#names,)*
+                }
+            }
+
+            #(
+                #vis fn #names_column(&self) -> &::rusty_arena::soa::Column<#types> {
+                    &self.#names
+                }
+
+                #vis fn #names_column_mut(&mut self) -> &mut ::rusty_arena::soa::Column<#types> {
+                    &mut self.#names
+                }
+            )*
+        }
+
+        impl ::core::default::Default for #arena {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl ::rusty_arena::soa::Soa for #name {
+            type Arena = #arena;
+        }
+    })
+}
@@ -0,0 +1,46 @@
+/* C API of rusty-arena, built with the `ffi` feature. */
+
+#ifndef RUSTY_ARENA_H
+#define RUSTY_ARENA_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An untyped bump arena. Not thread-safe. */
+typedef struct rusty_arena rusty_arena;
+
+typedef struct rusty_arena_stats {
+    size_t blocks;
+    size_t bytes_reserved;
+    size_t bytes_used;
+    size_t wasted_bytes;
+    size_t high_water_mark;
+} rusty_arena_stats;
+
+rusty_arena *rusty_arena_new(void);
+
+/* Returns NULL if block_size is zero. */
+rusty_arena *rusty_arena_with_block_size(size_t block_size);
+
+/* Releases the arena and everything allocated from it. Accepts NULL. */
+void rusty_arena_destroy(rusty_arena *arena);
+
+/* Returns NULL on failure or if align is not a power of two. */
+void *rusty_arena_alloc(const rusty_arena *arena, size_t size, size_t align);
+
+/* Invalidates every pointer handed out by the arena. Keeps its blocks for
+   reuse, except the dedicated blocks of large allocations. */
+void rusty_arena_reset(rusty_arena *arena);
+
+size_t rusty_arena_bytes_allocated(const rusty_arena *arena);
+
+void rusty_arena_get_stats(const rusty_arena *arena, rusty_arena_stats *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUSTY_ARENA_H */
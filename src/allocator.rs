@@ -0,0 +1,102 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::NonNull;
+
+use crate::bump::Bump;
+
+// Memory is only reclaimed in bulk, except that the most recent allocation
+// can be grown, shrunk or freed in place.
+macro_rules! impl_allocator {
+    ($allocator:path, $error:path) => {
+        unsafe impl<A: GlobalAlloc> $allocator for &Bump<A> {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, $error> {
+                self.allocate_bytes(layout).map_err(|_| $error)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.deallocate_bytes(ptr, layout)
+            }
+
+            unsafe fn grow(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, $error> {
+                self.reallocate_bytes(ptr, old_layout, new_layout)
+                    .map_err(|_| $error)
+            }
+
+            unsafe fn shrink(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, $error> {
+                self.reallocate_bytes(ptr, old_layout, new_layout)
+                    .map_err(|_| $error)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "nightly")]
+impl_allocator!(core::alloc::Allocator, core::alloc::AllocError);
+
+#[cfg(feature = "allocator-api2")]
+impl_allocator!(
+    allocator_api2::alloc::Allocator,
+    allocator_api2::alloc::AllocError
+);
+
+#[cfg(feature = "hashbrown")]
+impl_allocator!(
+    allocator_api2_02::alloc::Allocator,
+    allocator_api2_02::alloc::AllocError
+);
+
+#[cfg(all(test, any(feature = "nightly", feature = "allocator-api2")))]
+mod tests {
+    use crate::bump::Bump;
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn collections() {
+        use std::collections::BTreeMap;
+
+        let bump = Bump::new();
+        let mut vec = Vec::new_in(&bump);
+        for i in 0..1000u32 {
+            vec.push(i);
+        }
+        assert_eq!(vec.iter().sum::<u32>(), 499500);
+        // The vector kept growing in place at the tip of the block.
+        assert_eq!(bump.bytes_allocated(), 4096);
+
+        let boxed = Box::new_in([1u64; 4], &bump);
+        assert_eq!(boxed.iter().sum::<u64>(), 4);
+
+        let mut map = BTreeMap::new_in(&bump);
+        map.insert(1, "one");
+        map.insert(2, "two");
+        assert_eq!(map[&2], "two");
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn allocator_api2() {
+        use allocator_api2::boxed::Box;
+        use allocator_api2::vec::Vec;
+
+        let bump = Bump::new();
+        let mut vec = Vec::new_in(&bump);
+        for i in 0..1000u32 {
+            vec.push(i);
+        }
+        vec.shrink_to_fit();
+        assert_eq!(vec.iter().sum::<u32>(), 499500);
+        assert_eq!(bump.bytes_allocated(), 4096);
+
+        let boxed = Box::new_in(7u64, &bump);
+        assert_eq!(*boxed, 7);
+    }
+}
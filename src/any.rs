@@ -0,0 +1,96 @@
+//! An arena for values of any `'static` type, without declaring the types
+//! up front.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use core::any::{Any, TypeId};
+use core::cell::RefCell;
+
+use crate::arena::Arena;
+
+/// Holds one typed [`Arena`] per type allocated into it, created on first
+/// use.
+///
+/// Like with a typed arena, every value is dropped when the `AnyArena` is.
+pub struct AnyArena {
+    // Each value is a `Box<Arena<T>>` keyed by `TypeId::of::<T>()`. Arenas
+    // are never removed, so their addresses stay stable.
+    arenas: RefCell<BTreeMap<TypeId, Box<dyn Any>>>,
+}
+
+impl AnyArena {
+    pub fn new() -> Self {
+        Self {
+            arenas: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: 'static>(&self, value: T) -> &mut T {
+        self.arena::<T>().alloc(value)
+    }
+
+    /// Returns the arena holding the values of type `T`, creating it if
+    /// needed.
+    pub fn arena<T: 'static>(&self) -> &Arena<T> {
+        let mut arenas = self.arenas.borrow_mut();
+        let arena = arenas
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Arena::<T>::new()));
+        let arena: *const Arena<T> = arena.downcast_ref::<Arena<T>>().unwrap();
+        // The box is never dropped or replaced before `self` is.
+        unsafe { &*arena }
+    }
+
+    /// Returns the number of values of type `T`.
+    pub fn len_of<T: 'static>(&self) -> usize {
+        self.arenas
+            .borrow()
+            .get(&TypeId::of::<T>())
+            .map_or(0, |arena| arena.downcast_ref::<Arena<T>>().unwrap().len())
+    }
+
+    /// Returns the number of distinct types allocated so far.
+    pub fn type_count(&self) -> usize {
+        self.arenas.borrow().len()
+    }
+}
+
+impl Default for AnyArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::AnyArena;
+
+    struct Plugin(Rc<Cell<usize>>);
+
+    impl Drop for Plugin {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn mixed_types() {
+        let drops = Rc::new(Cell::new(0));
+        let arena = AnyArena::new();
+        let a = arena.alloc(1u32);
+        let b = arena.alloc("two");
+        let c = arena.alloc(3u32);
+        arena.alloc(Plugin(drops.clone()));
+        *a += 10;
+        assert_eq!((*a, *b, *c), (11, "two", 3));
+        assert_eq!(arena.len_of::<u32>(), 2);
+        assert_eq!(arena.len_of::<u8>(), 0);
+        assert_eq!(arena.type_count(), 3);
+        drop(arena);
+        assert_eq!(drops.get(), 1);
+    }
+}
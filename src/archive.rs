@@ -0,0 +1,360 @@
+//! Zero-copy archives of arenas with [rkyv](https://docs.rs/rkyv).
+//!
+//! A [`FrozenArena`] archives as an [`ArchivedArena`] and an [`IdArena`] as an
+//! [`ArchivedIdArena`]: the values in allocation order, in one contiguous run.
+//! The archive can be written at build time and read in place at run time,
+//! e.g. from a memory-mapped file, with `rkyv::access`. [`Idx`] handles
+//! stored in the values archive as [`ArchivedIdx`], which index the archived
+//! arena directly.
+//!
+//! ```
+//! use rkyv::rancor::Error;
+//! use rkyv::string::ArchivedString;
+//! use rusty_arena::arena::Arena;
+//! use rusty_arena::archive::ArchivedArena;
+//!
+//! let arena = Arena::new();
+//! arena.alloc(String::from("baked"));
+//! let bytes = rkyv::to_bytes::<Error>(&arena.freeze()).unwrap();
+//!
+//! let archived = rkyv::access::<ArchivedArena<ArchivedString>, Error>(&bytes).unwrap();
+//! assert_eq!(archived[0], "baked");
+//! ```
+
+use core::alloc::GlobalAlloc;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Index;
+use core::slice;
+
+use rkyv::bytecheck::CheckBytes;
+use rkyv::munge::munge;
+use rkyv::rancor::Fallible;
+use rkyv::ser::{Allocator, Writer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Archived, Deserialize, Place, Portable, Serialize};
+
+use crate::arena::Arena;
+use crate::frozen::FrozenArena;
+use crate::id::{IdArena, Idx};
+use crate::Global;
+
+/// The archived form of a [`FrozenArena`].
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[repr(transparent)]
+pub struct ArchivedArena<T> {
+    values: ArchivedVec<T>,
+}
+
+impl<T> ArchivedArena<T> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the value allocated `index`-th.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.values.get(index)
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        self.values.as_slice()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.values.iter()
+    }
+}
+
+impl<T> Index<usize> for ArchivedArena<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.values[index]
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ArchivedArena<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArchivedArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Archive, A: GlobalAlloc> Archive for FrozenArena<T, A> {
+    type Archived = ArchivedArena<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: VecResolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedArena { values } = out);
+        ArchivedVec::resolve_from_len(self.len(), resolver, values);
+    }
+}
+
+impl<T, A, S> Serialize<S> for FrozenArena<T, A>
+where
+    T: Serialize<S>,
+    A: GlobalAlloc,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<VecResolver, S::Error> {
+        ArchivedVec::serialize_from_iter::<T, _, _>(self.iter(), serializer)
+    }
+}
+
+/// Deserializes into an arena that holds all the values in one block.
+impl<T, D> Deserialize<FrozenArena<T, Global>, D> for ArchivedArena<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<FrozenArena<T>, D::Error> {
+        let arena = Arena::with_capacity(self.len());
+        for value in self.iter() {
+            arena.alloc(value.deserialize(deserializer)?);
+        }
+        Ok(arena.freeze())
+    }
+}
+
+/// The archived form of an [`Idx<T>`].
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[repr(transparent)]
+pub struct ArchivedIdx<T> {
+    index: Archived<u32>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ArchivedIdx<T> {
+    pub fn to_native(&self) -> Idx<T> {
+        Idx::from_u32(self.index.to_native())
+    }
+
+    pub fn index(&self) -> usize {
+        self.index.to_native() as usize
+    }
+}
+
+impl<T> Clone for ArchivedIdx<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArchivedIdx<T> {}
+
+impl<T> PartialEq for ArchivedIdx<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for ArchivedIdx<T> {}
+
+impl<T> Hash for ArchivedIdx<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index().hash(state)
+    }
+}
+
+impl<T> fmt::Debug for ArchivedIdx<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Idx({})", self.index)
+    }
+}
+
+impl<T> Archive for Idx<T> {
+    type Archived = ArchivedIdx<T>;
+    type Resolver = ();
+
+    fn resolve(&self, _: (), out: Place<ArchivedIdx<T>>) {
+        munge!(let ArchivedIdx { index, _marker: _ } = out);
+        self.as_u32().resolve((), index);
+    }
+}
+
+impl<T, S: Fallible + ?Sized> Serialize<S> for Idx<T> {
+    fn serialize(&self, _: &mut S) -> Result<(), S::Error> {
+        Ok(())
+    }
+}
+
+impl<T, D: Fallible + ?Sized> Deserialize<Idx<T>, D> for ArchivedIdx<T> {
+    fn deserialize(&self, _: &mut D) -> Result<Idx<T>, D::Error> {
+        Ok(self.to_native())
+    }
+}
+
+/// The archived form of an [`IdArena`], indexed by [`ArchivedIdx`] as well
+/// as [`Idx`].
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[repr(transparent)]
+pub struct ArchivedIdArena<T> {
+    values: ArchivedVec<T>,
+}
+
+impl<T> ArchivedIdArena<T> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        self.values.as_slice()
+    }
+
+    /// Iterates over the values with their indices, in allocation order.
+    pub fn iter<U: Archive<Archived = T>>(&self) -> impl Iterator<Item = (Idx<U>, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (Idx::from_u32(i as u32), x))
+    }
+}
+
+impl<T: Archive> Index<Idx<T>> for ArchivedIdArena<T::Archived> {
+    type Output = T::Archived;
+
+    fn index(&self, idx: Idx<T>) -> &T::Archived {
+        &self.values[idx.index()]
+    }
+}
+
+impl<T: Archive> Index<ArchivedIdx<T>> for ArchivedIdArena<T::Archived> {
+    type Output = T::Archived;
+
+    fn index(&self, idx: ArchivedIdx<T>) -> &T::Archived {
+        &self.values[idx.index()]
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArchivedIdArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.values.iter()).finish()
+    }
+}
+
+impl<T: Archive> Archive for IdArena<T> {
+    type Archived = ArchivedIdArena<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: VecResolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedIdArena { values } = out);
+        ArchivedVec::resolve_from_len(self.len(), resolver, values);
+    }
+}
+
+impl<T, S> Serialize<S> for IdArena<T>
+where
+    T: Serialize<S>,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<VecResolver, S::Error> {
+        ArchivedVec::serialize_from_slice(self.as_slice(), serializer)
+    }
+}
+
+impl<T, D> Deserialize<IdArena<T>, D> for ArchivedIdArena<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<IdArena<T>, D::Error> {
+        let mut arena = IdArena::with_capacity(self.len());
+        for value in self.values.iter() {
+            arena.alloc(value.deserialize(deserializer)?);
+        }
+        Ok(arena)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use rkyv::rancor::Error;
+    use rkyv::string::ArchivedString;
+
+    use super::*;
+
+    #[test]
+    fn frozen_arena() {
+        let arena = Arena::with_block_size(64);
+        for i in 0..100u32 {
+            arena.alloc(i.to_string());
+        }
+        let bytes = rkyv::to_bytes::<Error>(&arena.freeze()).unwrap();
+        let archived = rkyv::access::<ArchivedArena<ArchivedString>, Error>(&bytes).unwrap();
+        assert_eq!(archived.len(), 100);
+        assert_eq!(archived[42], "42");
+        assert!(archived
+            .iter()
+            .enumerate()
+            .all(|(i, s)| *s == i.to_string()));
+
+        let frozen: FrozenArena<String> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(frozen.len(), 100);
+        assert_eq!(frozen[99], "99");
+    }
+
+    #[test]
+    fn id_arena() {
+        #[derive(Archive, Serialize, Deserialize)]
+        struct Node {
+            value: u64,
+            next: Option<Idx<Node>>,
+        }
+
+        let mut nodes = IdArena::new();
+        let first = nodes.next_idx();
+        let mut prev = None;
+        for value in 0..10 {
+            prev = Some(nodes.alloc(Node { value, next: prev }));
+        }
+        nodes[first].next = prev;
+
+        let bytes = rkyv::to_bytes::<Error>(&nodes).unwrap();
+        let archived = rkyv::access::<ArchivedIdArena<ArchivedNode>, Error>(&bytes).unwrap();
+        let mut node = &archived[prev.unwrap()];
+        let mut values = Vec::new();
+        for _ in 0..10 {
+            values.push(node.value.to_native());
+            node = &archived[*node.next.as_ref().unwrap()];
+        }
+        assert_eq!(values, [9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+
+        let nodes: IdArena<Node> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(nodes[first].next, prev);
+    }
+
+    #[test]
+    fn rejects_corrupt_archives() {
+        let arena = Arena::new();
+        arena.alloc(String::from("a string long enough to be out of line"));
+        let mut bytes = rkyv::to_bytes::<Error>(&arena.freeze()).unwrap();
+        let len = bytes.len();
+        bytes[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(rkyv::access::<ArchivedArena<ArchivedString>, Error>(&bytes).is_err());
+    }
+}
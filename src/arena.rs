@@ -0,0 +1,2981 @@
+use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::marker::PhantomData;
+use core::mem;
+use core::num::NonZeroU32;
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::slice;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::block::RawBlock;
+use crate::frozen::FrozenArena;
+use crate::id::Idx;
+#[cfg(feature = "std")]
+use crate::persist;
+#[cfg(feature = "std")]
+use crate::relative::Relocatable;
+use crate::{
+    sanitizer, telemetry, AllocatedSince, ArenaAllocError, ArenaObserver, ArenaStats, Global,
+};
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+
+struct Block {
+    raw: RawBlock,
+    // Only kept up to date for blocks that are not being filled, the length
+    // of the current block follows from the bump pointer.
+    count_of_elements: usize,
+}
+
+impl Block {
+    /// Drops the elements of this block past the first `len`.
+    unsafe fn truncate<T>(&mut self, len: usize) {
+        if len >= self.count_of_elements {
+            return;
+        }
+        // Forget the elements before dropping them so that a panicking
+        // destructor leaks the rest instead of dropping them twice.
+        let count = mem::replace(&mut self.count_of_elements, len);
+        if mem::needs_drop::<T>() {
+            let ptr = self.raw.ptr().cast::<T>().add(len);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(ptr, count - len));
+        }
+        #[cfg(feature = "valgrind")]
+        for i in len..count {
+            sanitizer::freelike(self.raw.ptr().add(i * mem::size_of::<T>()));
+        }
+        #[cfg(feature = "poison")]
+        self.raw
+            .poison(len * mem::size_of::<T>()..count * mem::size_of::<T>());
+        sanitizer::mark_unaddressable(
+            self.raw.ptr().add(len * mem::size_of::<T>()),
+            (count - len) * mem::size_of::<T>(),
+        );
+    }
+}
+
+/// A position in an [`Arena`], returned by [`Arena::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Marker {
+    block: usize,
+    count: usize,
+}
+
+/// A position in an [`Arena`] that steps forward over the values allocated
+/// after it, created by [`Arena::cursor`].
+///
+/// The cursor doesn't borrow the arena, so values can be allocated between
+/// steps; a step that finds nothing new returns `None`, and a later one picks
+/// up what was allocated since.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    block: usize,
+    index: usize,
+    offset: usize,
+}
+
+impl Cursor {
+    /// Returns the next value with its byte offset, or `None` if the cursor
+    /// has caught up with the arena.
+    ///
+    /// The offset is where the value would be if all the values were laid
+    /// out back to back in allocation order, as [`Arena::compact`] does.
+    pub fn next<'a, T, A: GlobalAlloc>(
+        &mut self,
+        arena: &'a mut Arena<T, A>,
+    ) -> Option<(usize, &'a T)> {
+        let offset = self.offset;
+        let ptr = arena.internal.step(self)?;
+        Some((offset, unsafe { &*ptr }))
+    }
+
+    /// Returns the byte offset of the next value.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// A compact reference to a value in an [`Arena`], returned by
+/// [`Arena::handle_of`].
+///
+/// It packs the index of the block holding the value in its top
+/// [`HANDLE_BLOCK_BITS`] bits and the position of the value in that block in
+/// the others, so that large graphs can link their nodes with 4 bytes instead
+/// of a pointer's 8. An `Option<Handle32>` takes 4 bytes as well. Handles
+/// don't borrow the arena; resolving one that [`rewind`](Arena::rewind),
+/// [`reset`](Arena::reset) or [`compact`](Arena::compact) left behind gives
+/// another value or panics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle32(NonZeroU32);
+
+/// The number of bits of a [`Handle32`] that hold the block index.
+pub const HANDLE_BLOCK_BITS: u32 = 12;
+const HANDLE_INDEX_BITS: u32 = 32 - HANDLE_BLOCK_BITS;
+
+impl Handle32 {
+    // Stored plus one, which leaves out the last position of the last block.
+    fn new(block: usize, index: usize) -> Option<Self> {
+        if block >> HANDLE_BLOCK_BITS != 0 || index >> HANDLE_INDEX_BITS != 0 {
+            return None;
+        }
+        let packed = (block << HANDLE_INDEX_BITS | index) as u32;
+        packed.checked_add(1).and_then(NonZeroU32::new).map(Self)
+    }
+
+    /// Rebuilds a handle from [`as_u32`](Self::as_u32), e.g. when reading
+    /// back a serialized graph.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `raw` is zero, which no handle is.
+    pub fn from_u32(raw: u32) -> Self {
+        Self(NonZeroU32::new(raw).expect("zero is not a handle"))
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self.0.get()
+    }
+
+    pub(crate) fn block(self) -> usize {
+        ((self.0.get() - 1) >> HANDLE_INDEX_BITS) as usize
+    }
+
+    pub(crate) fn index(self) -> usize {
+        ((self.0.get() - 1) & ((1 << HANDLE_INDEX_BITS) - 1)) as usize
+    }
+}
+
+// The bump pointer and the counters live in `Cell`s so that allocating out of
+// the current block never touches a borrow flag. The block list is only
+// borrowed when moving to another block.
+struct Internal<T, A: GlobalAlloc> {
+    // Where the blocks come from and go back to.
+    backing: A,
+    blocks: RefCell<Vec<Block>>,
+    // Index of the block being filled. Blocks after it are spare blocks kept
+    // around by `reset`.
+    current: Cell<usize>,
+    // Size of the next fresh block, doubled after each one up to
+    // `max_block_size`.
+    block_size: Cell<usize>,
+    max_block_size: usize,
+    bytes: Cell<usize>,
+    alloc_bytes_remaining: Cell<usize>,
+    alloc_ptr: Cell<*mut u8>,
+    // Start of the current block, so that its length can be computed without
+    // borrowing `blocks`.
+    current_start: Cell<*mut u8>,
+    // Number of allocated values of a zero-sized `T`, which live outside of
+    // the blocks.
+    zst_count: Cell<usize>,
+    // Only brought up to date before the used bytes can go down or when
+    // leaving a block.
+    high_water_mark: Cell<usize>,
+    // Upper bound on `bytes`, `usize::MAX` when unlimited.
+    limit: usize,
+    // Called instead of `ArenaAllocError::handle` by infallible allocations.
+    error_handler: Option<fn(ArenaAllocError) -> !>,
+    // Whether `reset` hands the pages of the used blocks back to the OS.
+    #[cfg(all(feature = "madvise", unix))]
+    discard_on_reset: bool,
+    observer: Option<Box<dyn ArenaObserver + Send>>,
+    // A fresh block not yet reported to the observer. Reporting waits until
+    // the allocation that needed it is done.
+    pending_block: Cell<Option<(*const u8, usize)>>,
+    // Set while `alloc_with` or `alloc_many` run their closure, whose results
+    // go into the slots at the bump pointer, so no other allocation may take
+    // those slots.
+    filling: Cell<bool>,
+    _marker: PhantomData<T>,
+}
+
+/// Marks the arena as filling the slots at the bump pointer, which are only
+/// counted as allocated by `finish`. If filling them panics instead, the
+/// `len` values written so far are dropped.
+struct Filling<'a, T, A: GlobalAlloc> {
+    internal: &'a Internal<T, A>,
+    ptr: *mut T,
+    len: usize,
+}
+
+impl<'a, T, A: GlobalAlloc> Filling<'a, T, A> {
+    fn new(internal: &'a Internal<T, A>, ptr: *mut T) -> Self {
+        internal.filling.set(true);
+        Self {
+            internal,
+            ptr,
+            len: 0,
+        }
+    }
+
+    /// Counts the first `len` slots, which must have been written, as
+    /// allocated.
+    unsafe fn finish(self, len: usize) {
+        let Self { internal, ptr, .. } = self;
+        mem::forget(self);
+        internal.filling.set(false);
+        internal.advance(len);
+        internal.observe(ptr.cast(), len * mem::size_of::<T>());
+    }
+}
+
+impl<T, A: GlobalAlloc> Drop for Filling<'_, T, A> {
+    fn drop(&mut self) {
+        self.internal.filling.set(false);
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr, self.len)) };
+    }
+}
+
+// The pointers all point into blocks owned by the arena, so sending it to
+// another thread sends the values along. It is never `Sync`: allocating
+// through `&Arena` updates the `Cell`s without synchronization.
+unsafe impl<T: Send, A: GlobalAlloc + Send> Send for Internal<T, A> {}
+
+impl<T, A: GlobalAlloc> Internal<T, A> {
+    fn new(block_size: usize, max_block_size: usize, backing: A) -> Self {
+        Self {
+            backing,
+            blocks: RefCell::new(Vec::new()),
+            current: Cell::new(0),
+            block_size: Cell::new(block_size),
+            max_block_size,
+            bytes: Cell::new(0),
+            alloc_bytes_remaining: Cell::new(0),
+            alloc_ptr: Cell::new(ptr::null_mut()),
+            current_start: Cell::new(ptr::null_mut()),
+            zst_count: Cell::new(0),
+            high_water_mark: Cell::new(0),
+            limit: usize::MAX,
+            error_handler: None,
+            #[cfg(all(feature = "madvise", unix))]
+            discard_on_reset: false,
+            observer: None,
+            pending_block: Cell::new(None),
+            filling: Cell::new(false),
+            _marker: PhantomData,
+        }
+    }
+
+    unsafe fn alloc(&self, data: T) -> *mut T {
+        match self.try_alloc(data) {
+            Ok(x) => x,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    unsafe fn try_alloc(&self, data: T) -> Result<*mut T, ArenaAllocError> {
+        self.try_alloc_sized(data, self.block_size.get())
+    }
+
+    #[inline(always)]
+    unsafe fn try_alloc_sized(
+        &self,
+        data: T,
+        block_size: usize,
+    ) -> Result<*mut T, ArenaAllocError> {
+        self.make_room_sized(1, block_size)?;
+        let ptr = self.push(data);
+        self.observe(ptr.cast(), mem::size_of::<T>());
+        Ok(ptr)
+    }
+
+    /// Writes the result of `f` straight into the slot at the bump pointer,
+    /// which lets the optimizer construct large values in place.
+    #[inline(always)]
+    unsafe fn try_alloc_with<F: FnOnce() -> T>(&self, f: F) -> Result<*mut T, ArenaAllocError> {
+        self.make_room(1)?;
+        let ptr = self.next_ptr();
+        let filling = Filling::new(self, ptr);
+        ptr::write(ptr, f());
+        filling.finish(1);
+        Ok(ptr)
+    }
+
+    /// Writes `f(i)` straight into the `i`-th of `len` slots at the bump
+    /// pointer, in a fresh block if the current one has no room for all of
+    /// them.
+    unsafe fn try_alloc_many<F: FnMut(usize) -> T>(
+        &self,
+        len: usize,
+        mut f: F,
+    ) -> Result<*mut [T], ArenaAllocError> {
+        self.make_room(len)?;
+        let ptr = self.next_ptr();
+        let mut filling = Filling::new(self, ptr);
+        for i in 0..len {
+            ptr::write(ptr.add(i), f(i));
+            filling.len += 1;
+        }
+        filling.finish(len);
+        Ok(ptr::slice_from_raw_parts_mut(ptr, len))
+    }
+
+    unsafe fn try_alloc_slice_copy(&self, src: &[T]) -> Result<*mut [T], ArenaAllocError>
+    where
+        T: Copy,
+    {
+        self.make_room(src.len())?;
+        let ptr = self.next_ptr();
+        ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+        self.advance(src.len());
+        self.observe(ptr.cast(), mem::size_of_val(src));
+        Ok(ptr::slice_from_raw_parts_mut(ptr, src.len()))
+    }
+
+    /// The clones are written in place, so `Clone` must not allocate from
+    /// this arena.
+    unsafe fn try_alloc_slice_clone(&self, src: &[T]) -> Result<*mut [T], ArenaAllocError>
+    where
+        T: Clone,
+    {
+        self.make_room(src.len())?;
+        let ptr = self.next_ptr();
+        for x in src {
+            self.push(x.clone());
+        }
+        self.observe(ptr.cast(), mem::size_of_val(src));
+        Ok(ptr::slice_from_raw_parts_mut(ptr, src.len()))
+    }
+
+    /// Writes up to `len` items of `iter` in place, so the iterator must not
+    /// allocate from this arena.
+    unsafe fn try_alloc_iter<I: Iterator<Item = T>>(
+        &self,
+        iter: I,
+        len: usize,
+    ) -> Result<*mut [T], ArenaAllocError> {
+        self.make_room(len)?;
+        let ptr = self.next_ptr();
+        let mut written = 0;
+        for x in iter.take(len) {
+            self.push(x);
+            written += 1;
+        }
+        sanitizer::mark_unaddressable(self.alloc_ptr.get(), (len - written) * mem::size_of::<T>());
+        self.observe(ptr.cast(), written * mem::size_of::<T>());
+        Ok(ptr::slice_from_raw_parts_mut(ptr, written))
+    }
+
+    unsafe fn try_alloc_vec(&self, mut vec: Vec<T>) -> Result<*mut [T], ArenaAllocError> {
+        self.make_room(vec.len())?;
+        let len = vec.len();
+        let ptr = self.next_ptr();
+        ptr::copy_nonoverlapping(vec.as_ptr(), ptr, len);
+        vec.set_len(0);
+        self.advance(len);
+        self.observe(ptr.cast(), len * mem::size_of::<T>());
+        Ok(ptr::slice_from_raw_parts_mut(ptr, len))
+    }
+
+    #[inline]
+    fn observe(&self, ptr: *const u8, size: usize) {
+        if let Some(observer) = &self.observer {
+            self.report_block(observer.as_ref());
+            observer.on_alloc(ptr, size);
+        }
+    }
+
+    fn report_block(&self, observer: &dyn ArenaObserver) {
+        if let Some((ptr, size)) = self.pending_block.take() {
+            observer.on_block(ptr, size);
+        }
+    }
+
+    /// Makes sure the current block has room for `len` more elements.
+    fn make_room(&self, len: usize) -> Result<(), ArenaAllocError> {
+        self.make_room_sized(len, self.block_size.get())
+    }
+
+    #[inline(always)]
+    fn make_room_sized(&self, len: usize, block_size: usize) -> Result<(), ArenaAllocError> {
+        assert!(
+            !self.filling.get(),
+            "cannot allocate from an arena inside its `alloc_with` or `alloc_many`"
+        );
+        let layout = Layout::array::<T>(len).map_err(|_| ArenaAllocError::CapacityOverflow)?;
+        if layout.size() > self.alloc_bytes_remaining.get() {
+            self.new_block(layout, block_size)?;
+        }
+        unsafe { sanitizer::mark_addressable(self.alloc_ptr.get(), layout.size()) };
+        Ok(())
+    }
+
+    /// Returns where the next element goes. Zero-sized types get a dangling
+    /// pointer and never touch the blocks.
+    fn next_ptr(&self) -> *mut T {
+        if mem::size_of::<T>() == 0 {
+            return NonNull::dangling().as_ptr();
+        }
+        self.alloc_ptr.get().cast::<T>()
+    }
+
+    /// Writes `data` into the current block, which must have room for it.
+    unsafe fn push(&self, data: T) -> *mut T {
+        let ptr = self.next_ptr();
+        ptr::write(ptr, data);
+        self.advance(1);
+        ptr
+    }
+
+    /// Returns the number of elements in the current block.
+    fn current_len(&self) -> usize {
+        if mem::size_of::<T>() == 0 {
+            return 0;
+        }
+        (self.alloc_ptr.get().addr() - self.current_start.get().addr()) / mem::size_of::<T>()
+    }
+
+    /// Stores the length of the current block before it is left or walked.
+    fn sync_current(&self, blocks: &mut [Block]) {
+        if let Some(block) = blocks.get_mut(self.current.get()) {
+            block.count_of_elements = self.current_len();
+        }
+    }
+
+    /// Accounts for `len` elements just written at the bump pointer.
+    unsafe fn advance(&self, len: usize) {
+        if mem::size_of::<T>() == 0 {
+            self.zst_count.set(self.zst_count.get() + len);
+            return;
+        }
+        // Each element is an allocation of its own for Valgrind.
+        #[cfg(feature = "valgrind")]
+        for i in 0..len {
+            let ptr = self.alloc_ptr.get().add(i * mem::size_of::<T>());
+            sanitizer::malloclike(ptr, mem::size_of::<T>());
+        }
+        self.advance_ptr(len);
+    }
+
+    unsafe fn advance_ptr(&self, len: usize) {
+        let size = mem::size_of::<T>() * len;
+        self.alloc_bytes_remaining
+            .set(self.alloc_bytes_remaining.get() - size);
+        self.alloc_ptr.set(self.alloc_ptr.get().add(size));
+    }
+
+    /// Moves to a block that can hold `layout`, reusing the next spare block
+    /// when it is large enough.
+    #[cold]
+    fn new_block(&self, layout: Layout, block_size: usize) -> Result<(), ArenaAllocError> {
+        let mut blocks = self.blocks.borrow_mut();
+        let next = if blocks.is_empty() {
+            0
+        } else {
+            self.current.get() + 1
+        };
+        self.sync_current(&mut blocks);
+        self.record_high_water(&blocks);
+        let reusable = blocks
+            .get(next)
+            .is_some_and(|b| b.raw.size() >= layout.size());
+        if !reusable {
+            let size = self.budget(layout.size(), cmp::max(layout.size(), block_size))?;
+            let raw = RawBlock::try_new_in(size, layout.align(), &self.backing)?;
+            unsafe { sanitizer::mark_unaddressable(raw.ptr(), raw.size()) };
+            self.bytes.set(self.bytes.get() + raw.size());
+            self.pending_block.set(Some((raw.ptr(), raw.size())));
+            self.grow_block_size();
+            blocks.insert(
+                next,
+                Block {
+                    raw,
+                    count_of_elements: 0,
+                },
+            );
+        }
+        self.current.set(next);
+        self.rewind_to_current(&blocks);
+        Ok(())
+    }
+
+    /// Reports the failure of an infallible allocation.
+    #[cold]
+    fn fail(&self, e: ArenaAllocError) -> ! {
+        match self.error_handler {
+            Some(handler) => handler(e),
+            None => e.handle(),
+        }
+    }
+
+    /// Returns the size of the next block: `wanted` bytes, cut down to what
+    /// is left of the limit but never below `needed`.
+    fn budget(&self, needed: usize, wanted: usize) -> Result<usize, ArenaAllocError> {
+        let left = self.limit - self.bytes.get();
+        if needed > left {
+            return Err(ArenaAllocError::LimitExceeded { limit: self.limit });
+        }
+        Ok(cmp::min(wanted, left))
+    }
+
+    fn grow_block_size(&self) {
+        self.block_size.set(cmp::min(
+            self.block_size.get().saturating_mul(2),
+            self.max_block_size,
+        ));
+    }
+
+    /// Makes sure that `additional` more elements fit in the current block and
+    /// the spare blocks after it.
+    fn reserve(&self, additional: usize) -> Result<(), ArenaAllocError> {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            return Ok(());
+        }
+        let mut blocks = self.blocks.borrow_mut();
+        let mut free = self.alloc_bytes_remaining.get() / size;
+        for block in blocks.iter().skip(self.current.get() + 1) {
+            free += block.raw.size() / size;
+        }
+        if free >= additional {
+            return Ok(());
+        }
+
+        let layout =
+            Layout::array::<T>(additional - free).map_err(|_| ArenaAllocError::CapacityOverflow)?;
+        let size = self.budget(
+            layout.size(),
+            cmp::max(layout.size(), self.block_size.get()),
+        )?;
+        let raw = RawBlock::try_new_in(size, layout.align(), &self.backing)?;
+        unsafe { sanitizer::mark_unaddressable(raw.ptr(), raw.size()) };
+        self.bytes.set(self.bytes.get() + raw.size());
+        let (ptr, size) = (raw.ptr(), raw.size());
+        blocks.push(Block {
+            raw,
+            count_of_elements: 0,
+        });
+        if blocks.len() == 1 {
+            self.rewind_to_current(&blocks);
+        }
+        drop(blocks);
+        if let Some(observer) = &self.observer {
+            observer.on_block(ptr, size);
+        }
+        Ok(())
+    }
+
+    /// Points the bump pointer at the start of the current block.
+    fn rewind_to_current(&self, blocks: &[Block]) {
+        let block = &blocks[self.current.get()];
+        self.current_start.set(block.raw.ptr());
+        self.alloc_ptr.set(block.raw.ptr());
+        self.alloc_bytes_remaining.set(block.raw.size());
+    }
+
+    fn reset(&mut self) {
+        self.record_high_water(&self.blocks.borrow());
+        self.drop_elements();
+        telemetry::reset(self.bytes.get());
+        #[cfg(all(feature = "madvise", unix))]
+        if self.discard_on_reset {
+            let blocks = self.blocks.get_mut();
+            let used = cmp::min(self.current.get() + 1, blocks.len());
+            for block in &blocks[..used] {
+                unsafe { block.raw.discard() };
+            }
+        }
+        self.current.set(0);
+        let blocks = self.blocks.borrow();
+        if !blocks.is_empty() {
+            self.rewind_to_current(&blocks);
+        }
+    }
+
+    fn checkpoint(&self) -> Marker {
+        if mem::size_of::<T>() == 0 {
+            return Marker {
+                block: 0,
+                count: self.zst_count.get(),
+            };
+        }
+        Marker {
+            block: self.current.get(),
+            count: self.current_len(),
+        }
+    }
+
+    fn rewind(&self, marker: Marker) {
+        if mem::size_of::<T>() == 0 {
+            assert!(
+                marker.count <= self.zst_count.get(),
+                "marker is ahead of the arena"
+            );
+            unsafe { self.truncate_zst(marker.count) };
+            return;
+        }
+        let mut blocks = self.blocks.borrow_mut();
+        if blocks.is_empty() {
+            return;
+        }
+        let current = self.current.get();
+        assert!(
+            marker.block < current
+                || (marker.block == current && marker.count <= self.current_len()),
+            "marker is ahead of the arena"
+        );
+        self.sync_current(&mut blocks);
+        self.record_high_water(&blocks);
+        unsafe {
+            for block in blocks[marker.block + 1..=current].iter_mut() {
+                block.truncate::<T>(0);
+            }
+            blocks[marker.block].truncate::<T>(marker.count);
+        }
+        self.current.set(marker.block);
+        self.rewind_to_current(&blocks);
+        unsafe { self.advance_ptr(marker.count) };
+    }
+
+    fn contains(&self, ptr: *const T) -> bool {
+        if mem::size_of::<T>() == 0 {
+            return false;
+        }
+        let blocks = self.blocks.borrow();
+        let current = self.current.get();
+        let addr = ptr.addr();
+        blocks
+            .iter()
+            .take(current + 1)
+            .enumerate()
+            .any(|(i, block)| {
+                let len = if i == current {
+                    self.current_len()
+                } else {
+                    block.count_of_elements
+                };
+                let start = block.raw.ptr().addr();
+                addr >= start && addr < start + len * mem::size_of::<T>()
+            })
+    }
+
+    /// Returns the block of `ptr` and its position there. The most recent
+    /// blocks are searched first.
+    fn handle_of(&self, ptr: *const T) -> Option<Handle32> {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            return None;
+        }
+        let blocks = self.blocks.borrow();
+        let current = self.current.get();
+        let (block, index) = (0..cmp::min(current + 1, blocks.len()))
+            .rev()
+            .find_map(|i| {
+                let offset = ptr.addr().checked_sub(blocks[i].raw.ptr().addr())?;
+                let index = offset / size;
+                (offset % size == 0 && index < self.block_len(&blocks, i)).then_some((i, index))
+            })?;
+        Handle32::new(block, index)
+    }
+
+    fn resolve(&self, handle: Handle32) -> Option<*mut T> {
+        if mem::size_of::<T>() == 0 {
+            return None;
+        }
+        let blocks = self.blocks.borrow();
+        let (block, index) = (handle.block(), handle.index());
+        if block > self.current.get() || block >= blocks.len() {
+            return None;
+        }
+        (index < self.block_len(&blocks, block))
+            .then(|| unsafe { blocks[block].raw.ptr().cast::<T>().add(index) })
+    }
+
+    /// Returns the value allocated `index`-th, walking the block counts.
+    fn get(&self, mut index: usize) -> Option<*mut T> {
+        if mem::size_of::<T>() == 0 {
+            return (index < self.zst_count.get()).then(|| NonNull::dangling().as_ptr());
+        }
+        let blocks = self.blocks.borrow();
+        for i in 0..cmp::min(self.current.get() + 1, blocks.len()) {
+            let len = self.block_len(&blocks, i);
+            if index < len {
+                return Some(unsafe { blocks[i].raw.ptr().cast::<T>().add(index) });
+            }
+            index -= len;
+        }
+        None
+    }
+
+    fn cursor(&self, marker: Marker) -> Cursor {
+        let blocks = self.blocks.borrow();
+        let before: usize = if mem::size_of::<T>() == 0 {
+            0
+        } else {
+            assert!(
+                blocks.is_empty()
+                    || marker.block < self.current.get()
+                    || (marker.block == self.current.get() && marker.count <= self.current_len()),
+                "marker is ahead of the arena"
+            );
+            blocks[..marker.block.min(blocks.len())]
+                .iter()
+                .map(|b| b.count_of_elements)
+                .sum()
+        };
+        Cursor {
+            block: marker.block,
+            index: marker.count,
+            offset: (before + marker.count) * mem::size_of::<T>(),
+        }
+    }
+
+    /// Moves `cursor` past the value it is at, returning it, if there is one.
+    fn step(&self, cursor: &mut Cursor) -> Option<*mut T> {
+        if mem::size_of::<T>() == 0 {
+            if cursor.index >= self.zst_count.get() {
+                return None;
+            }
+            cursor.index += 1;
+            return Some(NonNull::dangling().as_ptr());
+        }
+        let blocks = self.blocks.borrow();
+        let current = self.current.get();
+        while cursor.block <= current && cursor.block < blocks.len() {
+            if cursor.index < self.block_len(&blocks, cursor.block) {
+                let ptr = unsafe { blocks[cursor.block].raw.ptr().cast::<T>().add(cursor.index) };
+                cursor.index += 1;
+                cursor.offset += mem::size_of::<T>();
+                return Some(ptr);
+            }
+            if cursor.block == current {
+                break;
+            }
+            cursor.block += 1;
+            cursor.index = 0;
+        }
+        None
+    }
+
+    /// Returns the number of elements in block `i`, which is at most the
+    /// current one.
+    fn block_len(&self, blocks: &[Block], i: usize) -> usize {
+        if i == self.current.get() {
+            self.current_len()
+        } else {
+            blocks[i].count_of_elements
+        }
+    }
+
+    fn dealloc_last(&self, ptr: *const T) -> bool {
+        if mem::size_of::<T>() == 0 {
+            let count = self.zst_count.get();
+            if count == 0 {
+                return false;
+            }
+            self.rewind(Marker {
+                block: 0,
+                count: count - 1,
+            });
+            return true;
+        }
+        let len = self.current_len();
+        if len == 0 || ptr != self.alloc_ptr.get().cast::<T>().wrapping_sub(1) {
+            return false;
+        }
+        self.rewind(Marker {
+            block: self.current.get(),
+            count: len - 1,
+        });
+        true
+    }
+
+    unsafe fn truncate_zst(&self, len: usize) {
+        let count = self.zst_count.replace(len);
+        if mem::needs_drop::<T>() {
+            let ptr = NonNull::<T>::dangling().as_ptr();
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(ptr, count - len));
+        }
+    }
+
+    /// Returns the bytes taken up by values, given the synced block list.
+    fn used_bytes(&self, blocks: &[Block]) -> usize {
+        let current = cmp::min(self.current.get(), blocks.len());
+        let filled: usize = blocks[..current].iter().map(|b| b.count_of_elements).sum();
+        (filled + self.current_len()) * mem::size_of::<T>()
+    }
+
+    fn record_high_water(&self, blocks: &[Block]) {
+        let used = self.used_bytes(blocks);
+        if used > self.high_water_mark.get() {
+            self.high_water_mark.set(used);
+        }
+    }
+
+    fn stats(&self) -> ArenaStats {
+        let blocks = self.blocks.borrow();
+        let current = cmp::min(self.current.get(), blocks.len());
+        let used = self.used_bytes(&blocks);
+        let wasted_bytes = blocks[..current]
+            .iter()
+            .map(|b| b.raw.size() - b.count_of_elements * mem::size_of::<T>())
+            .sum();
+        ArenaStats {
+            blocks: blocks.len(),
+            bytes_reserved: self.bytes.get(),
+            bytes_used: used,
+            wasted_bytes,
+            high_water_mark: cmp::max(self.high_water_mark.get(), used),
+        }
+    }
+
+    fn allocated_since(&self, marker: Marker) -> AllocatedSince {
+        if mem::size_of::<T>() == 0 {
+            let zst_count = self.zst_count.get();
+            assert!(marker.count <= zst_count, "marker is ahead of the arena");
+            return AllocatedSince {
+                elements: zst_count - marker.count,
+                ..AllocatedSince::default()
+            };
+        }
+        let blocks = self.blocks.borrow();
+        if blocks.is_empty() {
+            return AllocatedSince::default();
+        }
+        let current = self.current.get();
+        assert!(
+            marker.block < current
+                || (marker.block == current && marker.count <= self.current_len()),
+            "marker is ahead of the arena"
+        );
+        // Blocks before the current one have their length synced.
+        let count = |i: usize| {
+            if i == current {
+                self.current_len()
+            } else {
+                blocks[i].count_of_elements
+            }
+        };
+        let elements = (marker.block..=current).map(count).sum::<usize>() - marker.count;
+        AllocatedSince {
+            elements,
+            bytes_used: elements * mem::size_of::<T>(),
+            blocks: current - marker.block,
+            bytes_reserved: blocks[marker.block + 1..=current]
+                .iter()
+                .map(|b| b.raw.size())
+                .sum(),
+            wasted_bytes: (marker.block..current)
+                .map(|i| blocks[i].raw.size() - count(i) * mem::size_of::<T>())
+                .sum(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        let blocks = self.blocks.borrow();
+        let current = cmp::min(self.current.get(), blocks.len());
+        let filled: usize = blocks[..current].iter().map(|b| b.count_of_elements).sum();
+        filled + self.current_len() + self.zst_count.get()
+    }
+
+    fn raw_iter(&mut self) -> RawIter<'_, T> {
+        self.sync_current(&mut self.blocks.borrow_mut());
+        let blocks = self.blocks.get_mut();
+        let used = cmp::min(self.current.get() + 1, blocks.len());
+        RawIter {
+            blocks: blocks[..used].iter(),
+            ptr: ptr::null_mut(),
+            len: 0,
+            zst_len: self.zst_count.get(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the values of each block as a slice, in allocation order.
+    fn slices(&mut self) -> Vec<&mut [T]> {
+        if mem::size_of::<T>() == 0 {
+            let len = self.zst_count.get();
+            return alloc::vec![unsafe {
+                slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), len)
+            }];
+        }
+        self.sync_current(&mut self.blocks.borrow_mut());
+        let blocks = self.blocks.get_mut();
+        let used = cmp::min(self.current.get() + 1, blocks.len());
+        blocks[..used]
+            .iter()
+            .map(|b| unsafe {
+                slice::from_raw_parts_mut(b.raw.ptr().cast::<T>(), b.count_of_elements)
+            })
+            .collect()
+    }
+
+    /// Moves every value out of the arena, leaving it empty.
+    fn take_all(&mut self) -> IntoIter<T, A>
+    where
+        A: Clone,
+    {
+        self.sync_current(&mut self.blocks.borrow_mut());
+        let blocks = mem::take(self.blocks.get_mut());
+        self.current.set(0);
+        self.alloc_ptr.set(ptr::null_mut());
+        self.current_start.set(ptr::null_mut());
+        self.alloc_bytes_remaining.set(0);
+        IntoIter {
+            blocks: blocks.into_iter(),
+            block: None,
+            index: 0,
+            zst_len: self.zst_count.replace(0),
+            backing: self.backing.clone(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves every value into one block of exactly their size and releases
+    /// all other blocks, spare ones included.
+    fn compact(&mut self) -> Result<(), ArenaAllocError> {
+        let size = mem::size_of::<T>();
+        self.sync_current(&mut self.blocks.borrow_mut());
+        self.record_high_water(&self.blocks.borrow());
+        let len = self.len() - self.zst_count.get();
+        let blocks = self.blocks.get_mut();
+        if size == 0 || blocks.len() == 1 && blocks[0].raw.size() == len * size {
+            return Ok(());
+        }
+        let raw = if len > 0 {
+            let layout = Layout::array::<T>(len).map_err(|_| ArenaAllocError::CapacityOverflow)?;
+            Some(RawBlock::try_new_in(
+                layout.size(),
+                layout.align(),
+                &self.backing,
+            )?)
+        } else {
+            None
+        };
+
+        // The values are moved bitwise and the old blocks released without
+        // dropping anything. Spare blocks hold no values.
+        let mut moved = 0;
+        for block in mem::take(blocks) {
+            let count = block.count_of_elements;
+            unsafe {
+                if let Some(raw) = &raw {
+                    let dst = raw.ptr().cast::<T>().add(moved);
+                    ptr::copy_nonoverlapping(block.raw.ptr().cast::<T>(), dst, count);
+                }
+                #[cfg(feature = "valgrind")]
+                for i in 0..count {
+                    sanitizer::freelike(block.raw.ptr().add(i * size));
+                    sanitizer::malloclike(
+                        raw.as_ref().unwrap().ptr().add((moved + i) * size),
+                        size,
+                    );
+                }
+                block.raw.free(&self.backing);
+            }
+            moved += count;
+        }
+        debug_assert_eq!(moved, len);
+
+        self.current.set(0);
+        self.pending_block.set(None);
+        self.alloc_ptr.set(ptr::null_mut());
+        self.current_start.set(ptr::null_mut());
+        self.alloc_bytes_remaining.set(0);
+        self.bytes.set(0);
+        if let Some(raw) = raw {
+            self.bytes.set(raw.size());
+            if let Some(observer) = &self.observer {
+                observer.on_block(raw.ptr(), raw.size());
+            }
+            self.blocks.get_mut().push(Block {
+                raw,
+                count_of_elements: 0,
+            });
+            self.rewind_to_current(&self.blocks.borrow());
+            unsafe { self.advance_ptr(len) };
+        }
+        Ok(())
+    }
+
+    fn drop_elements(&mut self) {
+        unsafe { self.truncate_zst(0) };
+        let mut blocks = self.blocks.borrow_mut();
+        self.sync_current(&mut blocks);
+        for block in blocks.iter_mut() {
+            unsafe { block.truncate::<T>(0) };
+        }
+    }
+}
+
+impl<T, A: GlobalAlloc> Internal<T, A> {
+    /// Drops the values and releases the blocks. The values are only dropped,
+    /// never read otherwise, which is what the eyepatch below relies on.
+    fn release(&mut self) {
+        self.drop_elements();
+        for block in self.blocks.get_mut().drain(..) {
+            unsafe { block.raw.free(&self.backing) };
+        }
+        telemetry::dropped(self.bytes.get());
+    }
+}
+
+// SAFETY: `release` does not touch a `T` other than to drop it, so whatever
+// the values borrow may already be gone when the arena is dropped, as long
+// as dropping a `T` doesn't use it. `PhantomData<T>` tells dropck that
+// values are dropped here, so a `T` with a `Drop` impl of its own still gets
+// its borrows checked.
+//
+// This lets values point at each other, e.g. the nodes of a graph holding
+// `&'a Node<'a>` where `'a` is the borrow of the arena itself.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T, A: GlobalAlloc> Drop for Internal<T, A> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T, A: GlobalAlloc> Drop for Internal<T, A> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// A typed arena: every value allocated from it is dropped when the arena is.
+///
+/// Values never move once allocated, so the returned references stay valid
+/// for as long as the arena is borrowed. `reset` and `rewind` take
+/// `&mut self`, so no reference can survive them:
+///
+/// ```compile_fail
+/// let mut arena = rusty_arena::arena::Arena::new();
+/// let x = arena.alloc(1);
+/// arena.reset();
+/// *x += 1;
+/// ```
+///
+/// With the `nightly` feature, values may also borrow from the arena they
+/// live in, e.g. graph nodes that point at each other, as long as their type
+/// has no `Drop` impl that could observe the borrow.
+pub struct Arena<T, A: GlobalAlloc = Global> {
+    internal: Internal<T, A>,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Self::with_block_growth(BLOCK_SIZE, MAX_BLOCK_SIZE)
+    }
+
+    /// Creates an arena with room for at least `capacity` values.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let arena = Self::new();
+        arena.reserve(capacity);
+        arena
+    }
+
+    /// Creates an arena that requests blocks of `block_size` bytes from the
+    /// system allocator. Values larger than that get a block of their own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    pub fn with_block_size(block_size: usize) -> Self {
+        Self::with_block_growth(block_size, block_size)
+    }
+
+    /// Creates an arena whose first block has `initial` bytes and where each
+    /// following block doubles in size until it reaches `max`.
+    ///
+    /// [`new`](Self::new) starts at 4 KiB and grows up to 2 MiB.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero or larger than `max`.
+    pub fn with_block_growth(initial: usize, max: usize) -> Self {
+        Self::with_block_growth_in(initial, max, Global)
+    }
+
+    /// Creates an arena that never holds more than `limit` bytes of blocks.
+    /// Allocations past it panic, or fail with
+    /// [`ArenaAllocError::LimitExceeded`] through [`try_alloc`](Self::try_alloc).
+    pub fn with_limit(limit: usize) -> Self {
+        let mut arena = Self::new();
+        arena.internal.limit = limit;
+        arena
+    }
+}
+
+impl<T, A: GlobalAlloc> Arena<T, A> {
+    /// Creates an arena that takes its blocks from `backing` instead of the
+    /// global allocator.
+    pub fn new_in(backing: A) -> Self {
+        Self::with_block_growth_in(BLOCK_SIZE, MAX_BLOCK_SIZE, backing)
+    }
+
+    /// Like [`with_block_growth`](Arena::with_block_growth), with blocks
+    /// from `backing`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero or larger than `max`.
+    pub fn with_block_growth_in(initial: usize, max: usize, backing: A) -> Self {
+        assert!(initial > 0, "block size must be non-zero");
+        assert!(initial <= max, "initial block size exceeds the maximum");
+        Self {
+            internal: Internal::new(initial, max, backing),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, data: T) -> &mut T {
+        unsafe { &mut *self.internal.alloc(data) }
+    }
+
+    /// Moves `data` into the arena and returns its position in allocation
+    /// order as an [`Idx`], which doesn't borrow the arena.
+    ///
+    /// Values that link to each other by index rather than by reference have
+    /// no lifetime parameter. Once the structure is built,
+    /// [`freeze`](Self::freeze) turns the arena into an owned
+    /// [`FrozenArena`] that is indexed by the same handles and can be kept in
+    /// a struct field or sent to another thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena already holds `u32::MAX` values.
+    pub fn alloc_idx(&self, data: T) -> Idx<T> {
+        let idx = self.next_idx();
+        self.alloc(data);
+        idx
+    }
+
+    /// Returns the index the next allocation will get, so that a value can
+    /// refer to itself.
+    pub fn next_idx(&self) -> Idx<T> {
+        Idx::from_u32(u32::try_from(self.len()).expect("too many values in the arena"))
+    }
+
+    /// Allocates blocks up front so that the next `additional` calls to
+    /// [`alloc`](Self::alloc) do not need to go to the system allocator.
+    ///
+    /// Slices allocated later still need a contiguous run of one block and
+    /// may not be able to use the reserved space.
+    pub fn reserve(&self, additional: usize) {
+        if let Err(e) = self.internal.reserve(additional) {
+            self.internal.fail(e);
+        }
+    }
+
+    /// Moves every value into a single block of exactly their size, and
+    /// releases the other blocks, including the spare ones kept by
+    /// [`reset`](Self::reset).
+    ///
+    /// An arena filled bit by bit over a long time ends up spread over many
+    /// blocks with unused tails. Compacting it gives the values back their
+    /// locality and the unused memory back to the allocator. It takes
+    /// `&mut self`, so no reference into the arena is left to be invalidated.
+    /// The next allocation starts a new block.
+    pub fn compact(&mut self) {
+        if let Err(e) = self.internal.compact() {
+            self.internal.fail(e);
+        }
+    }
+
+    /// Like [`compact`](Self::compact), but returns an error, leaving the
+    /// arena untouched, when the new block cannot be obtained.
+    pub fn try_compact(&mut self) -> Result<(), ArenaAllocError> {
+        self.internal.compact()
+    }
+
+    /// Like [`alloc`](Self::alloc), but returns an error instead of aborting
+    /// when a new block cannot be obtained.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc(&self, data: T) -> Result<&mut T, ArenaAllocError> {
+        unsafe { Ok(&mut *self.internal.try_alloc(data)?) }
+    }
+
+    /// Copies `src` into a contiguous run of arena memory.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy(&self, src: &[T]) -> &mut [T]
+    where
+        T: Copy,
+    {
+        if src.is_empty() {
+            return &mut [];
+        }
+        match unsafe { self.internal.try_alloc_slice_copy(src) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Clones every element of `src` into a contiguous run of arena memory.
+    ///
+    /// The clones are buffered first, so `Clone` implementations are free to
+    /// allocate from this arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_clone(&self, src: &[T]) -> &mut [T]
+    where
+        T: Clone,
+    {
+        if src.is_empty() {
+            return &mut [];
+        }
+        match unsafe { self.internal.try_alloc_vec(src.to_vec()) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Moves every item of `iter` into a contiguous run of arena memory.
+    ///
+    /// The items are buffered first, so the iterator is free to allocate
+    /// from this arena while it runs.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_from_iter<I: IntoIterator<Item = T>>(&self, iter: I) -> &mut [T] {
+        let vec: Vec<T> = iter.into_iter().collect();
+        if vec.is_empty() {
+            return &mut [];
+        }
+        match unsafe { self.internal.try_alloc_vec(vec) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Sets aside `n` contiguous slots, in a fresh block if the current one
+    /// has no room for all of them, and writes `f(i)` straight into the
+    /// `i`-th one, e.g. to set up a table of nodes at once.
+    ///
+    /// If `f` panics, the values already written are dropped and nothing is
+    /// allocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` allocates from this arena, since its slots are already
+    /// set aside.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_many<F: FnMut(usize) -> T>(&self, n: usize, f: F) -> &mut [T] {
+        if n == 0 {
+            return &mut [];
+        }
+        match unsafe { self.internal.try_alloc_many(n, f) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Another name for [`alloc_many`](Self::alloc_many), matching
+    /// [`Bump::alloc_slice_fill_with`](crate::bump::Bump::alloc_slice_fill_with).
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_fill_with<F: FnMut(usize) -> T>(&self, len: usize, f: F) -> &mut [T] {
+        self.alloc_many(len, f)
+    }
+
+    /// Allocates space for a `T` and writes the result of `f` straight into
+    /// it, which lets the optimizer construct large values in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` allocates from this arena, since its slot is already
+    /// set aside. Child nodes have to be allocated before calling this.
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with<F: FnOnce() -> T>(&self, f: F) -> &mut T {
+        match unsafe { self.internal.try_alloc_with(f) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Like [`alloc`](Self::alloc), for callers that hold the arena
+    /// exclusively.
+    pub fn alloc_mut(&mut self, data: T) -> &mut T {
+        unsafe { &mut *self.internal.alloc(data) }
+    }
+
+    /// Like [`alloc_slice_clone`](Self::alloc_slice_clone), but clones
+    /// straight into the arena without buffering, since nothing else can
+    /// allocate while the arena is borrowed exclusively.
+    pub fn alloc_slice_clone_mut(&mut self, src: &[T]) -> &mut [T]
+    where
+        T: Clone,
+    {
+        if src.is_empty() {
+            return &mut [];
+        }
+        match unsafe { self.internal.try_alloc_slice_clone(src) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Like [`alloc_many`](Self::alloc_many), for callers that hold the
+    /// arena exclusively.
+    pub fn alloc_slice_fill_with_mut<F: FnMut(usize) -> T>(
+        &mut self,
+        len: usize,
+        f: F,
+    ) -> &mut [T] {
+        self.alloc_many(len, f)
+    }
+
+    /// Like [`alloc_from_iter`](Self::alloc_from_iter), but writes the items
+    /// straight into the arena when the iterator reports its exact length.
+    pub fn alloc_from_iter_mut<I: IntoIterator<Item = T>>(&mut self, iter: I) -> &mut [T] {
+        let iter = iter.into_iter();
+        let result = match iter.size_hint() {
+            (0, Some(0)) => return &mut [],
+            (min, Some(max)) if min == max => unsafe { self.internal.try_alloc_iter(iter, min) },
+            _ => {
+                let vec: Vec<T> = iter.collect();
+                if vec.is_empty() {
+                    return &mut [];
+                }
+                unsafe { self.internal.try_alloc_vec(vec) }
+            }
+        };
+        match result {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    /// Drops every element but keeps the blocks, so that the arena can be
+    /// refilled without going back to the system allocator.
+    pub fn reset(&mut self) {
+        self.internal.reset();
+    }
+
+    /// Returns the current position, to be passed to [`rewind`](Self::rewind)
+    /// later.
+    pub fn checkpoint(&self) -> Marker {
+        self.internal.checkpoint()
+    }
+
+    /// Drops everything allocated after `marker` was taken and makes that
+    /// space available again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena was already rewound past `marker`.
+    pub fn rewind(&mut self, marker: Marker) {
+        self.internal.rewind(marker);
+    }
+
+    /// Returns whether `ptr` points into a value held by this arena, e.g. to
+    /// check in a debug assertion that a node belongs to the right graph.
+    ///
+    /// This walks the blocks, so it takes time proportional to their number.
+    /// Values of a zero-sized `T` have no address of their own, and are never
+    /// found.
+    pub fn contains(&self, ptr: *const T) -> bool {
+        self.internal.contains(ptr)
+    }
+
+    /// Returns the value allocated `index`-th, or `None` if there are not
+    /// that many.
+    ///
+    /// This takes time proportional to the number of blocks, and needs
+    /// `&mut self` so that the reference can't alias one returned by
+    /// [`alloc`](Self::alloc). The [`FrozenArena`] returned by
+    /// [`freeze`](Self::freeze) looks values up by position through `&self`
+    /// with a binary search.
+    pub fn get(&mut self, index: usize) -> Option<&T> {
+        self.get_mut(index).map(|x| &*x)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.internal.get(index).map(|ptr| unsafe { &mut *ptr })
+    }
+
+    /// Returns a cursor positioned at `marker`, which steps over the values
+    /// allocated after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena was already rewound past `marker`.
+    pub fn cursor(&self, marker: Marker) -> Cursor {
+        self.internal.cursor(marker)
+    }
+
+    /// Returns a 4-byte handle to `value`, to be turned back into a reference
+    /// by [`resolve`](Self::resolve) or [`FrozenArena::resolve`].
+    ///
+    /// Returns `None` if `value` isn't in this arena, is zero-sized, or lies
+    /// past what a handle can address: the first `2^HANDLE_BLOCK_BITS` blocks
+    /// and `2^(32 - HANDLE_BLOCK_BITS)` values in each. This searches the
+    /// blocks from the most recent one.
+    pub fn handle_of(&self, value: &T) -> Option<Handle32> {
+        self.internal.handle_of(value)
+    }
+
+    /// Returns the value `handle` refers to.
+    ///
+    /// This needs `&mut self` so that the reference can't alias one returned
+    /// by [`alloc`](Self::alloc).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` doesn't refer to a value in this arena.
+    pub fn resolve(&mut self, handle: Handle32) -> &T {
+        self.resolve_mut(handle)
+    }
+
+    /// Like [`resolve`](Self::resolve), but returns a mutable reference.
+    pub fn resolve_mut(&mut self, handle: Handle32) -> &mut T {
+        match self.internal.resolve(handle) {
+            Some(ptr) => unsafe { &mut *ptr },
+            None => panic!("{handle:?} does not refer to a value in this arena"),
+        }
+    }
+
+    /// Drops the value at `ptr` and reclaims its space if it is the most
+    /// recent allocation, e.g. a node that a parser allocated ahead and then
+    /// discarded. Returns `false`, doing nothing, otherwise.
+    ///
+    /// For a zero-sized `T` all values share an address, and the most recent
+    /// one is dropped whenever there is one.
+    pub fn dealloc_last(&mut self, ptr: *const T) -> bool {
+        self.internal.dealloc_last(ptr)
+    }
+
+    /// Runs `f` with a temporary region of the arena. Everything allocated
+    /// through the [`Scope`] is dropped and its space reclaimed when `f`
+    /// returns or unwinds.
+    pub fn scope<R, F>(&mut self, f: F) -> R
+    where
+        F: for<'s> FnOnce(&mut Scope<'s, T, A>) -> R,
+    {
+        let mut scope = Scope::new(self);
+        f(&mut scope)
+    }
+
+    /// Returns the number of values in the arena.
+    pub fn len(&self) -> usize {
+        self.internal.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a new arena holding clones of every value, in allocation
+    /// order.
+    ///
+    /// This is not a `Clone` impl because reading the values through `&self`
+    /// could alias a `&mut` handed out by [`alloc`](Self::alloc).
+    pub fn deep_clone(&mut self) -> Self
+    where
+        T: Clone,
+        A: Clone,
+    {
+        let mut arena = Self::with_block_growth_in(
+            self.internal.block_size.get(),
+            self.internal.max_block_size,
+            self.internal.backing.clone(),
+        );
+        arena.reserve(self.len());
+        for x in self.iter() {
+            arena.alloc_mut(x.clone());
+        }
+        arena
+    }
+
+    /// Moves every value into a `Vec`, in allocation order.
+    pub fn into_vec(self) -> Vec<T>
+    where
+        A: Clone,
+    {
+        let mut vec = Vec::with_capacity(self.len());
+        vec.extend(self);
+        vec
+    }
+
+    /// Stops allocation and returns a read-only view of the values, which
+    /// can be shared between threads.
+    pub fn freeze(mut self) -> FrozenArena<T, A> {
+        let slices = self
+            .internal
+            .slices()
+            .into_iter()
+            .map(|s| s as *const [T])
+            .collect();
+        // Moving the arena doesn't move the blocks the slices point into.
+        FrozenArena::new(self, slices)
+    }
+
+    /// Returns an iterator over the values in allocation order.
+    ///
+    /// This needs `&mut self` so that no reference returned by
+    /// [`alloc`](Self::alloc) can alias the iterated values. For the same
+    /// reason the arena itself isn't `Serialize`; with the `serde` feature
+    /// serialize the iterator, or the arena after [`freeze`](Self::freeze).
+    pub fn iter(&mut self) -> Iter<'_, T> {
+        Iter {
+            raw: self.internal.raw_iter(),
+        }
+    }
+
+    /// Returns an iterator over mutable references to the values in
+    /// allocation order.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            raw: self.internal.raw_iter(),
+        }
+    }
+
+    /// Returns a parallel iterator over the values, handing out one block
+    /// per task.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter(&mut self) -> impl rayon::iter::ParallelIterator<Item = &T>
+    where
+        T: Sync,
+    {
+        use rayon::prelude::*;
+        let slices: Vec<&[T]> = self.internal.slices().into_iter().map(|s| &*s).collect();
+        slices.into_par_iter().flat_map_iter(|s| s)
+    }
+
+    /// Like [`par_iter`](Self::par_iter), with mutable references.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter_mut(&mut self) -> impl rayon::iter::ParallelIterator<Item = &mut T>
+    where
+        T: Send,
+    {
+        use rayon::prelude::*;
+        self.internal.slices().into_par_iter().flat_map_iter(|s| s)
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.internal.bytes.get()
+    }
+
+    /// Like [`bytes_allocated`](Self::bytes_allocated), plus the heap memory
+    /// taken up by the list of blocks.
+    pub fn memory_usage(&self) -> usize {
+        let blocks = self.internal.blocks.borrow();
+        self.internal.bytes.get() + blocks.capacity() * mem::size_of::<Block>()
+    }
+
+    /// Returns a snapshot of the block and byte counts of the arena.
+    pub fn stats(&self) -> ArenaStats {
+        self.internal.stats()
+    }
+
+    /// Returns what was allocated after `marker` was taken, e.g. to attribute
+    /// memory to the phase of a computation that started there.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena was already rewound past `marker`.
+    pub fn allocated_since(&self, marker: Marker) -> AllocatedSince {
+        self.internal.allocated_since(marker)
+    }
+
+    /// Makes [`reset`](Self::reset) give the physical pages of the blocks it
+    /// keeps back to the OS with `madvise`, while keeping the blocks
+    /// themselves. The pages are faulted back in as the arena fills up again.
+    ///
+    /// This keeps a long-lived arena from holding on to the memory of its
+    /// busiest moment between bursts. Only whole pages inside a block are
+    /// discarded.
+    #[cfg(all(feature = "madvise", unix))]
+    pub fn set_discard_on_reset(&mut self, discard: bool) {
+        self.internal.discard_on_reset = discard;
+    }
+
+    /// Makes infallible allocations call `handler` when a block cannot be
+    /// obtained, instead of panicking on overflow or limits and calling
+    /// [`handle_alloc_error`](alloc::alloc::handle_alloc_error) when the
+    /// system allocator fails.
+    pub fn set_alloc_error_handler(&mut self, handler: fn(ArenaAllocError) -> !) {
+        self.internal.error_handler = Some(handler);
+    }
+
+    /// Reports every following allocation and new block to `observer`,
+    /// replacing the previous one.
+    ///
+    /// The observer has to be `Send` since it moves along with the arena.
+    pub fn set_observer<O: ArenaObserver + Send + 'static>(&mut self, observer: O) {
+        self.internal.observer = Some(Box::new(observer));
+    }
+}
+
+impl<T, A: GlobalAlloc> Extend<T> for Arena<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.alloc_from_iter_mut(iter);
+    }
+}
+
+impl<T> FromIterator<T> for Arena<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut arena = Self::new();
+        arena.extend(iter);
+        arena
+    }
+}
+
+impl<T, A: GlobalAlloc + Clone> IntoIterator for Arena<T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+
+    /// Moves the values out in allocation order. Each block is released as
+    /// soon as its last value is taken.
+    fn into_iter(mut self) -> IntoIter<T, A> {
+        self.internal.take_all()
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Relocatable, A: GlobalAlloc> Arena<T, A> {
+    /// Writes the values to the file at `path`, in allocation order, so that
+    /// [`load`](Arena::load) can bring them back in a later run.
+    ///
+    /// The values are stored as their raw bytes, which is why they have to be
+    /// [`Relocatable`]. The file can only be loaded into an arena of a type
+    /// with the same size and alignment, on a machine with the same byte
+    /// order.
+    pub fn persist<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let len = self.len();
+        let chunks = self.iter().map(|value| unsafe {
+            slice::from_raw_parts((value as *const T).cast::<u8>(), mem::size_of::<T>())
+        });
+        let kind = persist::Kind::Values {
+            size: mem::size_of::<T>(),
+            align: mem::align_of::<T>(),
+        };
+        persist::write(path.as_ref(), kind, len, chunks)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Relocatable> Arena<T> {
+    /// Reads back the values written by [`persist`](Arena::persist), into an
+    /// arena that holds them all in one block.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the file wasn't written
+    /// by `persist`, holds values of another size, or doesn't match the
+    /// checksum it was written with.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let kind = persist::Kind::Values {
+            size: mem::size_of::<T>(),
+            align: mem::align_of::<T>(),
+        };
+        let (count, bytes) = persist::read(path.as_ref(), kind)?;
+        let payload = persist::payload(&bytes).as_ptr().cast::<T>();
+        let arena = Self::with_capacity(count);
+        for i in 0..count {
+            // Any bytes make a valid `T`, and they are only aligned to one.
+            arena.alloc(unsafe { ptr::read_unaligned(payload.wrapping_add(i)) });
+        }
+        Ok(arena)
+    }
+}
+
+/// An [`Arena`] whose block size is the compile-time constant `BLOCK`.
+///
+/// Allocating through [`alloc`](Self::alloc) lets the block sizing be folded
+/// by the optimizer. Everything else is available through `Deref`.
+pub struct FixedBlockArena<T, const BLOCK: usize> {
+    arena: Arena<T>,
+}
+
+impl<T, const BLOCK: usize> FixedBlockArena<T, BLOCK> {
+    pub fn new() -> Self {
+        const { assert!(BLOCK > 0, "block size must be non-zero") };
+        Self {
+            arena: Arena::with_block_size(BLOCK),
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, data: T) -> &mut T {
+        match unsafe { self.arena.internal.try_alloc_sized(data, BLOCK) } {
+            Ok(x) => unsafe { &mut *x },
+            Err(e) => self.arena.internal.fail(e),
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc(&self, data: T) -> Result<&mut T, ArenaAllocError> {
+        unsafe { Ok(&mut *self.arena.internal.try_alloc_sized(data, BLOCK)?) }
+    }
+
+    /// See [`Arena::reset`].
+    pub fn reset(&mut self) {
+        self.arena.reset();
+    }
+
+    /// See [`Arena::rewind`].
+    pub fn rewind(&mut self, marker: Marker) {
+        self.arena.rewind(marker);
+    }
+}
+
+impl<T, const BLOCK: usize> Default for FixedBlockArena<T, BLOCK> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const BLOCK: usize> Deref for FixedBlockArena<T, BLOCK> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        &self.arena
+    }
+}
+
+/// A temporary region of an [`Arena`], created by [`Arena::scope`].
+///
+/// References handed out by a scope cannot outlive it, so the scope can
+/// safely drop its values when it ends.
+pub struct Scope<'s, T, A: GlobalAlloc = Global> {
+    arena: &'s Arena<T, A>,
+    marker: Marker,
+}
+
+impl<'s, T, A: GlobalAlloc> Scope<'s, T, A> {
+    fn new(arena: &'s Arena<T, A>) -> Self {
+        Self {
+            marker: arena.checkpoint(),
+            arena,
+        }
+    }
+
+    pub fn alloc(&self, data: T) -> &'s mut T {
+        self.arena.alloc(data)
+    }
+
+    /// See [`Arena::alloc_with`].
+    #[inline(always)]
+    pub fn alloc_with<F: FnOnce() -> T>(&self, f: F) -> &'s mut T {
+        self.arena.alloc_with(f)
+    }
+
+    pub fn alloc_from_iter<I: IntoIterator<Item = T>>(&self, iter: I) -> &'s mut [T] {
+        self.arena.alloc_from_iter(iter)
+    }
+
+    /// Opens a nested scope, see [`Arena::scope`].
+    pub fn scope<R, F>(&mut self, f: F) -> R
+    where
+        F: for<'n> FnOnce(&mut Scope<'n, T, A>) -> R,
+    {
+        let mut scope = Scope::new(self.arena);
+        f(&mut scope)
+    }
+}
+
+impl<T, A: GlobalAlloc> Drop for Scope<'_, T, A> {
+    fn drop(&mut self) {
+        self.arena.internal.rewind(self.marker);
+    }
+}
+
+struct RawIter<'a, T> {
+    blocks: slice::Iter<'a, Block>,
+    ptr: *mut T,
+    // Elements left in the block `ptr` points into.
+    len: usize,
+    // Values of a zero-sized `T` left to yield.
+    zst_len: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T> Clone for RawIter<'_, T> {
+    fn clone(&self) -> Self {
+        RawIter {
+            blocks: self.blocks.clone(),
+            ptr: self.ptr,
+            len: self.len,
+            zst_len: self.zst_len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Iterator for RawIter<'_, T> {
+    type Item = *mut T;
+
+    fn next(&mut self) -> Option<*mut T> {
+        if mem::size_of::<T>() == 0 {
+            if self.zst_len == 0 {
+                return None;
+            }
+            self.zst_len -= 1;
+            return Some(NonNull::dangling().as_ptr());
+        }
+        while self.len == 0 {
+            let block = self.blocks.next()?;
+            self.ptr = block.raw.ptr().cast::<T>();
+            self.len = block.count_of_elements;
+        }
+        let ptr = self.ptr;
+        self.ptr = unsafe { ptr.add(1) };
+        self.len -= 1;
+        Some(ptr)
+    }
+}
+
+/// An iterator over the values of an [`Arena`], created by [`Arena::iter`].
+pub struct Iter<'a, T> {
+    raw: RawIter<'a, T>,
+}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Iter {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.raw.next().map(|ptr| unsafe { &*ptr })
+    }
+}
+
+/// A mutable iterator over the values of an [`Arena`], created by
+/// [`Arena::iter_mut`].
+pub struct IterMut<'a, T> {
+    raw: RawIter<'a, T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.raw.next().map(|ptr| unsafe { &mut *ptr })
+    }
+}
+
+/// An owning iterator over the values of an [`Arena`], created by its
+/// [`IntoIterator`] implementation.
+pub struct IntoIter<T, A: GlobalAlloc = Global> {
+    blocks: vec::IntoIter<Block>,
+    // The block being drained. Its elements before `index` were moved out.
+    block: Option<Block>,
+    index: usize,
+    zst_len: usize,
+    backing: A,
+    _marker: PhantomData<T>,
+}
+
+impl<T, A: GlobalAlloc> Iterator for IntoIter<T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if mem::size_of::<T>() == 0 {
+            if self.zst_len == 0 {
+                return None;
+            }
+            self.zst_len -= 1;
+            return Some(unsafe { ptr::read(NonNull::dangling().as_ptr()) });
+        }
+        loop {
+            if let Some(block) = &self.block {
+                if self.index < block.count_of_elements {
+                    let ptr = unsafe { block.raw.ptr().cast::<T>().add(self.index) };
+                    let x = unsafe { ptr::read(ptr) };
+                    unsafe { sanitizer::freelike(ptr.cast()) };
+                    self.index += 1;
+                    return Some(x);
+                }
+            }
+            let next = self.blocks.next()?;
+            if let Some(drained) = self.block.replace(next) {
+                unsafe { drained.raw.free(&self.backing) };
+            }
+            self.index = 0;
+        }
+    }
+}
+
+// Owns the remaining values and their blocks, like the arena it came from.
+unsafe impl<T: Send, A: GlobalAlloc + Send> Send for IntoIter<T, A> {}
+
+impl<T, A: GlobalAlloc> Drop for IntoIter<T, A> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+        if let Some(block) = self.block.take() {
+            unsafe { block.raw.free(&self.backing) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{Arena, FixedBlockArena, Handle32};
+    use crate::{AllocatedSince, ArenaAllocError, ArenaObserver, Global};
+
+    struct X<'a> {
+        drop_cnt: &'a RefCell<i32>,
+    }
+
+    impl<'a> Drop for X<'a> {
+        fn drop(&mut self) {
+            *self.drop_cnt.borrow_mut() += 1;
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let drop_cnt = RefCell::new(0);
+        {
+            let arena = Arena::new();
+            for _ in 0..1000 {
+                arena.alloc(X {
+                    drop_cnt: &drop_cnt,
+                });
+            }
+            assert!(arena.bytes_allocated() > 0);
+        }
+        assert_eq!(*drop_cnt.borrow(), 1000);
+    }
+
+    #[test]
+    fn alloc_with() {
+        let drop_cnt = RefCell::new(0);
+        {
+            let arena = Arena::new();
+            let x = arena.alloc_with(|| X {
+                drop_cnt: &drop_cnt,
+            });
+            assert_eq!(*x.drop_cnt.borrow(), 0);
+            // Nothing is left in the slot when `f` panics.
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                arena.alloc_with(|| -> X { panic!("no value") })
+            }));
+            assert!(r.is_err());
+            assert_eq!(arena.len(), 1);
+            arena.alloc_with(|| X {
+                drop_cnt: &drop_cnt,
+            });
+        }
+        assert_eq!(*drop_cnt.borrow(), 2);
+
+        let arena = Arena::with_block_size(1);
+        let big = arena.alloc_with(|| [7u64; 1024]);
+        assert!(big.iter().all(|&x| x == 7));
+    }
+
+    #[test]
+    #[should_panic(expected = "inside its `alloc_with` or `alloc_many`")]
+    fn alloc_with_reentrant() {
+        let arena = Arena::new();
+        arena.alloc_with(|| *arena.alloc(1) + 1);
+    }
+
+    #[test]
+    fn try_alloc() {
+        let arena = Arena::new();
+        let x = arena.try_alloc(5u64).unwrap();
+        assert_eq!(*x, 5);
+        assert_eq!(arena.bytes_allocated(), 4096);
+    }
+
+    #[test]
+    fn alloc_slice_copy() {
+        let arena = Arena::new();
+        arena.alloc(0u32);
+        let src: Vec<u32> = (1..=2000).collect();
+        let slice = arena.alloc_slice_copy(&src);
+        assert_eq!(slice, &src[..]);
+        assert!(arena.alloc_slice_copy(&[]).is_empty());
+    }
+
+    #[test]
+    fn alloc_slice_clone() {
+        let rc = Rc::new(());
+        {
+            let arena = Arena::new();
+            let src = vec![rc.clone(), rc.clone(), rc.clone()];
+            let slice = arena.alloc_slice_clone(&src);
+            assert_eq!(slice.len(), 3);
+            assert_eq!(Rc::strong_count(&rc), 7);
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn alloc_from_iter() {
+        let drop_cnt = RefCell::new(0);
+        {
+            let arena = Arena::new();
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+            let xs = arena.alloc_from_iter((0..700).map(|_| X {
+                drop_cnt: &drop_cnt,
+            }));
+            assert_eq!(xs.len(), 700);
+            assert!(arena.alloc_from_iter(std::iter::empty()).is_empty());
+            assert_eq!(*drop_cnt.borrow(), 0);
+        }
+        assert_eq!(*drop_cnt.borrow(), 701);
+
+        let arena = Arena::new();
+        let odd = arena.alloc_from_iter((0..100u32).filter(|x| x % 2 == 1));
+        assert_eq!(odd.len(), 50);
+        assert_eq!(odd[49], 99);
+    }
+
+    #[test]
+    fn alloc_slice_fill_with() {
+        let mut arena = Arena::with_block_size(64);
+        arena.alloc_slice_copy(&[0u64; 5]);
+        // More than fits in a block.
+        let table = arena.alloc_slice_fill_with(20, |i| i as u64 * 3);
+        assert!(table.iter().enumerate().all(|(i, &x)| x == i as u64 * 3));
+        assert_eq!(arena.len(), 5 + 20);
+
+        let table = arena.alloc_slice_fill_with_mut(8, |i| i as u64);
+        assert_eq!(table, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(arena.alloc_slice_fill_with(0, |_| 1).is_empty());
+        assert_eq!(arena.len(), 5 + 20 + 8);
+    }
+
+    #[test]
+    fn alloc_many() {
+        let drop_cnt = RefCell::new(0);
+        {
+            let arena = Arena::with_block_size(4);
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+            let xs = arena.alloc_many(10, |_| X {
+                drop_cnt: &drop_cnt,
+            });
+            assert_eq!(xs.len(), 10);
+            assert_eq!(arena.len(), 11);
+
+            // The values written before the panic are dropped right away.
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                arena.alloc_many(5, |i| {
+                    assert!(i < 3);
+                    X {
+                        drop_cnt: &drop_cnt,
+                    }
+                })
+            }));
+            assert!(r.is_err());
+            assert_eq!(*drop_cnt.borrow(), 3);
+            assert_eq!(arena.len(), 11);
+            assert!(arena.alloc_many(0, |_| unreachable!()).is_empty());
+        }
+        assert_eq!(*drop_cnt.borrow(), 14);
+
+        let arena = Arena::new();
+        let table = arena.alloc_many(100, |i| i * i);
+        assert!(table.iter().enumerate().all(|(i, &x)| x == i * i));
+        assert_eq!(Arena::new().alloc_many(3, |_| ()).len(), 3);
+    }
+
+    #[test]
+    fn alloc_mut() {
+        let drop_cnt = RefCell::new(0);
+        {
+            let mut arena = Arena::new();
+            arena.alloc_mut(X {
+                drop_cnt: &drop_cnt,
+            });
+            let xs = arena.alloc_from_iter_mut((0..700).map(|_| X {
+                drop_cnt: &drop_cnt,
+            }));
+            assert_eq!(xs.len(), 700);
+            let xs = arena.alloc_from_iter_mut((0..20).filter(|x| x % 2 == 0).map(|_| X {
+                drop_cnt: &drop_cnt,
+            }));
+            assert_eq!(xs.len(), 10);
+            assert_eq!(*drop_cnt.borrow(), 0);
+        }
+        assert_eq!(*drop_cnt.borrow(), 711);
+
+        let rc = Rc::new(0);
+        {
+            let mut arena = Arena::new();
+            let src = vec![rc.clone(); 3];
+            assert_eq!(arena.alloc_slice_clone_mut(&src).len(), 3);
+            assert_eq!(Rc::strong_count(&rc), 7);
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn len() {
+        let mut arena = Arena::with_block_size(64);
+        assert!(arena.is_empty());
+        for i in 0..100u32 {
+            arena.alloc(i);
+        }
+        let marker = arena.checkpoint();
+        arena.alloc_slice_copy(&[1; 30]);
+        assert_eq!(arena.len(), 130);
+        arena.rewind(marker);
+        assert_eq!(arena.len(), 100);
+        arena.reset();
+        assert!(arena.is_empty());
+
+        let units = Arena::new();
+        units.alloc(());
+        assert_eq!(units.len(), 1);
+    }
+
+    #[test]
+    fn iter() {
+        let mut arena = Arena::with_block_size(64);
+        for i in 0..100u32 {
+            arena.alloc(i);
+        }
+        arena.alloc_slice_copy(&[100, 101]);
+        assert!(arena.iter().copied().eq(0..102));
+
+        for x in arena.iter_mut() {
+            *x *= 2;
+        }
+        assert_eq!(arena.iter().sum::<u32>(), 101 * 102);
+
+        let marker = arena.checkpoint();
+        arena.alloc(7);
+        arena.rewind(marker);
+        assert_eq!(arena.iter().count(), 102);
+
+        arena.reset();
+        assert_eq!(arena.iter().next(), None);
+
+        let mut units = Arena::new();
+        units.alloc(());
+        units.alloc(());
+        assert_eq!(units.iter().count(), 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_iter() {
+        use rayon::prelude::*;
+
+        let mut arena = Arena::with_block_size(64);
+        for i in 0..1000u64 {
+            arena.alloc(i);
+        }
+        arena.par_iter_mut().for_each(|x| *x *= 2);
+        assert_eq!(arena.par_iter().sum::<u64>(), 999 * 1000);
+    }
+
+    #[test]
+    fn extend() {
+        let mut arena: Arena<u32> = (0..10).collect();
+        arena.extend(10..20);
+        arena.extend((20..100).filter(|_| true));
+        assert_eq!(arena.len(), 100);
+        assert!(arena.iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn deep_clone() {
+        let rc = Rc::new(0);
+        let mut arena = Arena::with_block_size(64);
+        for _ in 0..10 {
+            arena.alloc(rc.clone());
+        }
+        let mut copy = arena.deep_clone();
+        assert_eq!(copy.len(), 10);
+        assert_eq!(Rc::strong_count(&rc), 21);
+        drop(arena);
+        assert_eq!(copy.iter().count(), 10);
+        drop(copy);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn into_iter() {
+        let arena = Arena::with_block_size(64);
+        for i in 0..100u32 {
+            arena.alloc(i);
+        }
+        assert!(arena.into_iter().eq(0..100));
+
+        let drop_cnt = RefCell::new(0);
+        let arena = Arena::with_block_size(64);
+        for _ in 0..10 {
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+        }
+        let mut iter = arena.into_iter();
+        drop(iter.next());
+        assert_eq!(*drop_cnt.borrow(), 1);
+        drop(iter);
+        assert_eq!(*drop_cnt.borrow(), 10);
+
+        let units = Arena::new();
+        units.alloc(());
+        assert_eq!(units.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn into_vec() {
+        let arena = Arena::with_block_size(64);
+        arena.alloc_slice_copy(&[1u64, 2, 3]);
+        for i in 4..=100 {
+            arena.alloc(i);
+        }
+        let vec = arena.into_vec();
+        assert_eq!(vec.len(), 100);
+        assert_eq!(vec.iter().sum::<u64>(), 5050);
+
+        let rc = Rc::new(0);
+        let arena = Arena::new();
+        arena.alloc_slice_clone(&[rc.clone(), rc.clone()]);
+        let vec = arena.into_vec();
+        assert_eq!(Rc::strong_count(&rc), 3);
+        drop(vec);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn reset() {
+        let drop_cnt = RefCell::new(0);
+        let mut arena = Arena::new();
+        for _ in 0..1000 {
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+        }
+        let bytes = arena.bytes_allocated();
+        arena.reset();
+        assert_eq!(*drop_cnt.borrow(), 1000);
+
+        for _ in 0..1000 {
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+        }
+        assert_eq!(arena.bytes_allocated(), bytes);
+        drop(arena);
+        assert_eq!(*drop_cnt.borrow(), 2000);
+    }
+
+    #[cfg(all(feature = "madvise", unix))]
+    #[test]
+    fn discard_on_reset() {
+        let mut arena = Arena::with_block_size(1 << 16);
+        arena.set_discard_on_reset(true);
+        for round in 0..3u64 {
+            for i in 0..100_000 {
+                arena.alloc(i + round);
+            }
+            assert_eq!(
+                arena.iter().sum::<u64>(),
+                (0..100_000).sum::<u64>() + 100_000 * round
+            );
+            arena.reset();
+        }
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn rewind() {
+        let drop_cnt = RefCell::new(0);
+        let mut arena = Arena::new();
+        arena.alloc(X {
+            drop_cnt: &drop_cnt,
+        });
+        let marker = arena.checkpoint();
+        for _ in 0..1000 {
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+        }
+        let bytes = arena.bytes_allocated();
+        arena.rewind(marker);
+        assert_eq!(*drop_cnt.borrow(), 1000);
+        assert_eq!(arena.checkpoint(), marker);
+
+        for _ in 0..1000 {
+            arena.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+        }
+        assert_eq!(arena.bytes_allocated(), bytes);
+        drop(arena);
+        assert_eq!(*drop_cnt.borrow(), 2001);
+    }
+
+    #[test]
+    #[should_panic(expected = "marker is ahead of the arena")]
+    fn rewind_past_marker() {
+        let mut arena = Arena::new();
+        let start = arena.checkpoint();
+        arena.alloc(1u8);
+        let marker = arena.checkpoint();
+        arena.rewind(start);
+        arena.rewind(marker);
+    }
+
+    #[test]
+    fn scope() {
+        let drop_cnt = RefCell::new(0);
+        let mut arena = Arena::new();
+        arena.alloc(X {
+            drop_cnt: &drop_cnt,
+        });
+        let n = arena.scope(|scope| {
+            let x = scope.alloc(X {
+                drop_cnt: &drop_cnt,
+            });
+            scope.scope(|inner| {
+                for _ in 0..1000 {
+                    inner.alloc(X {
+                        drop_cnt: &drop_cnt,
+                    });
+                }
+            });
+            assert_eq!(*drop_cnt.borrow(), 1000);
+            *x.drop_cnt.borrow()
+        });
+        assert_eq!(n, 1000);
+        assert_eq!(*drop_cnt.borrow(), 1001);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            arena.scope(|scope| {
+                scope.alloc(X {
+                    drop_cnt: &drop_cnt,
+                });
+                panic!("boom");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(*drop_cnt.borrow(), 1002);
+    }
+
+    #[test]
+    fn with_block_size() {
+        let arena = Arena::with_block_size(64 * 1024);
+        for i in 0..10000u32 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.bytes_allocated(), 64 * 1024);
+
+        let arena = Arena::with_block_size(16);
+        arena.alloc([0u8; 100]);
+        assert_eq!(arena.bytes_allocated(), 100);
+    }
+
+    #[test]
+    fn fixed_block_arena() {
+        let mut arena = FixedBlockArena::<u64, 1024>::new();
+        for i in 0..256 {
+            assert_eq!(*arena.alloc(i), i);
+        }
+        assert_eq!(arena.bytes_allocated(), 2048);
+        assert_eq!(arena.alloc_slice_copy(&[1, 2, 3]), &[1, 2, 3]);
+        assert_eq!(arena.bytes_allocated(), 3072);
+        arena.reset();
+        arena.alloc(0);
+        assert_eq!(arena.bytes_allocated(), 3072);
+    }
+
+    #[test]
+    fn stats() {
+        let mut arena = Arena::with_block_size(64);
+        for i in 0..10u64 {
+            arena.alloc(i);
+        }
+        arena.alloc_slice_copy(&[0; 7]);
+        let stats = arena.stats();
+        assert_eq!(stats.blocks, 3);
+        assert_eq!(stats.bytes_reserved, 192);
+        assert_eq!(stats.bytes_used, 136);
+        assert_eq!(stats.wasted_bytes, 48);
+        assert_eq!(stats.high_water_mark, 136);
+
+        arena.reset();
+        arena.alloc(1);
+        let stats = arena.stats();
+        assert_eq!(stats.bytes_used, 8);
+        assert_eq!(stats.wasted_bytes, 0);
+        assert_eq!(stats.high_water_mark, 136);
+    }
+
+    #[test]
+    fn allocated_since() {
+        let mut arena = Arena::with_block_size(64);
+        arena.alloc_slice_copy(&[0u64; 3]);
+        let marker = arena.checkpoint();
+        assert_eq!(arena.allocated_since(marker), AllocatedSince::default());
+
+        for i in 0..10 {
+            arena.alloc(i);
+        }
+        let since = arena.allocated_since(marker);
+        assert_eq!(since.elements, 10);
+        assert_eq!(since.bytes_used, 80);
+        assert_eq!(since.blocks, 1);
+        assert_eq!(since.bytes_reserved, 64);
+        assert_eq!(since.wasted_bytes, 0);
+
+        let phase = arena.checkpoint();
+        arena.alloc_slice_copy(&[0; 7]);
+        let since = arena.allocated_since(marker);
+        assert_eq!(since.elements, 17);
+        assert_eq!(since.blocks, 2);
+        assert_eq!(since.bytes_reserved, 128);
+        assert_eq!(since.wasted_bytes, 24);
+        assert_eq!(arena.allocated_since(phase).elements, 7);
+
+        arena.rewind(marker);
+        assert_eq!(arena.allocated_since(marker), AllocatedSince::default());
+
+        let units = Arena::new();
+        let marker = units.checkpoint();
+        units.alloc(());
+        units.alloc(());
+        assert_eq!(units.allocated_since(marker).elements, 2);
+        assert_eq!(units.allocated_since(marker).bytes_used, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "marker is ahead of the arena")]
+    fn allocated_since_rewound_marker() {
+        let mut arena = Arena::new();
+        arena.alloc(1);
+        let marker = arena.checkpoint();
+        arena.alloc(2);
+        arena.reset();
+        arena.allocated_since(marker);
+    }
+
+    #[test]
+    fn dealloc_last() {
+        let drop_cnt = RefCell::new(0);
+        let mut arena = Arena::with_block_size(64);
+        let first = arena.alloc(X {
+            drop_cnt: &drop_cnt,
+        }) as *const X;
+        let lookahead = arena.alloc(X {
+            drop_cnt: &drop_cnt,
+        }) as *const X;
+        assert!(!arena.dealloc_last(first));
+        assert!(arena.dealloc_last(lookahead));
+        assert_eq!(*drop_cnt.borrow(), 1);
+        assert_eq!(arena.len(), 1);
+        // The space is reused by the next allocation.
+        let next = arena.alloc(X {
+            drop_cnt: &drop_cnt,
+        }) as *const X;
+        assert_eq!(next, lookahead);
+        assert!(!arena.dealloc_last(lookahead.wrapping_add(1)));
+        assert!(arena.dealloc_last(next));
+        assert!(arena.dealloc_last(first));
+        assert!(!arena.dealloc_last(first));
+        assert_eq!(*drop_cnt.borrow(), 3);
+        assert!(arena.is_empty());
+
+        let mut units = Arena::new();
+        let unit = units.alloc(()) as *const ();
+        assert!(units.dealloc_last(unit));
+        assert!(!units.dealloc_last(unit));
+    }
+
+    #[test]
+    fn contains() {
+        let mut arena = Arena::with_block_size(64);
+        let other = Arena::new();
+        let first = arena.alloc(1u64) as *const u64;
+        let marker = arena.checkpoint();
+        let nodes: Vec<*const u64> = (0..20).map(|i| arena.alloc(i) as *const u64).collect();
+        let stranger = other.alloc(1u64) as *const u64;
+        assert!(arena.contains(first));
+        assert!(nodes.iter().all(|&node| arena.contains(node)));
+        assert!(!arena.contains(stranger));
+        assert!(!other.contains(first));
+        assert!(!arena.contains(&1));
+        // Past the last value of the current block.
+        assert!(!arena.contains(nodes[19].wrapping_add(1)));
+
+        arena.rewind(marker);
+        assert!(arena.contains(first));
+        assert!(!arena.contains(nodes[0]));
+    }
+
+    #[test]
+    fn get() {
+        let mut arena = Arena::with_block_size(64);
+        for i in 0..10u64 {
+            arena.alloc(i);
+        }
+        arena.alloc_slice_copy(&[10, 11, 12, 13, 14, 15, 16]);
+        arena.alloc(17);
+        assert!((0..18).all(|i| arena.get(i) == Some(&(i as u64))));
+        assert_eq!(arena.get(18), None);
+        *arena.get_mut(12).unwrap() += 100;
+        assert_eq!(arena.get(12), Some(&112));
+
+        arena.reset();
+        assert_eq!(arena.get(0), None);
+
+        let mut units = Arena::new();
+        units.alloc(());
+        assert_eq!(units.get(0), Some(&()));
+        assert_eq!(units.get(1), None);
+    }
+
+    #[test]
+    fn cursor() {
+        let mut arena = Arena::with_block_size(64);
+        arena.alloc_slice_copy(&[0u64; 3]);
+        let mut cursor = arena.cursor(arena.checkpoint());
+        assert_eq!(cursor.offset(), 24);
+        assert_eq!(cursor.next(&mut arena), None);
+
+        for i in 1..=10 {
+            arena.alloc(i);
+        }
+        let mut seen = Vec::new();
+        while let Some((offset, &value)) = cursor.next(&mut arena) {
+            seen.push((offset, value));
+        }
+        assert_eq!(seen.len(), 10);
+        assert!(seen
+            .iter()
+            .all(|&(offset, value)| offset == 16 + 8 * value as usize));
+
+        // Values allocated later are picked up, past the tail of a block left
+        // unused by a slice.
+        arena.alloc_slice_copy(&[11, 12, 13, 14, 15, 16, 17]);
+        assert_eq!(cursor.next(&mut arena), Some((104, &11)));
+        assert_eq!(cursor.offset(), 112);
+        assert_eq!(arena.cursor(arena.checkpoint()).offset(), arena.len() * 8);
+
+        let mut units = Arena::new();
+        let mut cursor = units.cursor(units.checkpoint());
+        units.alloc(());
+        assert_eq!(cursor.next(&mut units), Some((0, &())));
+        assert_eq!(cursor.next(&mut units), None);
+    }
+
+    #[test]
+    fn handles() {
+        struct Node {
+            value: u64,
+            next: Option<Handle32>,
+        }
+
+        let mut arena = Arena::with_block_size(64);
+        let mut next = None;
+        for value in 0..100 {
+            let node = arena.alloc(Node { value, next });
+            next = arena.handle_of(node);
+        }
+        assert_eq!(std::mem::size_of::<Option<Handle32>>(), 4);
+        let mut values = Vec::new();
+        while let Some(handle) = next {
+            let node = arena.resolve(handle);
+            values.push(node.value);
+            next = node.next;
+        }
+        assert!(values.into_iter().eq((0..100).rev()));
+
+        let last = arena.alloc(Node {
+            value: 100,
+            next: None,
+        });
+        let handle = arena.handle_of(last).unwrap();
+        arena.resolve_mut(handle).value += 1;
+        assert_eq!(Handle32::from_u32(handle.as_u32()), handle);
+        let frozen = arena.freeze();
+        assert_eq!(frozen.resolve(handle).value, 101);
+
+        let other = Arena::new();
+        assert_eq!(
+            other.handle_of(&Node {
+                value: 0,
+                next: None
+            }),
+            None
+        );
+        assert_eq!(Arena::new().handle_of(&()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not refer to a value in this arena")]
+    fn stale_handle() {
+        let mut arena = Arena::new();
+        let handle = arena.handle_of(arena.alloc(1)).unwrap();
+        arena.reset();
+        arena.resolve(handle);
+    }
+
+    #[test]
+    fn compact() {
+        let drop_cnt = RefCell::new(0);
+        let mut arena = Arena::with_block_growth(64, 1024);
+        for i in 0..100 {
+            arena.alloc((
+                i,
+                X {
+                    drop_cnt: &drop_cnt,
+                },
+            ));
+            if i % 10 == 0 {
+                arena.alloc_from_iter((0..3).map(|_| {
+                    (
+                        0,
+                        X {
+                            drop_cnt: &drop_cnt,
+                        },
+                    )
+                }));
+            }
+        }
+        arena.reserve(1000);
+        let before: Vec<usize> = arena.iter().map(|(i, _)| *i).collect();
+        assert!(arena.stats().blocks > 3);
+
+        arena.compact();
+        assert_eq!(*drop_cnt.borrow(), 0);
+        let stats = arena.stats();
+        assert_eq!(stats.blocks, 1);
+        assert_eq!(stats.bytes_reserved, stats.bytes_used);
+        assert_eq!(stats.wasted_bytes, 0);
+        assert!(arena.iter().map(|(i, _)| *i).eq(before));
+
+        arena.alloc((
+            100,
+            X {
+                drop_cnt: &drop_cnt,
+            },
+        ));
+        assert_eq!(arena.stats().blocks, 2);
+        assert_eq!(arena.len(), 131);
+        drop(arena);
+        assert_eq!(*drop_cnt.borrow(), 131);
+
+        let mut empty = Arena::<u64>::new();
+        empty.reserve(10);
+        empty.compact();
+        assert_eq!(empty.stats().blocks, 0);
+        assert_eq!(empty.alloc(7), &7);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let arena = Arena::new();
+        assert_eq!(arena.memory_usage(), 0);
+        arena.alloc(1u64);
+        assert!(arena.memory_usage() >= 4096 + std::mem::size_of::<super::Block>());
+    }
+
+    #[test]
+    fn send_to_other_thread() {
+        let arena = thread::spawn(|| {
+            let arena = Arena::new();
+            for i in 0..1000 {
+                arena.alloc(i.to_string());
+            }
+            arena
+        })
+        .join()
+        .unwrap();
+        assert_eq!(arena.len(), 1000);
+        let values = thread::spawn(move || arena.into_iter().collect::<Vec<_>>())
+            .join()
+            .unwrap();
+        assert_eq!(values[999], "999");
+    }
+
+    #[test]
+    fn observer() {
+        #[derive(Default)]
+        struct Counts {
+            allocs: AtomicUsize,
+            bytes: AtomicUsize,
+            blocks: AtomicUsize,
+        }
+
+        impl ArenaObserver for Arc<Counts> {
+            fn on_alloc(&self, _ptr: *const u8, size: usize) {
+                self.allocs.fetch_add(1, Ordering::Relaxed);
+                self.bytes.fetch_add(size, Ordering::Relaxed);
+            }
+
+            fn on_block(&self, _ptr: *const u8, size: usize) {
+                assert!(size >= 64);
+                self.blocks.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let counts = Arc::new(Counts::default());
+        let mut arena = Arena::with_block_size(64);
+        arena.set_observer(counts.clone());
+        for i in 0..10u64 {
+            arena.alloc(i);
+        }
+        arena.alloc_slice_copy(&[0; 7]);
+        arena.reserve(100);
+        assert_eq!(counts.allocs.load(Ordering::Relaxed), 11);
+        assert_eq!(counts.bytes.load(Ordering::Relaxed), 136);
+        assert_eq!(counts.blocks.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn with_limit() {
+        let arena = Arena::with_limit(10000);
+        for i in 0..1250u64 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.bytes_allocated(), 10000);
+        assert_eq!(
+            arena.try_alloc(0),
+            Err(ArenaAllocError::LimitExceeded { limit: 10000 })
+        );
+    }
+
+    #[test]
+    fn alloc_error_handler() {
+        fn handler(e: ArenaAllocError) -> ! {
+            panic!("out of budget: {}", e);
+        }
+
+        let mut arena = Arena::with_limit(4096);
+        arena.set_alloc_error_handler(handler);
+        arena.alloc_slice_copy(&[0u64; 512]);
+        let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            arena.alloc(0);
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "out of budget: arena limit of 4096 bytes exceeded"
+        );
+    }
+
+    #[test]
+    fn with_capacity() {
+        let arena = Arena::with_capacity(10000);
+        let bytes = arena.bytes_allocated();
+        assert!(bytes >= 10000 * 8);
+        for i in 0..10000u64 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.bytes_allocated(), bytes);
+
+        arena.reserve(600);
+        let bytes = arena.bytes_allocated();
+        for i in 0..600u64 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.bytes_allocated(), bytes);
+    }
+
+    #[test]
+    fn block_growth() {
+        let arena = Arena::new();
+        for i in 0..(4096 + 8192) / 8 {
+            arena.alloc(i as u64);
+        }
+        assert_eq!(arena.bytes_allocated(), 4096 + 8192);
+        arena.alloc(0);
+        assert_eq!(arena.bytes_allocated(), 4096 + 8192 + 16384);
+
+        let arena = Arena::with_block_growth(1024, 2048);
+        for i in 0..1024u32 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.bytes_allocated(), 1024 + 2048 + 2048);
+    }
+
+    #[test]
+    fn alignment() {
+        #[repr(align(64))]
+        struct Line([u8; 100]);
+
+        let arena = Arena::new();
+        for i in 0..100u8 {
+            let line = arena.alloc(Line([i; 100]));
+            assert_eq!((line as *mut Line).addr() % 64, 0);
+            assert_eq!(line.0[99], i);
+        }
+    }
+
+    #[test]
+    fn zero_sized() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Z;
+
+        impl Drop for Z {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut arena = Arena::new();
+        for _ in 0..1000 {
+            arena.alloc(Z);
+        }
+        let marker = arena.checkpoint();
+        assert_eq!(arena.alloc_from_iter((0..10).map(|_| Z)).len(), 10);
+        assert_eq!(arena.bytes_allocated(), 0);
+        arena.rewind(marker);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+        drop(arena);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1010);
+
+        let arena = Arena::new();
+        arena.alloc_slice_copy(&[(), (), ()]);
+        assert_eq!(arena.bytes_allocated(), 0);
+    }
+
+    #[test]
+    fn rewind_without_drop() {
+        let mut arena = Arena::new();
+        arena.alloc(1u64);
+        let marker = arena.checkpoint();
+        let first = arena.alloc(2u64) as *mut u64;
+        for i in 0..5000 {
+            arena.alloc(i);
+        }
+        arena.rewind(marker);
+        assert_eq!(arena.checkpoint(), marker);
+        assert_eq!(arena.alloc(3) as *mut u64, first);
+    }
+
+    #[test]
+    fn new_in() {
+        #[derive(Clone, Default)]
+        struct Counting(Rc<Cell<isize>>);
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                self.0.set(self.0.get() + layout.size() as isize);
+                Global.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                self.0.set(self.0.get() - layout.size() as isize);
+                Global.dealloc(ptr, layout)
+            }
+        }
+
+        let counting = Counting::default();
+        let mut arena = Arena::new_in(counting.clone());
+        arena.extend(0..5000u64);
+        assert_eq!(counting.0.get() as usize, arena.bytes_allocated());
+        let copy = arena.deep_clone();
+        assert_eq!(counting.0.get() as usize, 2 * arena.bytes_allocated());
+        drop(arena);
+
+        let mut iter = copy.into_iter();
+        assert_eq!(iter.next(), Some(0));
+        drop(iter);
+        assert_eq!(counting.0.get(), 0);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_on_release() {
+        #[derive(Clone, Default)]
+        struct Checking(Rc<Cell<usize>>);
+
+        unsafe impl GlobalAlloc for Checking {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                Global.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                let bytes = std::slice::from_raw_parts(ptr, layout.size());
+                assert!(bytes.iter().all(|&b| b == 0));
+                self.0.set(self.0.get() + 1);
+                Global.dealloc(ptr, layout)
+            }
+        }
+
+        let checking = Checking::default();
+        let arena = Arena::with_block_growth_in(64, 64, checking.clone());
+        for i in 0..100u64 {
+            arena.alloc([!i; 3]);
+        }
+        let blocks = arena.stats().blocks;
+        drop(arena);
+        assert_eq!(checking.0.get(), blocks);
+    }
+
+    // Reads memory that was given up, which ASan would rightly report.
+    #[cfg(all(feature = "poison", not(feature = "asan")))]
+    #[test]
+    fn poison() {
+        let mut arena = Arena::new();
+        let first: *const u32 = arena.alloc(1u32);
+        let marker = arena.checkpoint();
+        let second: *const u32 = arena.alloc(2u32);
+        assert_eq!(unsafe { second.add(1).read() }, 0xA5A5_A5A5);
+        arena.rewind(marker);
+        assert_eq!(unsafe { second.read() }, 0xDEDE_DEDE);
+        assert_eq!(unsafe { first.read() }, 1);
+        arena.reset();
+        assert_eq!(unsafe { first.read() }, 0xDEDE_DEDE);
+    }
+
+    #[cfg(feature = "asan")]
+    #[test]
+    fn asan_poisoning() {
+        extern "C" {
+            fn __asan_address_is_poisoned(addr: *const u8) -> i32;
+        }
+        let poisoned = |ptr: *const u64| unsafe { __asan_address_is_poisoned(ptr.cast()) != 0 };
+
+        let mut arena = Arena::new();
+        let first: *const u64 = arena.alloc(1);
+        assert!(!poisoned(first));
+        assert!(poisoned(first.wrapping_add(1)));
+        let marker = arena.checkpoint();
+        let rest = arena.alloc_slice_copy(&[2, 3]).as_ptr();
+        assert!(!poisoned(rest.wrapping_add(1)));
+        arena.rewind(marker);
+        assert!(poisoned(rest));
+        arena.reset();
+        assert!(poisoned(first));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn cyclic_references() {
+        struct Node<'a> {
+            value: u32,
+            next: Cell<Option<&'a Node<'a>>>,
+        }
+
+        let arena = Arena::new();
+        let a = arena.alloc(Node {
+            value: 1,
+            next: Cell::new(None),
+        });
+        let b = arena.alloc(Node {
+            value: 2,
+            next: Cell::new(Some(a)),
+        });
+        a.next.set(Some(b));
+        assert_eq!(a.next.get().unwrap().next.get().unwrap().value, 1);
+    }
+}
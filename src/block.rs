@@ -0,0 +1,136 @@
+use alloc::alloc::{alloc, dealloc, GlobalAlloc, Layout};
+use core::ptr::NonNull;
+#[cfg(any(feature = "zeroize", all(feature = "secure", unix)))]
+use core::{cmp, mem};
+#[cfg(feature = "poison")]
+use core::{ops::Range, ptr};
+
+use crate::{sanitizer, telemetry, ArenaAllocError};
+
+/// The byte new blocks are filled with, so that reading memory that was never
+/// written stands out.
+#[cfg(feature = "poison")]
+const FRESH: u8 = 0xA5;
+
+/// The byte that memory is filled with once a reset or rewind gave it up, so
+/// that reading through a dangling reference stands out.
+#[cfg(feature = "poison")]
+const FREED: u8 = 0xDE;
+
+/// The global allocator, the default source of arena blocks.
+///
+/// Arenas take their blocks from any [`GlobalAlloc`] passed to `new_in`, e.g.
+/// a jemalloc arena dedicated to one subsystem. `Global` forwards to whatever
+/// is registered as `#[global_allocator]`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+unsafe impl GlobalAlloc for Global {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        dealloc(ptr, layout)
+    }
+}
+
+/// A chunk of memory obtained from a block allocator.
+///
+/// It is not released on drop: the owner hands it back to the allocator it
+/// came from with [`free`](Self::free).
+pub(crate) struct RawBlock {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl RawBlock {
+    /// Allocates a block of `size` bytes aligned to `align` from `a`.
+    pub(crate) fn try_new_in<A: GlobalAlloc>(
+        size: usize,
+        align: usize,
+        a: &A,
+    ) -> Result<Self, ArenaAllocError> {
+        let layout =
+            Layout::from_size_align(size, align).map_err(|_| ArenaAllocError::CapacityOverflow)?;
+        debug_assert!(layout.size() > 0);
+        let ptr = NonNull::new(unsafe { a.alloc(layout) })
+            .ok_or(ArenaAllocError::AllocFailed { layout })?;
+        #[cfg(feature = "poison")]
+        unsafe {
+            ptr::write_bytes(ptr.as_ptr(), FRESH, size)
+        };
+        telemetry::block_allocated(size);
+        Ok(Self { ptr, layout })
+    }
+
+    pub(crate) fn ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Fills the bytes of `range`, which held values that are gone now, with
+    /// [`FREED`].
+    ///
+    /// The range may cover alignment padding that the sanitizers were told is
+    /// off limits, so it is made addressable first.
+    #[cfg(feature = "poison")]
+    pub(crate) unsafe fn poison(&self, range: Range<usize>) {
+        debug_assert!(range.start <= range.end && range.end <= self.size());
+        let len = range.end - range.start;
+        let start = self.ptr().add(range.start);
+        sanitizer::mark_addressable(start, len);
+        ptr::write_bytes(start, FREED, len)
+    }
+
+    /// Lets the OS reclaim the physical pages that lie entirely inside the
+    /// block. Their content is lost, but they stay mapped.
+    #[cfg(all(feature = "madvise", unix))]
+    pub(crate) unsafe fn discard(&self) {
+        let page = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+        let start = self.ptr().add(self.ptr().align_offset(page));
+        let end = self.ptr().addr() + self.size();
+        if start.addr() < end {
+            let len = (end - start.addr()) & !(page - 1);
+            #[cfg(target_os = "linux")]
+            let advice = libc::MADV_DONTNEED;
+            // Elsewhere `MADV_DONTNEED` may not release anything.
+            #[cfg(not(target_os = "linux"))]
+            let advice = libc::MADV_FREE;
+            libc::madvise(start.cast(), len, advice);
+        }
+    }
+
+    /// Returns the block to `a`, which must be the allocator it came from.
+    pub(crate) unsafe fn free<A: GlobalAlloc>(self, a: &A) {
+        telemetry::block_released(self.size());
+        sanitizer::mark_addressable(self.ptr(), self.size());
+        #[cfg(feature = "zeroize")]
+        wipe(self.ptr(), self.size());
+        a.dealloc(self.ptr(), self.layout)
+    }
+}
+
+/// Overwrites `len` bytes at `ptr` with zeros.
+///
+/// The writes are volatile so that they aren't optimized out as dead stores
+/// right before the memory is released.
+#[cfg(any(feature = "zeroize", all(feature = "secure", unix)))]
+pub(crate) unsafe fn wipe(ptr: *mut u8, len: usize) {
+    let word = mem::size_of::<usize>();
+    let head = cmp::min(ptr.align_offset(word), len);
+    let words = (len - head) / word;
+    for i in 0..head {
+        ptr.add(i).write_volatile(0);
+    }
+    let body = ptr.add(head).cast::<usize>();
+    for i in 0..words {
+        body.add(i).write_volatile(0);
+    }
+    for i in head + words * word..len {
+        ptr.add(i).write_volatile(0);
+    }
+}
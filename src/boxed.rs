@@ -0,0 +1,149 @@
+//! An owning pointer to a value stored in a [`Bump`].
+
+use core::alloc::GlobalAlloc;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use crate::bump::Bump;
+
+/// Owns a value whose storage lives in a [`Bump`].
+///
+/// Unlike values allocated with [`Bump::alloc`], the value is dropped when
+/// the box is, so destructors that release resources such as file handles
+/// run at the right time. The memory itself is only reclaimed with the arena.
+pub struct ArenaBox<'a, T: ?Sized> {
+    ptr: NonNull<T>,
+    // The box owns a `T` and borrows the arena.
+    _marker: PhantomData<(&'a T, T)>,
+}
+
+impl<'a, T> ArenaBox<'a, T> {
+    pub fn new_in<A: GlobalAlloc>(value: T, bump: &'a Bump<A>) -> Self {
+        unsafe { Self::from_raw(bump.alloc(value)) }
+    }
+
+    /// Moves the value out of the box.
+    pub fn into_inner(b: Self) -> T {
+        let b = ManuallyDrop::new(b);
+        unsafe { ptr::read(b.ptr.as_ptr()) }
+    }
+}
+
+impl<'a, T: ?Sized> ArenaBox<'a, T> {
+    /// Takes ownership of a value already in the arena, e.g. a slice from
+    /// [`Bump::alloc_from_iter`].
+    ///
+    /// # Safety
+    ///
+    /// The value must not be used or dropped through any other path
+    /// afterwards.
+    pub unsafe fn from_raw(value: &'a mut T) -> Self {
+        Self {
+            ptr: NonNull::from(value),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Gives up ownership, leaving the value in the arena without ever
+    /// dropping it.
+    pub fn leak(b: Self) -> &'a mut T {
+        let b = ManuallyDrop::new(b);
+        unsafe { &mut *b.ptr.as_ptr() }
+    }
+}
+
+impl<T: ?Sized> Drop for ArenaBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+    }
+}
+
+impl<T: ?Sized> Deref for ArenaBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for ArenaBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ArenaBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for ArenaBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for ArenaBox<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for ArenaBox<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::ArenaBox;
+    use crate::bump::Bump;
+
+    struct D<'c>(&'c Cell<usize>);
+
+    impl Drop for D<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn drops_with_the_box() {
+        let drops = Cell::new(0);
+        let bump = Bump::new();
+        let b = ArenaBox::new_in(D(&drops), &bump);
+        bump.alloc(D(&drops));
+        drop(b);
+        assert_eq!(drops.get(), 1);
+        drop(bump);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn into_inner_and_leak() {
+        let drops = Cell::new(0);
+        let bump = Bump::new();
+        let d = ArenaBox::into_inner(ArenaBox::new_in(D(&drops), &bump));
+        assert_eq!(drops.get(), 0);
+        drop(d);
+        assert_eq!(drops.get(), 1);
+
+        let leaked = ArenaBox::leak(ArenaBox::new_in(D(&drops), &bump));
+        assert_eq!(leaked.0.get(), 1);
+        drop(bump);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn slice() {
+        let drops = Cell::new(0);
+        let bump = Bump::new();
+        let b = unsafe { ArenaBox::from_raw(bump.alloc_from_iter((0..5).map(|_| D(&drops)))) };
+        assert_eq!(b.len(), 5);
+        drop(b);
+        assert_eq!(drops.get(), 5);
+    }
+}
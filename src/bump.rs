@@ -0,0 +1,1970 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::any;
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::fmt::{self, Write};
+use core::mem::{self, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::str;
+
+use crate::block::RawBlock;
+#[cfg(feature = "backtrace")]
+use crate::trace::{AllocReport, Traces};
+use crate::{sanitizer, telemetry, ArenaAllocError, ArenaObserver, ArenaStats, Global};
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+
+/// Written right after each allocation by [`Bump::set_canaries`].
+#[cfg(feature = "canary")]
+const CANARY: [u8; 8] = *b"\xCA\x9A\x2F\x5E\xB1\xD0\x73\xE6";
+
+struct Block {
+    raw: RawBlock,
+    // Bytes in use, only kept up to date for blocks that are not being
+    // filled.
+    used: usize,
+}
+
+/// A position in a [`Bump`], returned by [`Bump::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Marker {
+    block: usize,
+    offset: usize,
+    large: usize,
+    #[cfg(feature = "canary")]
+    canaries: usize,
+    #[cfg(feature = "valgrind")]
+    allocations: usize,
+    #[cfg(feature = "backtrace")]
+    traces: usize,
+}
+
+/// A handle to a value under construction by [`Bump::alloc_cyclic`], which
+/// can be stored in the value itself.
+pub struct Cyclic<'a, T> {
+    ptr: *const T,
+    ready: &'a Cell<bool>,
+}
+
+impl<'a, T> Cyclic<'a, T> {
+    /// Returns the value, or `None` while it is still being constructed.
+    pub fn get(self) -> Option<&'a T> {
+        if self.ready.get() {
+            Some(unsafe { &*self.ptr })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Clone for Cyclic<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Cyclic<'_, T> {}
+
+impl<T> fmt::Debug for Cyclic<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cyclic")
+            .field("ptr", &self.ptr)
+            .field("ready", &self.ready.get())
+            .finish()
+    }
+}
+
+// The bump pointer and the counters live in `Cell`s so that allocating out of
+// the current block never touches a borrow flag. The block lists are only
+// borrowed on the slow path.
+struct Internal<A: GlobalAlloc> {
+    // Where the blocks come from and go back to.
+    backing: A,
+    blocks: RefCell<Vec<Block>>,
+    // Index of the block being filled. Blocks after it are spare blocks kept
+    // around by `reset`.
+    current: Cell<usize>,
+    // Size of the next fresh block, doubled after each one up to
+    // `max_block_size`.
+    block_size: Cell<usize>,
+    max_block_size: usize,
+    bytes: Cell<usize>,
+    // Values larger than `block_size` get an exactly-sized block of their own
+    // so that they don't cut the current block short.
+    large: RefCell<Vec<RawBlock>>,
+    large_bytes: Cell<usize>,
+    alloc_ptr: Cell<*mut u8>,
+    alloc_end: Cell<*mut u8>,
+    // Start of the current block, so that checkpoints don't need to borrow
+    // `blocks`.
+    current_start: Cell<*mut u8>,
+    // Only brought up to date before the used bytes can go down or when
+    // leaving a block.
+    high_water_mark: Cell<usize>,
+    // Upper bound on `bytes + large_bytes`, `usize::MAX` when unlimited.
+    limit: usize,
+    // Called instead of `ArenaAllocError::handle` by infallible allocations.
+    error_handler: Option<fn(ArenaAllocError) -> !>,
+    // Whether `reset` hands the pages of the used blocks back to the OS.
+    #[cfg(all(feature = "madvise", unix))]
+    discard_on_reset: bool,
+    // The start and size of each allocation followed by a canary, when they
+    // are turned on.
+    #[cfg(feature = "canary")]
+    canaries: Option<RefCell<Vec<(*const u8, usize)>>>,
+    // The start of each allocation reported to Valgrind, when running under
+    // it.
+    #[cfg(feature = "valgrind")]
+    allocations: Option<RefCell<Vec<*const u8>>>,
+    // A backtrace per allocation, when they are turned on.
+    #[cfg(feature = "backtrace")]
+    traces: Option<Traces>,
+    observer: Option<Box<dyn ArenaObserver + Send>>,
+    // A fresh block not yet reported to the observer.
+    pending_block: Cell<Option<(*const u8, usize)>>,
+}
+
+// The pointers all point into blocks owned by the `Bump`, so it can be sent
+// to another thread. It is never `Sync`, see `arena::Internal`.
+unsafe impl<A: GlobalAlloc + Send> Send for Internal<A> {}
+
+impl<A: GlobalAlloc> Internal<A> {
+    fn new(block_size: usize, max_block_size: usize, backing: A) -> Self {
+        Self {
+            backing,
+            blocks: RefCell::new(Vec::new()),
+            current: Cell::new(0),
+            block_size: Cell::new(block_size),
+            max_block_size,
+            bytes: Cell::new(0),
+            large: RefCell::new(Vec::new()),
+            large_bytes: Cell::new(0),
+            alloc_ptr: Cell::new(ptr::null_mut()),
+            alloc_end: Cell::new(ptr::null_mut()),
+            current_start: Cell::new(ptr::null_mut()),
+            high_water_mark: Cell::new(0),
+            limit: usize::MAX,
+            error_handler: None,
+            #[cfg(all(feature = "madvise", unix))]
+            discard_on_reset: false,
+            #[cfg(feature = "canary")]
+            canaries: None,
+            #[cfg(feature = "valgrind")]
+            allocations: sanitizer::running_on_valgrind().then(|| RefCell::new(Vec::new())),
+            #[cfg(feature = "backtrace")]
+            traces: None,
+            observer: None,
+            pending_block: Cell::new(None),
+        }
+    }
+
+    /// `type_name` describes the allocation in backtrace reports.
+    unsafe fn alloc_layout(&self, layout: Layout, type_name: &'static str) -> *mut u8 {
+        match self.try_alloc_layout(layout, type_name) {
+            Ok(ptr) => ptr,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    #[cfg_attr(not(feature = "backtrace"), allow(unused_variables))]
+    unsafe fn try_alloc_layout(
+        &self,
+        layout: Layout,
+        type_name: &'static str,
+    ) -> Result<*mut u8, ArenaAllocError> {
+        #[cfg(feature = "canary")]
+        let ptr = match &self.canaries {
+            Some(canaries) if layout.size() > 0 => self.bump_guarded(layout, canaries)?,
+            _ => self.bump_layout(layout)?,
+        };
+        #[cfg(not(feature = "canary"))]
+        let ptr = self.bump_layout(layout)?;
+        #[cfg(feature = "backtrace")]
+        if let Some(traces) = self.traces.as_ref().filter(|_| layout.size() > 0) {
+            traces.record(ptr, layout.size(), type_name);
+        }
+        if let Some(observer) = &self.observer {
+            if let Some((block, size)) = self.pending_block.take() {
+                observer.on_block(block, size);
+            }
+            observer.on_alloc(ptr, layout.size());
+        }
+        Ok(ptr)
+    }
+
+    #[inline(always)]
+    unsafe fn bump_layout(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        if layout.size() == 0 {
+            return Ok(ptr::without_provenance_mut(layout.align()));
+        }
+        let alloc_ptr = self.alloc_ptr.get();
+        if !alloc_ptr.is_null() {
+            let offset = alloc_ptr.align_offset(layout.align());
+            let remaining = self.alloc_end.get().addr() - alloc_ptr.addr();
+            if offset <= remaining && layout.size() <= remaining - offset {
+                let ptr = alloc_ptr.add(offset);
+                self.alloc_ptr.set(ptr.add(layout.size()));
+                self.mark_allocated(ptr, layout.size());
+                return Ok(ptr);
+            }
+        }
+        self.alloc_slow(layout)
+    }
+
+    /// Allocates `layout` followed by a canary. The canary also keeps the
+    /// allocation from being resized in place, as it is not the last thing in
+    /// the block anymore.
+    #[cfg(feature = "canary")]
+    unsafe fn bump_guarded(
+        &self,
+        layout: Layout,
+        canaries: &RefCell<Vec<(*const u8, usize)>>,
+    ) -> Result<*mut u8, ArenaAllocError> {
+        let padded = layout
+            .size()
+            .checked_add(CANARY.len())
+            .and_then(|size| Layout::from_size_align(size, layout.align()).ok())
+            .ok_or(ArenaAllocError::CapacityOverflow)?;
+        let ptr = self.bump_layout(padded)?;
+        ptr::copy_nonoverlapping(CANARY.as_ptr(), ptr.add(layout.size()), CANARY.len());
+        canaries.borrow_mut().push((ptr, layout.size()));
+        Ok(ptr)
+    }
+
+    /// Checks the canaries of the allocations past the first `from`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of them was overwritten.
+    #[cfg(feature = "canary")]
+    fn check_canaries(&self, from: usize) {
+        let Some(canaries) = &self.canaries else {
+            return;
+        };
+        let broken = canaries
+            .borrow()
+            .iter()
+            .skip(from)
+            .copied()
+            .find(|&(ptr, size)| unsafe {
+                slice::from_raw_parts(ptr.add(size), CANARY.len()) != CANARY
+            });
+        if let Some((ptr, size)) = broken {
+            // Reported once, so that dropping the arena while unwinding
+            // doesn't panic again.
+            canaries.borrow_mut().clear();
+            panic!("buffer overrun past the {size}-byte allocation at {ptr:p}");
+        }
+    }
+
+    /// Checks and forgets the canaries past the first `keep`, before the
+    /// memory they guard is given up.
+    #[cfg(feature = "canary")]
+    fn pop_canaries(&self, keep: usize) {
+        self.check_canaries(keep);
+        if let Some(canaries) = &self.canaries {
+            canaries.borrow_mut().truncate(keep);
+        }
+    }
+
+    #[cold]
+    unsafe fn alloc_slow(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        if layout.size() > self.block_size.get() {
+            return self.alloc_large(layout);
+        }
+
+        self.new_block(layout)?;
+        let ptr = self.alloc_ptr.get();
+        self.alloc_ptr.set(ptr.add(layout.size()));
+        self.mark_allocated(ptr, layout.size());
+        Ok(ptr)
+    }
+
+    /// Tells the memory checkers that `size` bytes at `ptr` in the current
+    /// block were handed out.
+    #[inline(always)]
+    unsafe fn mark_allocated(&self, ptr: *mut u8, size: usize) {
+        sanitizer::mark_addressable(ptr, size);
+        #[cfg(feature = "valgrind")]
+        if let Some(allocations) = &self.allocations {
+            sanitizer::malloclike(ptr, size);
+            allocations.borrow_mut().push(ptr);
+        }
+    }
+
+    /// Ends the allocations reported to Valgrind past the first `keep`.
+    #[cfg(feature = "valgrind")]
+    fn free_allocations(&self, keep: usize) {
+        if let Some(allocations) = &self.allocations {
+            for ptr in allocations.borrow_mut().drain(keep..) {
+                unsafe { sanitizer::freelike(ptr) };
+            }
+        }
+    }
+
+    fn alloc_large(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        let size = self.budget(layout.size(), layout.size())?;
+        let block = RawBlock::try_new_in(size, layout.align(), &self.backing)?;
+        let ptr = block.ptr();
+        self.large_bytes.set(self.large_bytes.get() + block.size());
+        self.pending_block.set(Some((ptr, block.size())));
+        self.large.borrow_mut().push(block);
+        Ok(ptr)
+    }
+
+    /// Resizes the allocation at `ptr` in place. This only succeeds when it
+    /// is the most recent allocation and the current block has room.
+    unsafe fn resize_last(&self, ptr: *mut u8, old_size: usize, new_size: usize) -> bool {
+        // A large value's block may end right where the current block starts.
+        if ptr.is_null()
+            || ptr.addr() < self.current_start.get().addr()
+            || ptr.add(old_size) != self.alloc_ptr.get()
+        {
+            return false;
+        }
+        if new_size > self.alloc_end.get().addr() - ptr.addr() {
+            return false;
+        }
+        self.alloc_ptr.set(ptr.add(new_size));
+        if new_size > old_size {
+            sanitizer::mark_addressable(ptr.add(old_size), new_size - old_size);
+        } else {
+            sanitizer::mark_unaddressable(ptr.add(new_size), old_size - new_size);
+        }
+        #[cfg(feature = "valgrind")]
+        if let Some(allocations) = &self.allocations {
+            let mut allocations = allocations.borrow_mut();
+            if allocations.last() == Some(&ptr.cast_const()) {
+                if new_size == 0 {
+                    allocations.pop();
+                    sanitizer::freelike(ptr);
+                } else {
+                    sanitizer::resize_in_place(ptr, old_size, new_size);
+                }
+            }
+        }
+        #[cfg(feature = "backtrace")]
+        if let Some(traces) = &self.traces {
+            traces.resize_last(ptr, new_size);
+        }
+        true
+    }
+
+    /// Reports the failure of an infallible allocation.
+    #[cold]
+    fn fail(&self, e: ArenaAllocError) -> ! {
+        match self.error_handler {
+            Some(handler) => handler(e),
+            None => e.handle(),
+        }
+    }
+
+    /// Returns the size of the next block: `wanted` bytes, cut down to what
+    /// is left of the limit but never below `needed`.
+    fn budget(&self, needed: usize, wanted: usize) -> Result<usize, ArenaAllocError> {
+        let left = self.limit - self.bytes.get() - self.large_bytes.get();
+        if needed > left {
+            return Err(ArenaAllocError::LimitExceeded { limit: self.limit });
+        }
+        Ok(cmp::min(wanted, left))
+    }
+
+    /// Moves to a block that can hold `layout`, reusing the next spare block
+    /// when it is large enough.
+    fn new_block(&self, layout: Layout) -> Result<(), ArenaAllocError> {
+        let mut blocks = self.blocks.borrow_mut();
+        let next = if blocks.is_empty() {
+            0
+        } else {
+            self.current.get() + 1
+        };
+        if let Some(block) = blocks.get_mut(self.current.get()) {
+            block.used = self.current_used();
+        }
+        self.record_high_water(&blocks);
+        let reusable = blocks.get(next).is_some_and(|b| {
+            b.raw.size() >= layout.size() && b.raw.ptr().align_offset(layout.align()) == 0
+        });
+        if !reusable {
+            let block_size = self.block_size.get();
+            let size = self.budget(layout.size(), cmp::max(layout.size(), block_size))?;
+            let raw = RawBlock::try_new_in(size, layout.align(), &self.backing)?;
+            unsafe { sanitizer::mark_unaddressable(raw.ptr(), raw.size()) };
+            self.bytes.set(self.bytes.get() + raw.size());
+            self.pending_block.set(Some((raw.ptr(), raw.size())));
+            self.block_size
+                .set(cmp::min(block_size.saturating_mul(2), self.max_block_size));
+            blocks.insert(next, Block { raw, used: 0 });
+        }
+        self.current.set(next);
+        self.rewind_to_current(&blocks);
+        Ok(())
+    }
+
+    /// Points the bump pointer at the start of the current block.
+    fn rewind_to_current(&self, blocks: &[Block]) {
+        let block = &blocks[self.current.get()].raw;
+        self.current_start.set(block.ptr());
+        self.alloc_ptr.set(block.ptr());
+        self.alloc_end.set(unsafe { block.ptr().add(block.size()) });
+    }
+
+    /// Returns the bytes in use in the current block.
+    fn current_used(&self) -> usize {
+        self.alloc_ptr.get().addr() - self.current_start.get().addr()
+    }
+
+    fn used_bytes(&self, blocks: &[Block]) -> usize {
+        let current = cmp::min(self.current.get(), blocks.len());
+        let filled: usize = blocks[..current].iter().map(|b| b.used).sum();
+        filled + self.current_used() + self.large_bytes.get()
+    }
+
+    fn contains(&self, addr: usize) -> bool {
+        let within = |ptr: *mut u8, used: usize| addr >= ptr.addr() && addr < ptr.addr() + used;
+        let blocks = self.blocks.borrow();
+        let current = self.current.get();
+        blocks
+            .iter()
+            .take(current + 1)
+            .enumerate()
+            .any(|(i, block)| {
+                let used = if i == current {
+                    self.current_used()
+                } else {
+                    block.used
+                };
+                within(block.raw.ptr(), used)
+            })
+            || self
+                .large
+                .borrow()
+                .iter()
+                .any(|raw| within(raw.ptr(), raw.size()))
+    }
+
+    fn record_high_water(&self, blocks: &[Block]) {
+        let used = self.used_bytes(blocks);
+        if used > self.high_water_mark.get() {
+            self.high_water_mark.set(used);
+        }
+    }
+
+    fn stats(&self) -> ArenaStats {
+        let blocks = self.blocks.borrow();
+        let current = cmp::min(self.current.get(), blocks.len());
+        let used = self.used_bytes(&blocks);
+        ArenaStats {
+            blocks: blocks.len() + self.large.borrow().len(),
+            bytes_reserved: self.bytes.get() + self.large_bytes.get(),
+            bytes_used: used,
+            wasted_bytes: blocks[..current]
+                .iter()
+                .map(|b| b.raw.size() - b.used)
+                .sum(),
+            high_water_mark: cmp::max(self.high_water_mark.get(), used),
+        }
+    }
+
+    fn checkpoint(&self) -> Marker {
+        Marker {
+            block: self.current.get(),
+            offset: self.current_used(),
+            large: self.large.borrow().len(),
+            #[cfg(feature = "canary")]
+            canaries: self
+                .canaries
+                .as_ref()
+                .map_or(0, |canaries| canaries.borrow().len()),
+            #[cfg(feature = "valgrind")]
+            allocations: self
+                .allocations
+                .as_ref()
+                .map_or(0, |allocations| allocations.borrow().len()),
+            #[cfg(feature = "backtrace")]
+            traces: self.traces.as_ref().map_or(0, Traces::len),
+        }
+    }
+
+    fn rewind(&mut self, marker: Marker) {
+        let current = self.checkpoint();
+        assert!(
+            marker.large <= current.large,
+            "marker is ahead of the arena"
+        );
+        #[cfg(feature = "canary")]
+        self.pop_canaries(marker.canaries);
+        #[cfg(feature = "backtrace")]
+        if let Some(traces) = &self.traces {
+            traces.truncate(marker.traces);
+        }
+        self.record_high_water(&self.blocks.borrow());
+        self.free_large(marker.large);
+        if self.blocks.get_mut().is_empty() {
+            return;
+        }
+        assert!(
+            marker.block < current.block
+                || (marker.block == current.block && marker.offset <= current.offset),
+            "marker is ahead of the arena"
+        );
+        #[cfg(feature = "valgrind")]
+        self.free_allocations(marker.allocations);
+        #[cfg(any(feature = "poison", feature = "asan", feature = "valgrind"))]
+        self.poison_since(marker);
+        self.current.set(marker.block);
+        self.rewind_to_current(&self.blocks.borrow());
+        self.alloc_ptr
+            .set(unsafe { self.alloc_ptr.get().add(marker.offset) });
+    }
+
+    /// Poisons the bytes allocated in the blocks after `marker` was taken and
+    /// marks them off limits for the sanitizers.
+    #[cfg(any(feature = "poison", feature = "asan", feature = "valgrind"))]
+    fn poison_since(&self, marker: Marker) {
+        let blocks = self.blocks.borrow();
+        let current = self.current.get();
+        for (i, block) in blocks
+            .iter()
+            .enumerate()
+            .take(current + 1)
+            .skip(marker.block)
+        {
+            let start = if i == marker.block { marker.offset } else { 0 };
+            let end = if i == current {
+                self.current_used()
+            } else {
+                block.used
+            };
+            #[cfg(feature = "poison")]
+            unsafe {
+                block.raw.poison(start..end)
+            };
+            unsafe { sanitizer::mark_unaddressable(block.raw.ptr().add(start), end - start) };
+        }
+    }
+
+    /// Releases the dedicated blocks of large values past the first `len`.
+    fn free_large(&mut self, len: usize) {
+        for block in self.large.get_mut().drain(len..) {
+            self.large_bytes.set(self.large_bytes.get() - block.size());
+            unsafe { block.free(&self.backing) };
+        }
+    }
+
+    fn reset(&mut self) {
+        #[cfg(feature = "canary")]
+        self.pop_canaries(0);
+        #[cfg(feature = "backtrace")]
+        if let Some(traces) = &self.traces {
+            traces.truncate(0);
+        }
+        self.record_high_water(&self.blocks.borrow());
+        self.free_large(0);
+        telemetry::reset(self.bytes.get());
+        #[cfg(feature = "valgrind")]
+        self.free_allocations(0);
+        #[cfg(any(feature = "poison", feature = "asan", feature = "valgrind"))]
+        self.poison_since(Marker {
+            block: 0,
+            offset: 0,
+            large: 0,
+            #[cfg(feature = "canary")]
+            canaries: 0,
+            #[cfg(feature = "valgrind")]
+            allocations: 0,
+            #[cfg(feature = "backtrace")]
+            traces: 0,
+        });
+        #[cfg(all(feature = "madvise", unix))]
+        if self.discard_on_reset {
+            let blocks = self.blocks.get_mut();
+            let used = cmp::min(self.current.get() + 1, blocks.len());
+            for block in &blocks[..used] {
+                unsafe { block.raw.discard() };
+            }
+        }
+        self.current.set(0);
+        let blocks = self.blocks.borrow();
+        if !blocks.is_empty() {
+            self.rewind_to_current(&blocks);
+        }
+    }
+}
+
+impl<A: GlobalAlloc> Drop for Internal<A> {
+    fn drop(&mut self) {
+        #[cfg(feature = "canary")]
+        self.check_canaries(0);
+        #[cfg(feature = "valgrind")]
+        self.free_allocations(0);
+        telemetry::dropped(self.bytes.get() + self.large_bytes.get());
+        for block in self.blocks.get_mut().drain(..) {
+            unsafe { block.raw.free(&self.backing) };
+        }
+        for block in self.large.get_mut().drain(..) {
+            unsafe { block.free(&self.backing) };
+        }
+    }
+}
+
+/// An untyped bump arena that can hold values of any sized type.
+///
+/// Values are packed into a shared chain of blocks, each one aligned for its
+/// own type. Destructors of allocated values are never run: the memory is
+/// released all at once when the `Bump` is dropped.
+pub struct Bump<A: GlobalAlloc = Global> {
+    internal: Internal<A>,
+}
+
+impl Bump {
+    pub fn new() -> Self {
+        Self::with_block_growth(BLOCK_SIZE, MAX_BLOCK_SIZE)
+    }
+
+    /// Creates an arena that requests blocks of `block_size` bytes from the
+    /// system allocator. Values larger than that get a block of their own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    pub fn with_block_size(block_size: usize) -> Self {
+        Self::with_block_growth(block_size, block_size)
+    }
+
+    /// Creates an arena whose first block has `initial` bytes and where each
+    /// following block doubles in size until it reaches `max`.
+    ///
+    /// [`new`](Self::new) starts at 4 KiB and grows up to 2 MiB.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero or larger than `max`.
+    pub fn with_block_growth(initial: usize, max: usize) -> Self {
+        Self::with_block_growth_in(initial, max, Global)
+    }
+
+    /// Creates an arena that never holds more than `limit` bytes of blocks.
+    /// Allocations past it panic, or fail with
+    /// [`ArenaAllocError::LimitExceeded`] through [`try_alloc`](Self::try_alloc).
+    pub fn with_limit(limit: usize) -> Self {
+        let mut bump = Self::new();
+        bump.internal.limit = limit;
+        bump
+    }
+}
+
+impl<A: GlobalAlloc> Bump<A> {
+    /// Creates an arena that takes its blocks from `backing` instead of the
+    /// global allocator.
+    pub fn new_in(backing: A) -> Self {
+        Self::with_block_growth_in(BLOCK_SIZE, MAX_BLOCK_SIZE, backing)
+    }
+
+    /// Like [`with_block_growth`](Bump::with_block_growth), with blocks from
+    /// `backing`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero or larger than `max`.
+    pub fn with_block_growth_in(initial: usize, max: usize, backing: A) -> Self {
+        assert!(initial > 0, "block size must be non-zero");
+        assert!(initial <= max, "initial block size exceeds the maximum");
+        Self {
+            internal: Internal::new(initial, max, backing),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, data: T) -> &mut T {
+        self.alloc_with(|| data)
+    }
+
+    /// Allocates space for a `T` and writes the result of `f` straight into
+    /// it, which lets the optimizer construct large values in place.
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with<T, F: FnOnce() -> T>(&self, f: F) -> &mut T {
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::new::<T>(), any::type_name::<T>())
+                .cast::<T>();
+            ptr::write(ptr, f());
+            &mut *ptr
+        }
+    }
+
+    /// Allocates the value returned by `f`, which receives a handle to the
+    /// slot being filled, so that the value can point to itself, e.g. as the
+    /// parent of its children.
+    ///
+    /// The handle resolves once `f` has returned, like the `Weak` of
+    /// [`Rc::new_cyclic`](alloc::rc::Rc::new_cyclic). The value is returned
+    /// by shared reference since the handles alias it.
+    pub fn alloc_cyclic<'a, T, F: FnOnce(Cyclic<'a, T>) -> T>(&'a self, f: F) -> &'a T {
+        let ready = &*self.alloc(Cell::new(false));
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::new::<T>(), any::type_name::<T>())
+                .cast::<T>();
+            ptr::write(ptr, f(Cyclic { ptr, ready }));
+            ready.set(true);
+            &*ptr
+        }
+    }
+
+    /// Like [`alloc`](Self::alloc), but returns an error instead of aborting
+    /// when a new block cannot be obtained.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, data: T) -> Result<&mut T, ArenaAllocError> {
+        unsafe {
+            let ptr = self
+                .internal
+                .try_alloc_layout(Layout::new::<T>(), any::type_name::<T>())?
+                .cast::<T>();
+            ptr::write(ptr, data);
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Allocates `data` at an address that is a multiple of `align`, e.g. 32
+    /// or 64 bytes for SIMD loads. Alignments smaller than the one of `T` are
+    /// rounded up to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_aligned<T>(&self, data: T, align: usize) -> &mut T {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let layout = match Layout::from_size_align(
+            mem::size_of::<T>(),
+            cmp::max(align, mem::align_of::<T>()),
+        ) {
+            Ok(layout) => layout,
+            Err(_) => self.internal.fail(ArenaAllocError::CapacityOverflow),
+        };
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(layout, any::type_name::<T>())
+                .cast::<T>();
+            ptr::write(ptr, data);
+            &mut *ptr
+        }
+    }
+
+    /// Allocates uninitialized space for a `T`.
+    ///
+    /// Use [`MaybeUninit::write`] to initialize it and get back `&mut T`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_uninit<T>(&self) -> &mut MaybeUninit<T> {
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::new::<T>(), any::type_name::<T>())
+                .cast::<MaybeUninit<T>>();
+            &mut *ptr
+        }
+    }
+
+    /// Allocates uninitialized space for `len` values of `T`, e.g. a buffer
+    /// to be filled by a `read()` without zeroing it first.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_uninit<T>(&self, len: usize) -> &mut [MaybeUninit<T>] {
+        let layout = match Layout::array::<T>(len) {
+            Ok(layout) => layout,
+            Err(_) => self.internal.fail(ArenaAllocError::CapacityOverflow),
+        };
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(layout, any::type_name::<[T]>())
+                .cast::<MaybeUninit<T>>();
+            slice::from_raw_parts_mut(ptr, len)
+        }
+    }
+
+    /// Allocates uninitialized memory for `layout`, for containers built on
+    /// top of the arena.
+    ///
+    /// The memory stays valid until the arena is reset, rewound past it or
+    /// dropped. A zero-sized layout gets a dangling, well-aligned pointer.
+    pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        match self.try_alloc_layout(layout) {
+            Ok(ptr) => ptr,
+            Err(e) => self.internal.fail(e),
+        }
+    }
+
+    pub fn try_alloc_layout(&self, layout: Layout) -> Result<NonNull<u8>, ArenaAllocError> {
+        unsafe {
+            let ptr = self.internal.try_alloc_layout(layout, "<untyped>")?;
+            Ok(NonNull::new_unchecked(ptr))
+        }
+    }
+
+    /// Grows the allocation at `ptr` from `old` to `new` and returns its
+    /// address. It grows in place when it is the most recent allocation and
+    /// the current block has room, otherwise its contents are moved to a new
+    /// allocation.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this arena for `old`, and still be
+    /// valid. `new` must be at least as large as `old`.
+    pub unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<NonNull<u8>, ArenaAllocError> {
+        debug_assert!(new.size() >= old.size());
+        self.reallocate(ptr, old, new)
+    }
+
+    /// Shrinks the allocation at `ptr` from `old` to `new` and returns its
+    /// address, which only changes when `new` needs a stricter alignment.
+    /// The space given up is reclaimed if it is the most recent allocation.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this arena for `old`, and still be
+    /// valid. `new` must be no larger than `old`.
+    pub unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<NonNull<u8>, ArenaAllocError> {
+        debug_assert!(new.size() <= old.size());
+        self.reallocate(ptr, old, new)
+    }
+
+    /// Extends the allocation at `ptr` from `old` to `new` in place, if it is
+    /// the most recent allocation and the current block has room for it.
+    /// Returns `false`, leaving the allocation as it is, otherwise.
+    ///
+    /// This lets a slice or string be built at the tip of the arena one piece
+    /// at a time, without copying it each time it grows. The new bytes are
+    /// uninitialized. Nothing is grown in place while canaries are on, as the
+    /// canary sits between the allocation and the free space.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this arena for `old`, and still be
+    /// valid.
+    pub unsafe fn grow_last(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> bool {
+        #[cfg(feature = "canary")]
+        if self.internal.canaries.is_some() {
+            return false;
+        }
+        new.size() >= old.size()
+            && ptr.as_ptr().align_offset(new.align()) == 0
+            && self
+                .internal
+                .resize_last(ptr.as_ptr(), old.size(), new.size())
+    }
+
+    /// Resizes in place when `ptr` is the most recent allocation or when it
+    /// shrinks, otherwise moves the contents to a new allocation.
+    unsafe fn reallocate(
+        &self,
+        ptr: NonNull<u8>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<NonNull<u8>, ArenaAllocError> {
+        if ptr.as_ptr().align_offset(new.align()) == 0
+            && (self
+                .internal
+                .resize_last(ptr.as_ptr(), old.size(), new.size())
+                || new.size() <= old.size())
+        {
+            return Ok(ptr);
+        }
+        let new_ptr = self.try_alloc_layout(new)?;
+        ptr::copy_nonoverlapping(
+            ptr.as_ptr(),
+            new_ptr.as_ptr(),
+            cmp::min(old.size(), new.size()),
+        );
+        Ok(new_ptr)
+    }
+
+    /// Copies `src` into a contiguous run of arena memory.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T] {
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::for_value(src), any::type_name::<[T]>())
+                .cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            slice::from_raw_parts_mut(ptr, src.len())
+        }
+    }
+
+    /// Clones every element of `src` into a contiguous run of arena memory.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_clone<T: Clone>(&self, src: &[T]) -> &mut [T] {
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::for_value(src), any::type_name::<[T]>())
+                .cast::<T>();
+            for (i, x) in src.iter().enumerate() {
+                ptr::write(ptr.add(i), x.clone());
+            }
+            slice::from_raw_parts_mut(ptr, src.len())
+        }
+    }
+
+    /// Allocates a slice of `len` values, where the value at index `i` is
+    /// `f(i)`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_fill_with<T, F: FnMut(usize) -> T>(&self, len: usize, mut f: F) -> &mut [T] {
+        let slice = self.alloc_slice_uninit(len);
+        for (i, slot) in slice.iter_mut().enumerate() {
+            slot.write(f(i));
+        }
+        unsafe { &mut *(slice as *mut [MaybeUninit<T>] as *mut [T]) }
+    }
+
+    /// Allocates a slice of `len` copies of `value`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_fill_copy<T: Copy>(&self, len: usize, value: T) -> &mut [T] {
+        self.alloc_slice_fill_with(len, |_| value)
+    }
+
+    /// Moves every item of `iter` into a contiguous run of arena memory.
+    ///
+    /// The items are buffered first, so iterators of unknown length work and
+    /// the iterator is free to allocate from this arena while it runs.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_from_iter<T, I: IntoIterator<Item = T>>(&self, iter: I) -> &mut [T] {
+        let mut vec: Vec<T> = iter.into_iter().collect();
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::for_value(vec.as_slice()), any::type_name::<[T]>())
+                .cast::<T>();
+            ptr::copy_nonoverlapping(vec.as_ptr(), ptr, vec.len());
+            let len = vec.len();
+            vec.set_len(0);
+            slice::from_raw_parts_mut(ptr, len)
+        }
+    }
+
+    /// Like [`alloc`](Self::alloc), for callers that hold the arena
+    /// exclusively.
+    pub fn alloc_mut<T>(&mut self, data: T) -> &mut T {
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(Layout::new::<T>(), any::type_name::<T>())
+                .cast::<T>();
+            ptr::write(ptr, data);
+            &mut *ptr
+        }
+    }
+
+    /// Like [`alloc_from_iter`](Self::alloc_from_iter), but writes the items
+    /// straight into the arena when the iterator reports its exact length.
+    pub fn alloc_from_iter_mut<T, I: IntoIterator<Item = T>>(&mut self, iter: I) -> &mut [T] {
+        let iter = iter.into_iter();
+        let len = match iter.size_hint() {
+            (min, Some(max)) if min == max => min,
+            _ => return self.alloc_from_iter(iter),
+        };
+        let layout = match Layout::array::<T>(len) {
+            Ok(layout) => layout,
+            Err(_) => self.internal.fail(ArenaAllocError::CapacityOverflow),
+        };
+        unsafe {
+            let ptr = self
+                .internal
+                .alloc_layout(layout, any::type_name::<[T]>())
+                .cast::<T>();
+            let mut written = 0;
+            for x in iter.take(len) {
+                ptr::write(ptr.add(written), x);
+                written += 1;
+            }
+            slice::from_raw_parts_mut(ptr, written)
+        }
+    }
+
+    /// Copies `src` into the arena.
+    pub fn alloc_str(&self, src: &str) -> &str {
+        let bytes = self.alloc_slice_copy(src.as_bytes());
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Like [`alloc_str`](Self::alloc_str), for text edited in place, e.g.
+    /// with `make_ascii_lowercase`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str_mut(&self, src: &str) -> &mut str {
+        let bytes = self.alloc_slice_copy(src.as_bytes());
+        unsafe { str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    /// Formats `args` straight into arena memory, e.g.
+    /// `bump.alloc_fmt(format_args!("{}:{}", file, line))`.
+    ///
+    /// The text is written at the tip of the current block and grows in
+    /// place while it stays the most recent allocation.
+    pub fn alloc_fmt(&self, args: fmt::Arguments<'_>) -> &str {
+        if let Some(s) = args.as_str() {
+            return self.alloc_str(s);
+        }
+
+        let mut writer = StrWriter {
+            bump: self,
+            ptr: ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        };
+        writer
+            .write_fmt(args)
+            .expect("a formatting trait implementation returned an error");
+        unsafe {
+            self.internal
+                .resize_last(writer.ptr, writer.cap, writer.len);
+            if writer.len == 0 {
+                return "";
+            }
+            str::from_utf8_unchecked(slice::from_raw_parts(writer.ptr, writer.len))
+        }
+    }
+
+    /// Forgets every allocation but keeps the blocks for reuse. Dedicated
+    /// blocks of large values are released.
+    pub fn reset(&mut self) {
+        self.internal.reset();
+    }
+
+    /// Returns the current position, to be passed to [`rewind`](Self::rewind)
+    /// later.
+    pub fn checkpoint(&self) -> Marker {
+        self.internal.checkpoint()
+    }
+
+    /// Forgets everything allocated after `marker` was taken and makes that
+    /// space available again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena was already rewound past `marker`.
+    pub fn rewind(&mut self, marker: Marker) {
+        self.internal.rewind(marker);
+    }
+
+    /// Returns the number of bytes obtained from the system allocator,
+    /// including the dedicated blocks of large values.
+    pub fn bytes_allocated(&self) -> usize {
+        self.internal.bytes.get() + self.internal.large_bytes.get()
+    }
+
+    /// Like [`bytes_allocated`](Self::bytes_allocated), plus the heap memory
+    /// taken up by the lists of blocks.
+    pub fn memory_usage(&self) -> usize {
+        let internal = &self.internal;
+        self.bytes_allocated()
+            + internal.blocks.borrow().capacity() * mem::size_of::<Block>()
+            + internal.large.borrow().capacity() * mem::size_of::<RawBlock>()
+    }
+
+    /// Returns a snapshot of the block and byte counts of the arena.
+    /// Dedicated blocks of large values count as fully used.
+    pub fn stats(&self) -> ArenaStats {
+        self.internal.stats()
+    }
+
+    /// Returns whether `ptr` points into memory handed out by this arena, e.g.
+    /// to check in a debug assertion that a node belongs to the right graph.
+    ///
+    /// This walks the blocks, so it takes time proportional to their number.
+    /// Zero-sized values have no address of their own, and are never found.
+    pub fn contains<T: ?Sized>(&self, ptr: *const T) -> bool {
+        self.internal.contains(ptr.cast::<u8>().addr())
+    }
+
+    /// Iterates over the bytes in use in each block, so that what was built in
+    /// the arena can be handed to a vectored write or a hasher without being
+    /// copied out first.
+    ///
+    /// The blocks come in the order they were filled, followed by the
+    /// dedicated blocks of large values. Empty blocks are skipped. The chunks
+    /// include the padding between values, which is uninitialized; when only
+    /// bytes and strings were allocated there is none, and the chunks can be
+    /// read as `&[u8]`.
+    ///
+    /// ```
+    /// use std::io::{IoSlice, Write};
+    /// use std::slice;
+    ///
+    /// use rusty_arena::bump::Bump;
+    ///
+    /// let mut bump = Bump::new();
+    /// bump.alloc_str("HTTP/1.1 200 OK\r\n");
+    /// bump.alloc_str("content-length: 0\r\n\r\n");
+    ///
+    /// // Only strings were allocated, so every byte is initialized.
+    /// let slices: Vec<_> = bump
+    ///     .iter_allocated_chunks()
+    ///     .map(|chunk| IoSlice::new(unsafe {
+    ///         slice::from_raw_parts(chunk.as_ptr().cast::<u8>(), chunk.len())
+    ///     }))
+    ///     .collect();
+    /// let mut out = Vec::new();
+    /// out.write_vectored(&slices).unwrap();
+    /// assert_eq!(out, b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+    /// ```
+    pub fn iter_allocated_chunks(&mut self) -> impl Iterator<Item = &[MaybeUninit<u8>]> {
+        let internal = &mut self.internal;
+        let current = internal.current.get();
+        let current_used = internal.current_used();
+        let blocks = internal.blocks.get_mut();
+        let filled = blocks
+            .iter()
+            .take(current + 1)
+            .enumerate()
+            .map(move |(i, block)| {
+                let used = if i == current {
+                    current_used
+                } else {
+                    block.used
+                };
+                (block.raw.ptr(), used)
+            });
+        let large = internal
+            .large
+            .get_mut()
+            .iter()
+            .map(|raw| (raw.ptr(), raw.size()));
+        filled
+            .chain(large)
+            .filter(|&(_, used)| used > 0)
+            .map(|(ptr, used)| unsafe {
+                // The padding between values is still poisoned.
+                sanitizer::mark_addressable(ptr, used);
+                slice::from_raw_parts(ptr.cast::<MaybeUninit<u8>>(), used)
+            })
+    }
+
+    /// Makes [`reset`](Self::reset) give the physical pages of the blocks it
+    /// keeps back to the OS with `madvise`, while keeping the blocks
+    /// themselves. The pages are faulted back in as the arena fills up again.
+    ///
+    /// This keeps a long-lived arena from holding on to the memory of its
+    /// busiest moment between bursts. Only whole pages inside a block are
+    /// discarded.
+    #[cfg(all(feature = "madvise", unix))]
+    pub fn set_discard_on_reset(&mut self, discard: bool) {
+        self.internal.discard_on_reset = discard;
+    }
+
+    /// Makes every following allocation be followed by a canary, a few bytes
+    /// that are checked on [`reset`](Self::reset),
+    /// [`rewind`](Self::rewind) and drop, or at any time with
+    /// [`check_canaries`](Self::check_canaries). A write past the end of an
+    /// allocation makes the check panic with the address and size of that
+    /// allocation.
+    ///
+    /// Allocations take more room and no longer grow in place while canaries
+    /// are on. Turning them off checks the ones written so far.
+    #[cfg(feature = "canary")]
+    pub fn set_canaries(&mut self, enabled: bool) {
+        match (&self.internal.canaries, enabled) {
+            (None, true) => self.internal.canaries = Some(RefCell::new(Vec::new())),
+            (Some(_), false) => {
+                self.check_canaries();
+                self.internal.canaries = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the canaries written since [`set_canaries`](Self::set_canaries)
+    /// was turned on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation was written past its end.
+    #[cfg(feature = "canary")]
+    pub fn check_canaries(&self) {
+        self.internal.check_canaries(0);
+    }
+
+    /// Makes every following allocation record its size, type and a
+    /// backtrace, to be looked at with [`report`](Self::report). Records of
+    /// memory given up by [`reset`](Self::reset) or [`rewind`](Self::rewind)
+    /// are dropped along with it.
+    ///
+    /// Capturing a backtrace is slow, so this is meant for finding out what
+    /// fills an arena rather than for normal operation. Turning it off
+    /// forgets the records.
+    #[cfg(feature = "backtrace")]
+    pub fn set_backtraces(&mut self, enabled: bool) {
+        match (&self.internal.traces, enabled) {
+            (None, true) => self.internal.traces = Some(Traces::default()),
+            (Some(_), false) => self.internal.traces = None,
+            _ => {}
+        }
+    }
+
+    /// Returns the allocations recorded since
+    /// [`set_backtraces`](Self::set_backtraces) was turned on that are still
+    /// in the arena. Dedicated blocks of large values are included.
+    #[cfg(feature = "backtrace")]
+    pub fn report(&self) -> AllocReport {
+        self.internal
+            .traces
+            .as_ref()
+            .map(Traces::report)
+            .unwrap_or_default()
+    }
+
+    /// Makes infallible allocations call `handler` when a block cannot be
+    /// obtained, instead of panicking on overflow or limits and calling
+    /// [`handle_alloc_error`](alloc::alloc::handle_alloc_error) when the
+    /// system allocator fails.
+    pub fn set_alloc_error_handler(&mut self, handler: fn(ArenaAllocError) -> !) {
+        self.internal.error_handler = Some(handler);
+    }
+
+    /// Reports every following allocation and new block to `observer`,
+    /// replacing the previous one.
+    ///
+    /// The observer has to be `Send` since it moves along with the arena.
+    pub fn set_observer<O: ArenaObserver + Send + 'static>(&mut self, observer: O) {
+        self.internal.observer = Some(Box::new(observer));
+    }
+}
+
+// Raw access for the containers that grow inside the arena.
+impl<A: GlobalAlloc> Bump<A> {
+    pub(crate) fn alloc_layout_raw(&self, layout: Layout, type_name: &'static str) -> *mut u8 {
+        unsafe { self.internal.alloc_layout(layout, type_name) }
+    }
+
+    /// Resizes the most recent allocation in place, see `Internal::resize_last`.
+    pub(crate) unsafe fn resize_last_raw(
+        &self,
+        ptr: *mut u8,
+        old_size: usize,
+        new_size: usize,
+    ) -> bool {
+        self.internal.resize_last(ptr, old_size, new_size)
+    }
+
+    pub(crate) fn fail(&self, e: ArenaAllocError) -> ! {
+        self.internal.fail(e)
+    }
+}
+
+// Backing for the `Allocator` implementations and the C API.
+#[cfg(any(
+    feature = "nightly",
+    feature = "allocator-api2",
+    feature = "hashbrown",
+    feature = "ffi"
+))]
+impl<A: GlobalAlloc> Bump<A> {
+    pub(crate) fn allocate_bytes(&self, layout: Layout) -> Result<NonNull<[u8]>, ArenaAllocError> {
+        let ptr = self.try_alloc_layout(layout)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+}
+
+#[cfg(any(feature = "nightly", feature = "allocator-api2", feature = "hashbrown"))]
+impl<A: GlobalAlloc> Bump<A> {
+    /// Gives the memory back when it is the most recent allocation.
+    pub(crate) unsafe fn deallocate_bytes(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.internal.resize_last(ptr.as_ptr(), layout.size(), 0);
+    }
+
+    pub(crate) unsafe fn reallocate_bytes(
+        &self,
+        ptr: NonNull<u8>,
+        old: Layout,
+        new: Layout,
+    ) -> Result<NonNull<[u8]>, ArenaAllocError> {
+        let ptr = self.reallocate(ptr, old, new)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, new.size()))
+    }
+}
+
+impl Default for Bump {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct StrWriter<'b, A: GlobalAlloc> {
+    bump: &'b Bump<A>,
+    ptr: *mut u8,
+    len: usize,
+    cap: usize,
+}
+
+impl<A: GlobalAlloc> Write for StrWriter<'_, A> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let new_len = self.len.checked_add(s.len()).ok_or(fmt::Error)?;
+        if new_len > self.cap {
+            let new_cap = cmp::max(new_len, self.cap.saturating_mul(2));
+            let internal = &self.bump.internal;
+            unsafe {
+                if !internal.resize_last(self.ptr, self.cap, new_cap) {
+                    let layout = Layout::from_size_align(new_cap, 1).map_err(|_| fmt::Error)?;
+                    let new_ptr = internal.alloc_layout(layout, "str");
+                    if self.len > 0 {
+                        ptr::copy_nonoverlapping(self.ptr, new_ptr, self.len);
+                    }
+                    self.ptr = new_ptr;
+                }
+            }
+            self.cap = new_cap;
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(s.as_ptr(), self.ptr.add(self.len), s.len());
+        }
+        self.len = new_len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::mem::MaybeUninit;
+    use std::ptr;
+    use std::slice;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use std::alloc::{GlobalAlloc, Layout};
+
+    use super::{Bump, Cyclic};
+    use crate::{ArenaAllocError, ArenaObserver, Global, StaticArena};
+
+    #[test]
+    fn mixed_types() {
+        let bump = Bump::new();
+        let a = bump.alloc(1u8);
+        let b = bump.alloc(2u64);
+        let c = bump.alloc([3u16; 3]);
+        let d = bump.alloc("four");
+        assert_eq!(*a, 1);
+        assert_eq!(*b, 2);
+        assert_eq!(*c, [3, 3, 3]);
+        assert_eq!(*d, "four");
+        assert_eq!((b as *mut u64).addr() % std::mem::align_of::<u64>(), 0);
+        assert!(bump.bytes_allocated() > 0);
+    }
+
+    #[test]
+    fn alignment() {
+        #[repr(align(64))]
+        struct Line([u8; 64]);
+
+        let bump = Bump::new();
+        for i in 0..100u8 {
+            bump.alloc(i);
+            let line = bump.alloc(Line([i; 64]));
+            assert_eq!((line as *mut Line).addr() % 64, 0);
+            assert_eq!(line.0[63], i);
+        }
+
+        for align in [1, 8, 32, 64, 4096] {
+            bump.alloc(1u8);
+            let x = bump.alloc_aligned(3u32, align);
+            assert_eq!(*x, 3);
+            assert_eq!((x as *mut u32).addr() % align, 0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "alignment must be a power of two")]
+    fn alloc_aligned_invalid() {
+        Bump::new().alloc_aligned(0u8, 3);
+    }
+
+    #[test]
+    fn zero_sized() {
+        #[repr(align(16))]
+        struct Z;
+
+        let bump = Bump::new();
+        let z = bump.alloc(Z) as *mut Z;
+        bump.alloc(());
+        bump.alloc_slice_copy::<u32>(&[]);
+        assert_eq!(z.addr() % 16, 0);
+        assert_eq!(bump.bytes_allocated(), 0);
+    }
+
+    #[test]
+    fn alloc_with() {
+        let bump = Bump::new();
+        let big = bump.alloc_with(|| [7u64; 1024]);
+        let nested = bump.alloc_with(|| *bump.alloc(5u32) + 1);
+        assert!(big.iter().all(|&x| x == 7));
+        assert_eq!(*nested, 6);
+    }
+
+    #[test]
+    fn alloc_cyclic() {
+        struct Node<'a> {
+            parent: Option<Cyclic<'a, Node<'a>>>,
+            children: &'a [Node<'a>],
+            name: &'static str,
+        }
+
+        let bump = Bump::new();
+        let root = bump.alloc_cyclic(|this| {
+            assert!(this.get().is_none());
+            Node {
+                parent: None,
+                children: bump.alloc_from_iter(["a", "b"].map(|name| Node {
+                    parent: Some(this),
+                    children: &[],
+                    name,
+                })),
+                name: "root",
+            }
+        });
+        for child in root.children {
+            let parent = child.parent.unwrap().get().unwrap();
+            assert!(std::ptr::eq(parent, root));
+            assert_eq!(parent.name, "root");
+        }
+        assert!(root.parent.is_none());
+        assert_eq!(root.children[1].name, "b");
+    }
+
+    #[test]
+    fn try_alloc() {
+        let bump = Bump::new();
+        assert_eq!(*bump.try_alloc(3i32).unwrap(), 3);
+        assert_eq!(*bump.try_alloc([0u8; 8192]).unwrap(), [0u8; 8192]);
+    }
+
+    #[test]
+    fn alloc_slices() {
+        let bump = Bump::new();
+        bump.alloc(1u8);
+        let words = bump.alloc_slice_copy(&[1u64, 2, 3]);
+        let names = bump.alloc_slice_clone(&[String::from("a"), String::from("b")]);
+        assert_eq!(words, &[1, 2, 3]);
+        assert_eq!(names, &["a", "b"]);
+        assert_eq!(words.as_ptr().addr() % std::mem::align_of::<u64>(), 0);
+        assert!(bump.alloc_slice_copy::<u8>(&[]).is_empty());
+    }
+
+    #[test]
+    fn alloc_slice_fill() {
+        let bump = Bump::new();
+        let squares = bump.alloc_slice_fill_with(5, |i| i * i);
+        assert_eq!(squares, [0, 1, 4, 9, 16]);
+        let zeros = bump.alloc_slice_fill_copy(3, 0u8);
+        assert_eq!(zeros, [0; 3]);
+        let nested = bump.alloc_slice_fill_with(2, |i| &*bump.alloc_slice_fill_copy(i, 'x'));
+        assert_eq!(nested, [&[][..], &['x'][..]]);
+
+        let s = bump.alloc_str_mut("Hello");
+        s.make_ascii_uppercase();
+        assert_eq!(s, "HELLO");
+    }
+
+    #[test]
+    fn alloc_from_iter() {
+        let bump = Bump::new();
+        let squares = bump.alloc_from_iter((0..10u64).map(|x| x * x));
+        let nested = bump.alloc_from_iter((0..3).map(|x| &*bump.alloc(x)));
+        assert_eq!(squares[9], 81);
+        assert_eq!(nested.iter().map(|x| **x).sum::<i32>(), 3);
+        assert!(bump.alloc_from_iter(std::iter::empty::<u8>()).is_empty());
+    }
+
+    #[test]
+    fn alloc_mut() {
+        let mut bump = Bump::new();
+        *bump.alloc_mut(1u8) += 1;
+        let squares = bump.alloc_from_iter_mut((0..10u64).map(|x| x * x));
+        assert_eq!(squares[9], 81);
+        let odd = bump.alloc_from_iter_mut((0..10u32).filter(|x| x % 2 == 1));
+        assert_eq!(odd, [1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn alloc_uninit() {
+        let bump = Bump::new();
+        let x = bump.alloc_uninit::<u64>().write(9);
+        *x += 1;
+        assert_eq!(*x, 10);
+
+        let buf = bump.alloc_slice_uninit::<u8>(64);
+        assert_eq!(buf.len(), 64);
+        for (i, b) in buf.iter_mut().enumerate() {
+            b.write(i as u8);
+        }
+        let buf = unsafe { &*(buf as *const [_] as *const [u8]) };
+        assert_eq!(buf[63], 63);
+    }
+
+    #[test]
+    fn alloc_str() {
+        let bump = Bump::new();
+        let hello = bump.alloc_str("hello");
+        let empty = bump.alloc_str("");
+        assert_eq!(hello, "hello");
+        assert_eq!(empty, "");
+    }
+
+    #[test]
+    fn alloc_fmt() {
+        let bump = Bump::new();
+        let name = "x";
+        let a = bump.alloc_fmt(format_args!("{}{}", name, 42));
+        let b = bump.alloc_fmt(format_args!("{:>5000}", bump.alloc(7)));
+        let c = bump.alloc_fmt(format_args!("plain"));
+        assert_eq!(a, "x42");
+        assert_eq!(b.len(), 5000);
+        assert!(b.ends_with(" 7"));
+        assert_eq!(c, "plain");
+        assert_eq!(bump.alloc_fmt(format_args!("{}", "")), "");
+    }
+
+    #[test]
+    fn reset() {
+        let mut bump = Bump::new();
+        for i in 0..1000u64 {
+            bump.alloc(i);
+        }
+        let bytes = bump.bytes_allocated();
+        bump.reset();
+        for i in 0..1000u64 {
+            assert_eq!(*bump.alloc(i), i);
+        }
+        assert_eq!(bump.bytes_allocated(), bytes);
+    }
+
+    #[cfg(all(feature = "madvise", unix))]
+    #[test]
+    fn discard_on_reset() {
+        let mut bump = Bump::with_block_size(1 << 20);
+        bump.set_discard_on_reset(true);
+        for round in 0..3u8 {
+            let values = bump.alloc_slice_fill_copy(1 << 19, round);
+            assert!(values.iter().all(|&x| x == round));
+            bump.reset();
+        }
+        assert_eq!(bump.bytes_allocated(), 1 << 20);
+    }
+
+    #[test]
+    fn rewind() {
+        let mut bump = Bump::new();
+        bump.alloc(1u8);
+        let marker = bump.checkpoint();
+        let first = bump.alloc(2u32) as *mut u32;
+        for i in 0..2000u64 {
+            bump.alloc(i);
+        }
+        bump.rewind(marker);
+        assert_eq!(bump.checkpoint(), marker);
+        assert_eq!(bump.alloc(3u32) as *mut u32, first);
+    }
+
+    #[test]
+    fn stats() {
+        let mut bump = Bump::with_block_size(64);
+        bump.alloc([0u8; 40]);
+        bump.alloc([0u8; 40]);
+        bump.alloc([0u8; 8]);
+        bump.alloc([0u8; 100]);
+        let stats = bump.stats();
+        assert_eq!(stats.blocks, 3);
+        assert_eq!(stats.bytes_reserved, 228);
+        assert_eq!(stats.bytes_used, 188);
+        assert_eq!(stats.wasted_bytes, 24);
+        assert_eq!(stats.high_water_mark, 188);
+
+        bump.reset();
+        let stats = bump.stats();
+        assert_eq!(stats.bytes_used, 0);
+        assert_eq!(stats.high_water_mark, 188);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let bump = Bump::new();
+        assert_eq!(bump.memory_usage(), 0);
+        bump.alloc([0u8; 10000]);
+        assert!(bump.memory_usage() > bump.bytes_allocated());
+    }
+
+    #[test]
+    fn send_to_other_thread() {
+        let bump = Bump::new();
+        let x: *const u64 = bump.alloc(7);
+        let bump = thread::spawn(move || {
+            bump.alloc_str("moved");
+            bump
+        })
+        .join()
+        .unwrap();
+        assert_eq!(unsafe { *x }, 7);
+        assert!(bump.bytes_allocated() >= 13);
+    }
+
+    #[test]
+    fn observer() {
+        struct Blocks(Arc<AtomicUsize>);
+
+        impl ArenaObserver for Blocks {
+            fn on_block(&self, _ptr: *const u8, size: usize) {
+                self.0.fetch_add(size, Ordering::Relaxed);
+            }
+        }
+
+        let blocks = Arc::new(AtomicUsize::new(0));
+        let mut bump = Bump::with_block_size(64);
+        bump.set_observer(Blocks(blocks.clone()));
+        bump.alloc([0u8; 40]);
+        bump.alloc([0u8; 40]);
+        bump.alloc([0u8; 100]);
+        assert_eq!(blocks.load(Ordering::Relaxed), 228);
+    }
+
+    #[test]
+    fn with_limit() {
+        let mut bump = Bump::with_limit(6000);
+        bump.alloc([0u8; 4000]);
+        let marker = bump.checkpoint();
+        bump.alloc([0u8; 1904]);
+        assert_eq!(bump.bytes_allocated(), 6000);
+        assert_eq!(
+            bump.try_alloc([0u8; 5000]),
+            Err(ArenaAllocError::LimitExceeded { limit: 6000 })
+        );
+        bump.rewind(marker);
+        bump.alloc([0u8; 96]);
+        assert_eq!(bump.bytes_allocated(), 6000);
+    }
+
+    #[test]
+    fn with_block_size() {
+        let bump = Bump::with_block_size(512);
+        for i in 0..64u64 {
+            bump.alloc(i);
+        }
+        assert_eq!(bump.bytes_allocated(), 512);
+        bump.alloc(0u8);
+        assert_eq!(bump.bytes_allocated(), 1024);
+    }
+
+    #[test]
+    fn block_growth() {
+        let bump = Bump::with_block_growth(256, 1024);
+        for i in 0..(256 + 512 + 1024 + 1024) / 8 {
+            bump.alloc(i as u64);
+        }
+        assert_eq!(bump.bytes_allocated(), 256 + 512 + 1024 + 1024);
+    }
+
+    #[test]
+    fn larger_than_block() {
+        let mut bump = Bump::new();
+        let small = bump.alloc(7u32) as *mut u32;
+        let marker = bump.checkpoint();
+        let big = bump.alloc([1u8; 10000]);
+        assert!(big.iter().all(|&x| x == 1));
+        assert_eq!(bump.bytes_allocated(), 4096 + 10000);
+
+        // The current block is still used for small values.
+        let next = bump.alloc(8u32) as *mut u32;
+        assert_eq!(next, unsafe { small.add(1) });
+        assert_eq!(unsafe { *small }, 7);
+
+        bump.rewind(marker);
+        assert_eq!(bump.bytes_allocated(), 4096);
+    }
+
+    #[test]
+    fn iter_allocated_chunks() {
+        fn bytes(chunk: &[MaybeUninit<u8>]) -> &[u8] {
+            unsafe { slice::from_raw_parts(chunk.as_ptr().cast(), chunk.len()) }
+        }
+
+        let mut bump = Bump::with_block_size(64);
+        assert_eq!(bump.iter_allocated_chunks().count(), 0);
+        for i in 0..100u8 {
+            bump.alloc_slice_fill_copy(10, i);
+        }
+        bump.alloc_slice_fill_copy(100, 0xffu8);
+        let all: Vec<u8> = bump
+            .iter_allocated_chunks()
+            .flat_map(bytes)
+            .copied()
+            .collect();
+        let expected: Vec<u8> = (0..100u8)
+            .flat_map(|i| [i; 10])
+            .chain([0xff; 100])
+            .collect();
+        assert_eq!(all, expected);
+
+        // Spare blocks kept by a reset are not yielded.
+        bump.reset();
+        bump.alloc_str("again");
+        let chunks: Vec<_> = bump.iter_allocated_chunks().map(bytes).collect();
+        assert_eq!(chunks, [b"again"]);
+    }
+
+    #[test]
+    fn grow_last() {
+        let bump = Bump::with_block_size(64);
+        let mut layout = Layout::array::<u8>(5).unwrap();
+        let ptr = bump.alloc_layout(layout);
+        unsafe {
+            ptr::copy_nonoverlapping(b"grown".as_ptr(), ptr.as_ptr(), 5);
+            for word in [" ", "in", " ", "place"] {
+                let len = layout.size();
+                let grown = Layout::array::<u8>(len + word.len()).unwrap();
+                assert!(bump.grow_last(ptr, layout, grown));
+                ptr::copy_nonoverlapping(word.as_ptr(), ptr.as_ptr().add(len), word.len());
+                layout = grown;
+            }
+            assert_eq!(
+                slice::from_raw_parts(ptr.as_ptr(), layout.size()),
+                b"grown in place"
+            );
+            assert_eq!(bump.stats().bytes_used, 14);
+
+            // Not past the end of the block, and not once something else was
+            // allocated.
+            assert!(!bump.grow_last(ptr, layout, Layout::array::<u8>(65).unwrap()));
+            bump.alloc(0u8);
+            assert!(!bump.grow_last(ptr, layout, Layout::array::<u8>(20).unwrap()));
+        }
+        assert_eq!(bump.stats().bytes_used, 15);
+    }
+
+    #[test]
+    fn grow_last_not_across_blocks() {
+        // The current block starts right where the large value's block ends.
+        let bump = Bump::with_block_growth_in(64, 64, StaticArena::<1024>::new());
+        let layout = Layout::from_size_align(128, 64).unwrap();
+        let large = bump.alloc_layout(layout);
+        let one = Layout::new::<u8>();
+        let small = bump.alloc_layout(one);
+        unsafe {
+            assert_eq!(large.as_ptr().add(128), small.as_ptr());
+            bump.shrink(small, one, Layout::new::<()>()).unwrap();
+            let grown = Layout::from_size_align(160, 64).unwrap();
+            assert!(!bump.grow_last(large, layout, grown));
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let bump = Bump::with_block_size(64);
+        let other = Bump::new();
+        let first = bump.alloc(1u32) as *const u32;
+        let values: Vec<*const [u8]> = (0..20u8)
+            .map(|i| bump.alloc_slice_fill_copy(10, i) as *const [u8])
+            .collect();
+        let large = bump.alloc([0u8; 100]) as *const [u8; 100];
+        assert!(bump.contains(first));
+        assert!(values.iter().all(|&value| bump.contains(value)));
+        assert!(bump.contains(large));
+        assert!(bump.contains(&unsafe { &*large }[99]));
+        assert!(!bump.contains(other.alloc(1u32)));
+        assert!(!bump.contains(&1u32));
+        assert!(!bump.contains(bump.alloc(())));
+        // Past the end of the current allocations.
+        assert!(!bump.contains((bump.alloc(0u8) as *const u8).wrapping_add(1)));
+    }
+
+    #[test]
+    fn raw_layouts() {
+        let bump = Bump::new();
+        let small = Layout::from_size_align(16, 8).unwrap();
+        let large = Layout::from_size_align(64, 8).unwrap();
+        let ptr = bump.alloc_layout(small);
+        assert_eq!(ptr.as_ptr().addr() % 8, 0);
+        unsafe {
+            ptr.as_ptr().write_bytes(7, 16);
+
+            // The most recent allocation grows in place.
+            let grown = bump.grow(ptr, small, large).unwrap();
+            assert_eq!(grown, ptr);
+            assert_eq!(bump.stats().bytes_used, 64);
+            let shrunk = bump.shrink(grown, large, small).unwrap();
+            assert_eq!(shrunk, ptr);
+            assert_eq!(bump.stats().bytes_used, 16);
+
+            // Others are moved.
+            bump.alloc_layout(small);
+            let moved = bump.grow(ptr, small, large).unwrap();
+            assert_ne!(moved, ptr);
+            assert!(slice::from_raw_parts(moved.as_ptr(), 16)
+                .iter()
+                .all(|&b| b == 7));
+        }
+
+        let bump = Bump::with_limit(4096);
+        let huge = Layout::from_size_align(8192, 8).unwrap();
+        assert!(bump.try_alloc_layout(huge).is_err());
+        let zst = bump.alloc_layout(Layout::new::<[u64; 0]>());
+        assert_eq!(zst.as_ptr().addr() % 8, 0);
+    }
+
+    #[test]
+    fn new_in() {
+        #[derive(Default)]
+        struct Counting(Cell<isize>);
+
+        unsafe impl GlobalAlloc for &Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                self.0.set(self.0.get() + layout.size() as isize);
+                Global.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                self.0.set(self.0.get() - layout.size() as isize);
+                Global.dealloc(ptr, layout)
+            }
+        }
+
+        let counting = Counting::default();
+        let mut bump = Bump::new_in(&counting);
+        bump.alloc(1u8);
+        let marker = bump.checkpoint();
+        bump.alloc([0u8; 10000]);
+        assert_eq!(counting.0.get(), 4096 + 10000);
+        bump.rewind(marker);
+        assert_eq!(counting.0.get(), 4096);
+        bump.alloc([0u8; 10000]);
+        drop(bump);
+        assert_eq!(counting.0.get(), 0);
+    }
+
+    // Reads memory that was given up, which ASan would rightly report.
+    #[cfg(all(feature = "poison", not(feature = "asan")))]
+    #[test]
+    fn poison() {
+        let mut arena = Bump::new();
+        let first: *const u32 = arena.alloc(1u32);
+        let marker = arena.checkpoint();
+        let second: *const u32 = arena.alloc(2u32);
+        assert_eq!(unsafe { second.add(1).read() }, 0xA5A5_A5A5);
+        arena.rewind(marker);
+        assert_eq!(unsafe { second.read() }, 0xDEDE_DEDE);
+        assert_eq!(unsafe { first.read() }, 1);
+        arena.reset();
+        assert_eq!(unsafe { first.read() }, 0xDEDE_DEDE);
+    }
+
+    #[cfg(feature = "canary")]
+    #[test]
+    fn canaries() {
+        let mut bump = Bump::new();
+        bump.set_canaries(true);
+        bump.alloc_str("hello");
+        let marker = bump.checkpoint();
+        let mut v = crate::vec::Vec::with_capacity_in(1, &bump);
+        v.extend(0..100u64);
+        assert_eq!(v.iter().sum::<u64>(), 4950);
+        drop(v);
+        bump.check_canaries();
+        bump.rewind(marker);
+        bump.alloc_slice_copy(&[1u32, 2, 3]);
+        bump.reset();
+        bump.set_canaries(false);
+        assert_eq!(bump.alloc(7u8), &7);
+    }
+
+    #[cfg(feature = "canary")]
+    #[test]
+    #[should_panic(expected = "buffer overrun past the 12-byte allocation")]
+    fn canary_overrun() {
+        let mut bump = Bump::new();
+        bump.set_canaries(true);
+        bump.alloc(0u64);
+        let values = bump.alloc_slice_copy(&[1u32, 2, 3]);
+        unsafe { values.as_mut_ptr().add(3).write(4) };
+        bump.reset();
+    }
+
+    #[cfg(feature = "asan")]
+    #[test]
+    fn asan_poisoning() {
+        extern "C" {
+            fn __asan_address_is_poisoned(addr: *const u8) -> i32;
+        }
+        let poisoned = |ptr: *const u8| unsafe { __asan_address_is_poisoned(ptr) != 0 };
+
+        let mut bump = Bump::new();
+        let first: *const u8 = bump.alloc(1u8);
+        assert!(!poisoned(first));
+        assert!(poisoned(first.wrapping_add(1)));
+        let marker = bump.checkpoint();
+        let second: *const u32 = bump.alloc(2u32);
+        assert!(!poisoned(second.cast()));
+        bump.rewind(marker);
+        assert!(poisoned(second.cast()));
+        bump.reset();
+        assert!(poisoned(first));
+    }
+
+    // Natively the client requests do nothing, so this only checks that they
+    // leave the arena alone. Run it under `valgrind` to see them at work.
+    #[cfg(feature = "valgrind")]
+    #[test]
+    fn valgrind_requests() {
+        assert!(!crate::sanitizer::running_on_valgrind());
+        let mut bump = Bump::new();
+        bump.alloc(1u8);
+        let marker = bump.checkpoint();
+        let s = bump.alloc_fmt(format_args!("{:>100}", 7));
+        assert!(s.ends_with(" 7"));
+        bump.rewind(marker);
+        assert_eq!(bump.checkpoint(), marker);
+        bump.reset();
+        assert_eq!(*bump.alloc(2u64), 2);
+    }
+
+    #[cfg(feature = "backtrace")]
+    #[test]
+    fn backtraces() {
+        let mut bump = Bump::new();
+        bump.alloc(0u8);
+        bump.set_backtraces(true);
+        bump.alloc(1u64);
+        let marker = bump.checkpoint();
+        bump.alloc_slice_copy(&[1u32, 2, 3]);
+        let mut v = crate::vec::Vec::new_in(&bump);
+        v.extend(0..100u16);
+        drop(v);
+
+        let report = bump.report();
+        assert_eq!(report.traces().len(), 3);
+        assert_eq!(report.total_bytes(), 8 + 12 + 200);
+        assert_eq!(report.by_type()[0], ("[u16]", 1, 200));
+        assert_eq!(report.traces()[0].type_name(), "u64");
+        assert!(report.to_string().starts_with("220 bytes in 3 allocations"));
+
+        bump.rewind(marker);
+        assert_eq!(bump.report().total_bytes(), 8);
+        bump.set_backtraces(false);
+        assert!(bump.report().traces().is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+//! Maps and sets whose storage lives in a [`Bump`], so that side tables die
+//! with the arena.
+//!
+//! These are the standard collections instantiated with `&Bump` as their
+//! allocator:
+//!
+//! ```
+//! # #[cfg(feature = "hashbrown")] {
+//! use rusty_arena::bump::Bump;
+//! use rusty_arena::collections::ArenaHashMap;
+//!
+//! let bump = Bump::new();
+//! let mut symbols = ArenaHashMap::new_in(&bump);
+//! symbols.insert("main", 0);
+//! assert_eq!(symbols["main"], 0);
+//! # }
+//! ```
+//!
+//! Storage freed by the collections is only reclaimed when it was the most
+//! recent allocation of the arena, so tables that keep growing leave their
+//! old buckets behind until the arena is reset.
+
+#[cfg(feature = "hashbrown")]
+use crate::bump::Bump;
+#[cfg(feature = "hashbrown")]
+use crate::Global;
+
+/// A hash map from [hashbrown] whose table lives in a [`Bump`].
+#[cfg(feature = "hashbrown")]
+pub type ArenaHashMap<'a, K, V, S = hashbrown::DefaultHashBuilder, A = Global> =
+    hashbrown::HashMap<K, V, S, &'a Bump<A>>;
+
+/// A hash set from [hashbrown] whose table lives in a [`Bump`].
+#[cfg(feature = "hashbrown")]
+pub type ArenaHashSet<'a, T, S = hashbrown::DefaultHashBuilder, A = Global> =
+    hashbrown::HashSet<T, S, &'a Bump<A>>;
+
+/// A B-tree map whose nodes live in a [`Bump`](crate::bump::Bump). Needs the
+/// `nightly` feature.
+#[cfg(feature = "nightly")]
+pub type ArenaBTreeMap<'a, K, V, A = crate::Global> =
+    alloc::collections::BTreeMap<K, V, &'a crate::bump::Bump<A>>;
+
+/// A B-tree set whose nodes live in a [`Bump`](crate::bump::Bump). Needs the
+/// `nightly` feature.
+#[cfg(feature = "nightly")]
+pub type ArenaBTreeSet<'a, T, A = crate::Global> =
+    alloc::collections::BTreeSet<T, &'a crate::bump::Bump<A>>;
+
+#[cfg(test)]
+mod tests {
+    use crate::bump::Bump;
+
+    #[cfg(feature = "hashbrown")]
+    #[test]
+    fn hash_map() {
+        use super::{ArenaHashMap, ArenaHashSet};
+
+        let bump = Bump::new();
+        let mut map = ArenaHashMap::new_in(&bump);
+        for i in 0..100u32 {
+            map.insert(i, i * 2);
+        }
+        assert_eq!(map[&21], 42);
+        assert!(bump.bytes_allocated() > 0);
+
+        let mut set = ArenaHashSet::new_in(&bump);
+        set.insert("a");
+        assert!(set.contains("a"));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn btree_map() {
+        use super::{ArenaBTreeMap, ArenaBTreeSet};
+
+        let bump = Bump::new();
+        let mut map = ArenaBTreeMap::new_in(&bump);
+        for i in (0..100u32).rev() {
+            map.insert(i, i * 2);
+        }
+        assert_eq!(map.first_key_value(), Some((&0, &0)));
+        assert!(bump.bytes_allocated() > 0);
+
+        let mut set = ArenaBTreeSet::new_in(&bump);
+        set.insert(3);
+        assert!(set.contains(&3));
+    }
+}
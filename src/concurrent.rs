@@ -0,0 +1,317 @@
+//! A bump arena that many threads can allocate from at once.
+
+use core::alloc::Layout;
+use core::cmp;
+use core::ptr;
+use core::slice;
+use core::str;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use alloc::vec::Vec;
+
+use crate::block::RawBlock;
+use crate::{telemetry, ArenaAllocError, Global};
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+
+// Sits at the start of every shared block. Threads claim space by moving
+// `cursor` forward with a compare-and-swap.
+struct Chunk {
+    // Offset of the first free byte from the start of the block.
+    cursor: AtomicUsize,
+    size: usize,
+}
+
+impl Chunk {
+    /// Claims room for `layout` in the block starting at `chunk`.
+    unsafe fn bump(chunk: *mut Chunk, layout: Layout) -> Option<*mut u8> {
+        let base = chunk.cast::<u8>();
+        let header = &*chunk;
+        let mut cursor = header.cursor.load(Ordering::Relaxed);
+        loop {
+            let offset = base.add(cursor).align_offset(layout.align());
+            let end = cursor.checked_add(offset)?.checked_add(layout.size())?;
+            if end > header.size {
+                return None;
+            }
+            match header.cursor.compare_exchange_weak(
+                cursor,
+                end,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(base.add(cursor + offset)),
+                Err(actual) => cursor = actual,
+            }
+        }
+    }
+}
+
+// Only touched when a thread needs a new block.
+struct State {
+    blocks: Vec<RawBlock>,
+    // Size of the next shared block, doubled after each one up to
+    // `max_block_size`.
+    block_size: usize,
+    max_block_size: usize,
+}
+
+/// A [`Bump`](crate::bump::Bump) that is `Sync`: threads allocate through a
+/// shared reference without a lock.
+///
+/// The bump pointer of the current block is an atomic, so an allocation that
+/// fits costs one compare-and-swap. Only moving to a new block takes a lock.
+/// Like with a `Bump`, destructors of allocated values are never run.
+pub struct ConcurrentBump {
+    // The block being filled, null before the first allocation. Blocks are
+    // only released through `&mut self`, so a thread holding a stale pointer
+    // can still bump it; it just finds the block full.
+    current: AtomicPtr<Chunk>,
+    bytes: AtomicUsize,
+    state: Mutex<State>,
+}
+
+// Every value is handed to exactly one thread, and the blocks are only
+// released through `&mut self`.
+unsafe impl Send for ConcurrentBump {}
+unsafe impl Sync for ConcurrentBump {}
+
+impl ConcurrentBump {
+    pub fn new() -> Self {
+        Self::with_block_growth(BLOCK_SIZE, MAX_BLOCK_SIZE)
+    }
+
+    /// Creates an arena that requests blocks of `block_size` bytes from the
+    /// system allocator. Values larger than that get a block of their own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    pub fn with_block_size(block_size: usize) -> Self {
+        Self::with_block_growth(block_size, block_size)
+    }
+
+    /// Creates an arena whose first block has `initial` bytes and where each
+    /// following block doubles in size until it reaches `max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero or larger than `max`.
+    pub fn with_block_growth(initial: usize, max: usize) -> Self {
+        assert!(initial > 0, "block size must be non-zero");
+        assert!(initial <= max, "initial block size exceeds the maximum");
+        Self {
+            current: AtomicPtr::new(ptr::null_mut()),
+            bytes: AtomicUsize::new(0),
+            state: Mutex::new(State {
+                blocks: Vec::new(),
+                block_size: initial,
+                max_block_size: max,
+            }),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, data: T) -> &mut T {
+        self.alloc_with(|| data)
+    }
+
+    /// Allocates space for a `T` and writes the result of `f` straight into
+    /// it.
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with<T, F: FnOnce() -> T>(&self, f: F) -> &mut T {
+        unsafe {
+            let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+            ptr::write(ptr, f());
+            &mut *ptr
+        }
+    }
+
+    /// Like [`alloc`](Self::alloc), but returns an error instead of aborting
+    /// when a new block cannot be obtained.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, data: T) -> Result<&mut T, ArenaAllocError> {
+        unsafe {
+            let ptr = self.try_alloc_layout(Layout::new::<T>())?.cast::<T>();
+            ptr::write(ptr, data);
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Copies `src` into a contiguous run of arena memory.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T] {
+        unsafe {
+            let ptr = self.alloc_layout(Layout::for_value(src)).cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            slice::from_raw_parts_mut(ptr, src.len())
+        }
+    }
+
+    /// Copies `src` into the arena.
+    pub fn alloc_str(&self, src: &str) -> &str {
+        let bytes = self.alloc_slice_copy(src.as_bytes());
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Releases every block. Unlike [`Bump::reset`](crate::bump::Bump::reset)
+    /// nothing is kept for reuse.
+    pub fn reset(&mut self) {
+        let bytes = self.bytes.get_mut();
+        telemetry::reset(*bytes);
+        *bytes = 0;
+        *self.current.get_mut() = ptr::null_mut();
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for block in state.blocks.drain(..) {
+            unsafe { block.free(&Global) };
+        }
+    }
+
+    /// Returns the number of bytes obtained from the system allocator.
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> *mut u8 {
+        match self.try_alloc_layout(layout) {
+            Ok(ptr) => ptr,
+            Err(e) => e.handle(),
+        }
+    }
+
+    #[inline(always)]
+    fn try_alloc_layout(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        if layout.size() == 0 {
+            return Ok(ptr::without_provenance_mut(layout.align()));
+        }
+        let chunk = self.current.load(Ordering::Acquire);
+        if !chunk.is_null() {
+            if let Some(ptr) = unsafe { Chunk::bump(chunk, layout) } {
+                return Ok(ptr);
+            }
+        }
+        self.alloc_slow(layout)
+    }
+
+    #[cold]
+    fn alloc_slow(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        let mut state = self.lock();
+        if layout.size() > state.block_size {
+            let block = RawBlock::try_new_in(layout.size(), layout.align(), &Global)?;
+            return Ok(self.push(&mut state, block));
+        }
+
+        // Another thread may have started a new block while this one waited.
+        let chunk = self.current.load(Ordering::Acquire);
+        if !chunk.is_null() {
+            if let Some(ptr) = unsafe { Chunk::bump(chunk, layout) } {
+                return Ok(ptr);
+            }
+        }
+
+        let (block_layout, offset) = Layout::new::<Chunk>()
+            .extend(layout)
+            .map_err(|_| ArenaAllocError::CapacityOverflow)?;
+        let size = cmp::max(block_layout.size(), state.block_size);
+        let block = RawBlock::try_new_in(size, block_layout.align(), &Global)?;
+        let chunk = block.ptr().cast::<Chunk>();
+        unsafe {
+            ptr::write(
+                chunk,
+                Chunk {
+                    cursor: AtomicUsize::new(offset + layout.size()),
+                    size: block.size(),
+                },
+            );
+        }
+        state.block_size = cmp::min(state.block_size.saturating_mul(2), state.max_block_size);
+        let ptr = self.push(&mut state, block);
+        // Publishes the header written above along with the block.
+        self.current.store(chunk, Ordering::Release);
+        Ok(unsafe { ptr.add(offset) })
+    }
+
+    fn push(&self, state: &mut State, block: RawBlock) -> *mut u8 {
+        let ptr = block.ptr();
+        self.bytes.fetch_add(block.size(), Ordering::Relaxed);
+        state.blocks.push(block);
+        ptr
+    }
+
+    // The state is only updated after a block was obtained, so a panic
+    // can't leave it half updated.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for ConcurrentBump {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ConcurrentBump {
+    fn drop(&mut self) {
+        telemetry::dropped(*self.bytes.get_mut());
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for block in state.blocks.drain(..) {
+            unsafe { block.free(&Global) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::ConcurrentBump;
+
+    #[test]
+    fn alloc_from_threads() {
+        let bump = ConcurrentBump::with_block_size(256);
+        thread::scope(|s| {
+            for t in 0..8u64 {
+                let bump = &bump;
+                s.spawn(move || {
+                    let values: Vec<&mut u64> =
+                        (0..1000).map(|i| bump.alloc(t * 1000 + i)).collect();
+                    for (i, value) in values.into_iter().enumerate() {
+                        assert_eq!(*value, t * 1000 + i as u64);
+                        *value = 0;
+                    }
+                });
+            }
+        });
+        assert!(bump.bytes_allocated() >= 8000 * 8);
+    }
+
+    #[test]
+    fn alignment_and_large_values() {
+        #[repr(align(64))]
+        struct Line([u8; 64]);
+
+        let bump = ConcurrentBump::with_block_size(128);
+        bump.alloc(1u8);
+        let line = bump.alloc(Line([7; 64]));
+        assert_eq!((line as *const Line).addr() % 64, 0);
+        let large = bump.alloc_slice_copy(&[3u32; 1000]);
+        assert_eq!(large.iter().sum::<u32>(), 3000);
+        assert_eq!(bump.alloc_str("text"), "text");
+        assert_eq!(line.0[63], 7);
+    }
+
+    #[test]
+    fn reset() {
+        let mut bump = ConcurrentBump::new();
+        bump.alloc([0u8; 100]);
+        assert_eq!(bump.bytes_allocated(), 4096);
+        bump.reset();
+        assert_eq!(bump.bytes_allocated(), 0);
+        assert_eq!(*bump.alloc(5), 5);
+        assert!(bump.try_alloc(1u8).is_ok());
+    }
+}
@@ -0,0 +1,356 @@
+//! Deserializing straight into a [`Bump`].
+//!
+//! Types that implement [`DeserializeIn`] build themselves out of arena
+//! memory: strings, byte buffers, slices and boxed nodes are allocated in the
+//! arena as the input is read, without going through the heap first. An
+//! [`ArenaDeserializer`] is the [`DeserializeSeed`] that starts this off, and
+//! the one to hand to `next_element_seed` and `next_value_seed` when
+//! implementing [`DeserializeIn`] for a type of your own.
+//!
+//! ```
+//! use rusty_arena::bump::Bump;
+//! use rusty_arena::de::ArenaDeserializer;
+//! use serde::de::DeserializeSeed;
+//!
+//! let bump = Bump::new();
+//! let mut json = serde_json::Deserializer::from_str(r#"[["a", "b"], [], ["c"]]"#);
+//! let lists: &[&[&str]] = ArenaDeserializer::new(&bump).deserialize(&mut json).unwrap();
+//! assert_eq!(lists, [&["a", "b"][..], &[], &["c"]]);
+//! ```
+
+use core::alloc::GlobalAlloc;
+use core::cmp;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+
+use serde::de::value::U8Deserializer;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+
+use crate::bump::Bump;
+use crate::vec::Vec;
+use crate::Global;
+
+/// Caps the capacity taken from a size hint, so that a hostile length prefix
+/// can't make the arena reserve more than this up front.
+const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+
+/// A type that can be deserialized into memory of a [`Bump`].
+///
+/// Strings and byte buffers are always copied into the arena, so the input
+/// can be dropped as soon as deserialization is done.
+pub trait DeserializeIn<'a, A: GlobalAlloc = Global>: Sized {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error>;
+}
+
+/// A [`DeserializeSeed`] that deserializes a `T` into a [`Bump`].
+pub struct ArenaDeserializer<'a, T, A: GlobalAlloc = Global> {
+    bump: &'a Bump<A>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, A: GlobalAlloc> ArenaDeserializer<'a, T, A> {
+    pub fn new(bump: &'a Bump<A>) -> Self {
+        Self {
+            bump,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn bump(&self) -> &'a Bump<A> {
+        self.bump
+    }
+}
+
+impl<T, A: GlobalAlloc> Clone for ArenaDeserializer<'_, T, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, A: GlobalAlloc> Copy for ArenaDeserializer<'_, T, A> {}
+
+impl<'de, 'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> DeserializeSeed<'de>
+    for ArenaDeserializer<'a, T, A>
+{
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        T::deserialize_in(deserializer, self.bump)
+    }
+}
+
+macro_rules! deserialize_in_owned {
+    ($($ty:ty)*) => {$(
+        impl<'a, A: GlobalAlloc> DeserializeIn<'a, A> for $ty {
+            fn deserialize_in<'de, D: Deserializer<'de>>(
+                deserializer: D,
+                _: &'a Bump<A>,
+            ) -> Result<Self, D::Error> {
+                <$ty>::deserialize(deserializer)
+            }
+        }
+    )*};
+}
+
+deserialize_in_owned! {
+    () bool char f32 f64
+    i8 i16 i32 i64 i128 isize
+    u8 u16 u32 u64 u128 usize
+}
+
+impl<'a, A: GlobalAlloc> DeserializeIn<'a, A> for &'a str {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error> {
+        struct StrVisitor<'a, A: GlobalAlloc>(&'a Bump<A>);
+
+        impl<'a, A: GlobalAlloc> Visitor<'_> for StrVisitor<'a, A> {
+            type Value = &'a str;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<&'a str, E> {
+                Ok(self.0.alloc_str(v))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor(bump))
+    }
+}
+
+/// Accepts sequences as well as byte strings, so `&[u8]` picks up the
+/// compact byte encoding of formats that have one.
+impl<'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> DeserializeIn<'a, A> for &'a mut [T] {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error> {
+        struct SliceVisitor<'a, T, A: GlobalAlloc>(&'a Bump<A>, PhantomData<fn() -> T>);
+
+        impl<'de, 'a, T: DeserializeIn<'a, A> + 'a, A: GlobalAlloc> Visitor<'de>
+            for SliceVisitor<'a, T, A>
+        {
+            type Value = &'a mut [T];
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<&'a mut [T], S::Error> {
+                let hint = seq.size_hint().unwrap_or(0);
+                let max = MAX_PREALLOC_BYTES / cmp::max(mem::size_of::<T>(), 1);
+                let mut vec = Vec::with_capacity_in(cmp::min(hint, max), self.0);
+                while let Some(value) = seq.next_element_seed(ArenaDeserializer::new(self.0))? {
+                    vec.push(value);
+                }
+                Ok(vec.into_bump_slice())
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<&'a mut [T], E> {
+                let mut vec = Vec::with_capacity_in(v.len(), self.0);
+                for &byte in v {
+                    vec.push(T::deserialize_in(U8Deserializer::<E>::new(byte), self.0)?);
+                }
+                Ok(vec.into_bump_slice())
+            }
+        }
+
+        deserializer.deserialize_seq(SliceVisitor(bump, PhantomData))
+    }
+}
+
+impl<'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> DeserializeIn<'a, A> for &'a [T] {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error> {
+        <&'a mut [T]>::deserialize_in(deserializer, bump).map(|slice| &*slice)
+    }
+}
+
+/// Allocates the value in the arena, for the children of a tree.
+impl<'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> DeserializeIn<'a, A> for &'a mut T {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error> {
+        T::deserialize_in(deserializer, bump).map(|value| bump.alloc(value))
+    }
+}
+
+impl<'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> DeserializeIn<'a, A> for &'a T {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error> {
+        <&'a mut T>::deserialize_in(deserializer, bump).map(|value| &*value)
+    }
+}
+
+impl<'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> DeserializeIn<'a, A> for Option<T> {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        bump: &'a Bump<A>,
+    ) -> Result<Self, D::Error> {
+        struct OptionVisitor<'a, T, A: GlobalAlloc>(&'a Bump<A>, PhantomData<fn() -> T>);
+
+        impl<'de, 'a, T: DeserializeIn<'a, A>, A: GlobalAlloc> Visitor<'de> for OptionVisitor<'a, T, A> {
+            type Value = Option<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an option")
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Option<T>, E> {
+                Ok(None)
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Option<T>, E> {
+                Ok(None)
+            }
+
+            fn visit_some<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Option<T>, D::Error> {
+                T::deserialize_in(deserializer, self.0).map(Some)
+            }
+        }
+
+        deserializer.deserialize_option(OptionVisitor(bump, PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::MapAccess;
+
+    /// A JSON document whose strings, arrays and objects all live in the arena.
+    #[derive(Debug, PartialEq)]
+    enum Json<'a> {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(&'a str),
+        Array(&'a [Json<'a>]),
+        Object(&'a [(&'a str, Json<'a>)]),
+    }
+
+    impl<'a> DeserializeIn<'a> for Json<'a> {
+        fn deserialize_in<'de, D: Deserializer<'de>>(
+            deserializer: D,
+            bump: &'a Bump,
+        ) -> Result<Self, D::Error> {
+            struct JsonVisitor<'a>(&'a Bump);
+
+            impl<'de, 'a> Visitor<'de> for JsonVisitor<'a> {
+                type Value = Json<'a>;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a JSON value")
+                }
+
+                fn visit_unit<E: de::Error>(self) -> Result<Json<'a>, E> {
+                    Ok(Json::Null)
+                }
+
+                fn visit_bool<E: de::Error>(self, v: bool) -> Result<Json<'a>, E> {
+                    Ok(Json::Bool(v))
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<Json<'a>, E> {
+                    Ok(Json::Number(v as f64))
+                }
+
+                fn visit_i64<E: de::Error>(self, v: i64) -> Result<Json<'a>, E> {
+                    Ok(Json::Number(v as f64))
+                }
+
+                fn visit_f64<E: de::Error>(self, v: f64) -> Result<Json<'a>, E> {
+                    Ok(Json::Number(v))
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Json<'a>, E> {
+                    Ok(Json::String(self.0.alloc_str(v)))
+                }
+
+                fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Json<'a>, S::Error> {
+                    let mut values = Vec::new_in(self.0);
+                    while let Some(value) = seq.next_element_seed(ArenaDeserializer::new(self.0))? {
+                        values.push(value);
+                    }
+                    Ok(Json::Array(values.into_bump_slice()))
+                }
+
+                fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Json<'a>, M::Error> {
+                    let mut entries = Vec::new_in(self.0);
+                    while let Some(key) = map.next_key_seed(ArenaDeserializer::new(self.0))? {
+                        let value = map.next_value_seed(ArenaDeserializer::new(self.0))?;
+                        entries.push((key, value));
+                    }
+                    Ok(Json::Object(entries.into_bump_slice()))
+                }
+            }
+
+            deserializer.deserialize_any(JsonVisitor(bump))
+        }
+    }
+
+    fn from_str<'a, T: DeserializeIn<'a>>(bump: &'a Bump, s: &str) -> serde_json::Result<T> {
+        let mut deserializer = serde_json::Deserializer::from_str(s);
+        let value = ArenaDeserializer::new(bump).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+
+    #[test]
+    fn document() {
+        let bump = Bump::new();
+        let input = String::from(r#"{"name": "arena", "tags": ["a", null, true], "size": 4096}"#);
+        let json: Json<'_> = from_str(&bump, &input).unwrap();
+        drop(input);
+        assert_eq!(
+            json,
+            Json::Object(&[
+                ("name", Json::String("arena")),
+                (
+                    "tags",
+                    Json::Array(&[Json::String("a"), Json::Null, Json::Bool(true)])
+                ),
+                ("size", Json::Number(4096.0)),
+            ])
+        );
+    }
+
+    #[test]
+    fn builtin() {
+        let bump = Bump::new();
+        let words: &[&str] = from_str(&bump, r#"["x", "yz"]"#).unwrap();
+        assert_eq!(words, ["x", "yz"]);
+        let bytes: &[u8] = from_str(&bump, "[1, 2, 255]").unwrap();
+        assert_eq!(bytes, [1, 2, 255]);
+        let node: Option<&bool> = from_str(&bump, "null").unwrap();
+        assert_eq!(node, None);
+        let nested: &mut Option<&u64> = from_str(&bump, "7").unwrap();
+        assert_eq!(*nested, Some(&7));
+        assert!(from_str::<&[u8]>(&bump, "[256]").is_err());
+    }
+
+    #[test]
+    fn byte_strings() {
+        let bump = Bump::new();
+        let deserializer = serde::de::value::BytesDeserializer::<de::value::Error>::new(b"abc");
+        let bytes: &[u8] = ArenaDeserializer::new(&bump)
+            .deserialize(deserializer)
+            .unwrap();
+        assert_eq!(bytes, b"abc");
+    }
+}
@@ -0,0 +1,123 @@
+//! A pair of typed arenas for values that live for one frame or batch.
+
+use core::mem;
+
+use crate::arena::Arena;
+
+/// Two [`Arena`]s used in turns: values are allocated into the front arena
+/// while the back arena still holds the previous frame, then
+/// [`swap`](Self::swap) drops the previous frame and switches the two.
+///
+/// ```
+/// use rusty_arena::double::DoubleArena;
+///
+/// let mut frames = DoubleArena::new();
+/// frames.alloc(String::from("frame 0"));
+/// frames.swap();
+/// let (front, back) = frames.split();
+/// for old in back.iter() {
+///     front.alloc(old.replace('0', "1"));
+/// }
+/// frames.swap();
+/// assert_eq!(frames.back().iter().next().unwrap(), "frame 1");
+/// ```
+///
+/// References returned by `alloc` borrow the `DoubleArena`, so none of them
+/// can survive a swap and point into the frame it drops.
+pub struct DoubleArena<T> {
+    front: Arena<T>,
+    back: Arena<T>,
+}
+
+impl<T> DoubleArena<T> {
+    pub fn new() -> Self {
+        Self {
+            front: Arena::new(),
+            back: Arena::new(),
+        }
+    }
+
+    /// Allocates `data` into the front arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, data: T) -> &mut T {
+        self.front.alloc(data)
+    }
+
+    /// Returns the arena being filled.
+    pub fn front(&self) -> &Arena<T> {
+        &self.front
+    }
+
+    /// Returns the arena holding the previous frame.
+    pub fn back(&mut self) -> &mut Arena<T> {
+        &mut self.back
+    }
+
+    /// Returns the front arena to allocate from, along with the back arena to
+    /// read the previous frame from at the same time.
+    pub fn split(&mut self) -> (&Arena<T>, &mut Arena<T>) {
+        (&self.front, &mut self.back)
+    }
+
+    /// Drops the values of the previous frame and makes the front arena the
+    /// back one. The emptied arena becomes the front, keeping its blocks.
+    pub fn swap(&mut self) {
+        self.back.reset();
+        mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Drops the values of both frames.
+    pub fn reset(&mut self) {
+        self.front.reset();
+        self.back.reset();
+    }
+}
+
+impl<T> Default for DoubleArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::DoubleArena;
+
+    struct Counted<'c>(&'c Cell<usize>, u32);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn frames() {
+        let drops = Cell::new(0);
+        let mut frames = DoubleArena::new();
+        for i in 0..10 {
+            frames.alloc(Counted(&drops, i));
+        }
+        frames.swap();
+        assert_eq!(drops.get(), 0);
+        assert!(frames.front().is_empty());
+
+        for frame in 1..4 {
+            let (front, back) = frames.split();
+            for old in back.iter() {
+                front.alloc(Counted(&drops, old.1 + 1));
+            }
+            frames.swap();
+            assert_eq!(drops.get(), 10 * frame);
+            let sum: u32 = frames.back().iter().map(|c| c.1).sum();
+            assert_eq!(sum, (0..10).sum::<u32>() + 10 * frame as u32);
+        }
+
+        let bytes = frames.front().bytes_allocated() + frames.back().bytes_allocated();
+        assert_eq!(bytes, 2 * 4096);
+        frames.reset();
+        assert_eq!(drops.get(), 40);
+    }
+}
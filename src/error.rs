@@ -0,0 +1,45 @@
+use alloc::alloc::{handle_alloc_error, Layout};
+use core::error::Error;
+use core::fmt;
+
+/// The error returned by the fallible `try_*` allocation methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArenaAllocError {
+    /// The requested size overflowed when computing a block layout.
+    CapacityOverflow,
+    /// The global allocator could not provide a block with this layout.
+    AllocFailed { layout: Layout },
+    /// A new block would take the arena past its byte limit.
+    LimitExceeded { limit: usize },
+}
+
+impl ArenaAllocError {
+    /// Turns the error into the failure of an infallible allocation.
+    pub(crate) fn handle(self) -> ! {
+        match self {
+            ArenaAllocError::CapacityOverflow | ArenaAllocError::LimitExceeded { .. } => {
+                panic!("{}", self)
+            }
+            ArenaAllocError::AllocFailed { layout } => handle_alloc_error(layout),
+        }
+    }
+}
+
+impl fmt::Display for ArenaAllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaAllocError::CapacityOverflow => f.write_str("arena capacity overflow"),
+            ArenaAllocError::AllocFailed { layout } => write!(
+                f,
+                "failed to allocate an arena block of {} bytes",
+                layout.size()
+            ),
+            ArenaAllocError::LimitExceeded { limit } => {
+                write!(f, "arena limit of {} bytes exceeded", limit)
+            }
+        }
+    }
+}
+
+impl Error for ArenaAllocError {}
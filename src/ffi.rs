@@ -0,0 +1,156 @@
+//! A C API around [`Bump`], declared in `include/rusty_arena.h`.
+//!
+//! Arenas are passed around as opaque `rusty_arena *` handles. Every function
+//! accepts a null handle and treats it as an empty arena.
+
+use core::alloc::Layout;
+use core::ffi::c_void;
+use core::ptr;
+
+use alloc::boxed::Box;
+
+use crate::bump::Bump;
+
+/// [`ArenaStats`](crate::ArenaStats) with a stable layout, `rusty_arena_stats`
+/// on the C side.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RustyArenaStats {
+    pub blocks: usize,
+    pub bytes_reserved: usize,
+    pub bytes_used: usize,
+    pub wasted_bytes: usize,
+    pub high_water_mark: usize,
+}
+
+/// Creates an arena. Release it with [`rusty_arena_destroy`].
+#[no_mangle]
+pub extern "C" fn rusty_arena_new() -> *mut Bump {
+    Box::into_raw(Box::new(Bump::new()))
+}
+
+/// Creates an arena that requests blocks of `block_size` bytes, or returns
+/// null if `block_size` is zero.
+#[no_mangle]
+pub extern "C" fn rusty_arena_with_block_size(block_size: usize) -> *mut Bump {
+    if block_size == 0 {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(Bump::with_block_size(block_size)))
+}
+
+/// Releases the arena and everything allocated from it.
+///
+/// # Safety
+///
+/// `arena` must be null or come from one of the constructors, and must not be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_arena_destroy(arena: *mut Bump) {
+    if !arena.is_null() {
+        drop(Box::from_raw(arena));
+    }
+}
+
+/// Allocates `size` bytes aligned to `align`, which must be a power of two.
+/// Returns null when the memory cannot be obtained or `align` is invalid.
+///
+/// # Safety
+///
+/// `arena` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_arena_alloc(
+    arena: *const Bump,
+    size: usize,
+    align: usize,
+) -> *mut c_void {
+    let (Some(arena), Ok(layout)) = (arena.as_ref(), Layout::from_size_align(size, align)) else {
+        return ptr::null_mut();
+    };
+    match arena.allocate_bytes(layout) {
+        Ok(ptr) => ptr.as_ptr().cast(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Releases every allocation at once. The blocks are kept for reuse, except
+/// the dedicated blocks of large allocations, which are freed.
+///
+/// # Safety
+///
+/// `arena` must be null or a live handle, and no pointer handed out by it may
+/// be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_arena_reset(arena: *mut Bump) {
+    if let Some(arena) = arena.as_mut() {
+        arena.reset();
+    }
+}
+
+/// Returns the number of bytes obtained from the system allocator.
+///
+/// # Safety
+///
+/// `arena` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_arena_bytes_allocated(arena: *const Bump) -> usize {
+    arena.as_ref().map_or(0, Bump::bytes_allocated)
+}
+
+/// Fills `out` with a snapshot of the memory usage of the arena.
+///
+/// # Safety
+///
+/// `arena` must be null or a live handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rusty_arena_get_stats(arena: *const Bump, out: *mut RustyArenaStats) {
+    let stats = arena.as_ref().map(Bump::stats).unwrap_or_default();
+    out.write(RustyArenaStats {
+        blocks: stats.blocks,
+        bytes_reserved: stats.bytes_reserved,
+        bytes_used: stats.bytes_used,
+        wasted_bytes: stats.wasted_bytes,
+        high_water_mark: stats.high_water_mark,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn alloc() {
+        unsafe {
+            let arena = rusty_arena_new();
+            let p = rusty_arena_alloc(arena, 24, 8).cast::<u64>();
+            assert!(!p.is_null());
+            assert_eq!(p.addr() % 8, 0);
+            p.write(7);
+            assert!(rusty_arena_alloc(arena, 8, 3).is_null());
+            assert_eq!(rusty_arena_bytes_allocated(arena), 4096);
+
+            let mut stats = RustyArenaStats::default();
+            rusty_arena_get_stats(arena, &mut stats);
+            assert_eq!(stats.blocks, 1);
+            assert_eq!(stats.bytes_used, 24);
+
+            rusty_arena_reset(arena);
+            rusty_arena_get_stats(arena, &mut stats);
+            assert_eq!(stats.bytes_used, 0);
+            rusty_arena_destroy(arena);
+        }
+    }
+
+    #[test]
+    fn null_handle() {
+        unsafe {
+            assert!(rusty_arena_with_block_size(0).is_null());
+            assert!(rusty_arena_alloc(ptr::null(), 8, 8).is_null());
+            assert_eq!(rusty_arena_bytes_allocated(ptr::null()), 0);
+            rusty_arena_reset(ptr::null_mut());
+            rusty_arena_destroy(ptr::null_mut());
+        }
+    }
+}
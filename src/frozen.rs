@@ -0,0 +1,305 @@
+//! A read-only view of a typed arena that can be shared between threads.
+
+use alloc::vec::Vec;
+use core::alloc::GlobalAlloc;
+use core::ops::Index;
+use core::slice;
+
+use crate::arena::{Arena, Handle32};
+use crate::id::Idx;
+use crate::Global;
+
+/// An [`Arena`] that no longer accepts allocations, created by
+/// [`Arena::freeze`].
+///
+/// Unlike the arena it came from, it can be read through `&self`, and it is
+/// `Sync` when `T` is, so a tree or table built once can be shared between
+/// threads. Values are addressed by their position in allocation order,
+/// either as a `usize` or as the [`Idx`] handed out by [`Arena::alloc_idx`].
+/// It has no lifetime parameter, so it can be stored in a struct field or
+/// moved to another thread.
+pub struct FrozenArena<T, A: GlobalAlloc = Global> {
+    // The values of each non-empty block, and the index of the first one of
+    // each.
+    slices: Vec<*const [T]>,
+    starts: Vec<usize>,
+    len: usize,
+    // The values of every block, empty ones included, which is what a
+    // `Handle32` indexes.
+    blocks: Vec<*const [T]>,
+    // Only kept to drop the values and release the blocks.
+    arena: Arena<T, A>,
+}
+
+// Nothing is written through `&self`: the slices were taken before the arena
+// was frozen, and the arena is only touched again by `thaw` and `drop`.
+unsafe impl<T: Sync, A: GlobalAlloc> Sync for FrozenArena<T, A> {}
+unsafe impl<T: Send, A: GlobalAlloc + Send> Send for FrozenArena<T, A> {}
+
+impl<T, A: GlobalAlloc> FrozenArena<T, A> {
+    pub(crate) fn new(arena: Arena<T, A>, blocks: Vec<*const [T]>) -> Self {
+        let slices: Vec<_> = blocks.iter().copied().filter(|s| !s.is_empty()).collect();
+        let mut len = 0;
+        let starts = slices
+            .iter()
+            .map(|s| {
+                let start = len;
+                len += s.len();
+                start
+            })
+            .collect();
+        Self {
+            slices,
+            starts,
+            len,
+            blocks,
+            arena,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value allocated `index`-th.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let block = self.starts.partition_point(|&start| start <= index) - 1;
+        let slice = unsafe { &*self.slices[block] };
+        Some(&slice[index - self.starts[block]])
+    }
+
+    /// Returns the value `handle` refers to, see [`Arena::handle_of`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` doesn't refer to a value in this arena.
+    pub fn resolve(&self, handle: Handle32) -> &T {
+        // Only the block list taken at freezing time is read, so that this
+        // doesn't touch the arena's `RefCell`s from several threads.
+        let value = self
+            .blocks
+            .get(handle.block())
+            .and_then(|&block| unsafe { &*block }.get(handle.index()));
+        match value {
+            Some(x) => x,
+            None => panic!("{handle:?} does not refer to a value in this arena"),
+        }
+    }
+
+    /// Returns an iterator over the values in allocation order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            slices: self.slices.iter(),
+            current: [].iter(),
+            len: self.len,
+        }
+    }
+
+    /// Turns the view back into an arena that accepts allocations.
+    pub fn thaw(self) -> Arena<T, A> {
+        self.arena
+    }
+}
+
+impl<T, A: GlobalAlloc> Index<usize> for FrozenArena<T, A> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(x) => x,
+            None => panic!(
+                "index {} out of bounds for a frozen arena of length {}",
+                index, self.len
+            ),
+        }
+    }
+}
+
+/// Looks up a value by the index [`Arena::alloc_idx`] returned for it.
+impl<T, A: GlobalAlloc> Index<Idx<T>> for FrozenArena<T, A> {
+    type Output = T;
+
+    fn index(&self, idx: Idx<T>) -> &T {
+        &self[idx.index()]
+    }
+}
+
+impl<'a, T, A: GlobalAlloc> IntoIterator for &'a FrozenArena<T, A> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of a [`FrozenArena`], created by
+/// [`FrozenArena::iter`].
+pub struct Iter<'a, T> {
+    slices: slice::Iter<'a, *const [T]>,
+    current: slice::Iter<'a, T>,
+    len: usize,
+}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Iter {
+            slices: self.slices.clone(),
+            current: self.current.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(x) = self.current.next() {
+                self.len -= 1;
+                return Some(x);
+            }
+            self.current = unsafe { &**self.slices.next()? }.iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::FrozenArena;
+    use crate::arena::Arena;
+    use crate::id::Idx;
+
+    #[test]
+    fn shared_between_threads() {
+        let arena = Arena::with_block_size(64);
+        for i in 0..1000u64 {
+            arena.alloc(i.to_string());
+        }
+        let frozen = arena.freeze();
+        assert_eq!(frozen.len(), 1000);
+        thread::scope(|s| {
+            for t in 0..4 {
+                let frozen = &frozen;
+                s.spawn(move || {
+                    for i in (t..1000).step_by(4) {
+                        assert_eq!(frozen[i], i.to_string());
+                    }
+                    assert_eq!(frozen.iter().count(), 1000);
+                });
+            }
+        });
+        assert!(frozen.get(1000).is_none());
+
+        let arena = frozen.thaw();
+        arena.alloc(String::from("more"));
+        assert_eq!(arena.len(), 1001);
+    }
+
+    #[test]
+    fn resolve_between_threads() {
+        let arena = Arena::with_block_size(64);
+        let handles: Vec<_> = (0..1000u64)
+            .map(|i| arena.handle_of(arena.alloc(i)).unwrap())
+            .collect();
+        let frozen = arena.freeze();
+        thread::scope(|s| {
+            for t in 0..4 {
+                let (frozen, handles) = (&frozen, &handles);
+                s.spawn(move || {
+                    for i in (t..1000).step_by(4) {
+                        assert_eq!(*frozen.resolve(handles[i]), i as u64);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn linked_by_idx() {
+        struct Node {
+            name: String,
+            parent: Option<Idx<Node>>,
+        }
+
+        struct Tree {
+            nodes: FrozenArena<Node>,
+            leaf: Idx<Node>,
+        }
+
+        let arena = Arena::with_block_size(64);
+        let root = arena.next_idx();
+        assert_eq!(
+            arena.alloc_idx(Node {
+                name: String::from("root"),
+                parent: None,
+            }),
+            root
+        );
+        let mut parent = root;
+        for i in 0..100 {
+            parent = arena.alloc_idx(Node {
+                name: i.to_string(),
+                parent: Some(parent),
+            });
+        }
+        let tree = Tree {
+            nodes: arena.freeze(),
+            leaf: parent,
+        };
+
+        let path = thread::spawn(move || {
+            let mut path = Vec::new();
+            let mut node = &tree.nodes[tree.leaf];
+            while let Some(parent) = node.parent {
+                path.push(node.name.clone());
+                node = &tree.nodes[parent];
+            }
+            path.push(node.name.clone());
+            path
+        })
+        .join()
+        .unwrap();
+        assert_eq!(path.len(), 101);
+        assert_eq!(path[0], "99");
+        assert_eq!(path[100], "root");
+    }
+
+    #[test]
+    fn empty_and_zero_sized() {
+        let frozen = Arena::<u32>::new().freeze();
+        assert!(frozen.is_empty());
+        assert_eq!(frozen.iter().next(), None);
+
+        let arena = Arena::new();
+        arena.alloc(());
+        arena.alloc(());
+        let frozen = arena.freeze();
+        assert_eq!(frozen.len(), 2);
+        assert_eq!(frozen[1], ());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn index_out_of_bounds() {
+        let arena = Arena::new();
+        arena.alloc(1);
+        let _ = arena.freeze()[1];
+    }
+}
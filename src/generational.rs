@@ -0,0 +1,259 @@
+//! An index-based arena whose values can be removed, with handles that
+//! detect reuse of their slot.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Index, IndexMut};
+
+/// A handle to a value in a [`GenArena<T>`].
+///
+/// It carries the generation of its slot, so once the value is removed the
+/// handle stops resolving even if the slot is reused.
+pub struct GenIdx<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> GenIdx<T> {
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for GenIdx<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for GenIdx<T> {}
+
+impl<T> PartialEq for GenIdx<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for GenIdx<T> {}
+
+impl<T> Hash for GenIdx<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for GenIdx<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GenIdx({}v{})", self.index, self.generation)
+    }
+}
+
+enum Slot<T> {
+    Occupied(T),
+    // Links to the next free slot.
+    Free(Option<u32>),
+}
+
+struct Entry<T> {
+    // Bumped every time the slot is emptied.
+    generation: u32,
+    slot: Slot<T>,
+}
+
+/// An arena of values that can be removed individually, reusing their slots
+/// for later insertions.
+pub struct GenArena<T> {
+    entries: Vec<Entry<T>>,
+    free_head: Option<u32>,
+    len: usize,
+}
+
+impl<T> GenArena<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            free_head: None,
+            len: 0,
+        }
+    }
+
+    /// Moves `value` into the first free slot and returns its handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena already has `u32::MAX` slots.
+    pub fn insert(&mut self, value: T) -> GenIdx<T> {
+        let index = match self.free_head {
+            Some(index) => {
+                let entry = &mut self.entries[index as usize];
+                self.free_head = match entry.slot {
+                    Slot::Free(next) => next,
+                    Slot::Occupied(_) => unreachable!(),
+                };
+                entry.slot = Slot::Occupied(value);
+                index
+            }
+            None => {
+                let index = u32::try_from(self.entries.len()).expect("too many slots");
+                self.entries.push(Entry {
+                    generation: 0,
+                    slot: Slot::Occupied(value),
+                });
+                index
+            }
+        };
+        self.len += 1;
+        GenIdx {
+            index,
+            generation: self.entries[index as usize].generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Removes the value of `idx` and returns it, or `None` if it was already
+    /// removed.
+    pub fn remove(&mut self, idx: GenIdx<T>) -> Option<T> {
+        let entry = self.entries.get_mut(idx.index())?;
+        if entry.generation != idx.generation || matches!(entry.slot, Slot::Free(_)) {
+            return None;
+        }
+        let value = match mem::replace(&mut entry.slot, Slot::Free(self.free_head)) {
+            Slot::Occupied(value) => value,
+            Slot::Free(_) => unreachable!(),
+        };
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free_head = Some(idx.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn contains(&self, idx: GenIdx<T>) -> bool {
+        self.get(idx).is_some()
+    }
+
+    pub fn get(&self, idx: GenIdx<T>) -> Option<&T> {
+        match self.entries.get(idx.index()) {
+            Some(Entry {
+                generation,
+                slot: Slot::Occupied(value),
+            }) if *generation == idx.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: GenIdx<T>) -> Option<&mut T> {
+        match self.entries.get_mut(idx.index()) {
+            Some(Entry {
+                generation,
+                slot: Slot::Occupied(value),
+            }) if *generation == idx.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of values, not counting free slots.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the live values with their handles, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (GenIdx<T>, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| match &entry.slot {
+                Slot::Occupied(value) => Some((
+                    GenIdx {
+                        index: i as u32,
+                        generation: entry.generation,
+                        _marker: PhantomData,
+                    },
+                    value,
+                )),
+                Slot::Free(_) => None,
+            })
+    }
+}
+
+impl<T> Default for GenArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<GenIdx<T>> for GenArena<T> {
+    type Output = T;
+
+    fn index(&self, idx: GenIdx<T>) -> &T {
+        self.get(idx).expect("stale arena handle")
+    }
+}
+
+impl<T> IndexMut<GenIdx<T>> for GenArena<T> {
+    fn index_mut(&mut self, idx: GenIdx<T>) -> &mut T {
+        self.get_mut(idx).expect("stale arena handle")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GenArena;
+
+    #[test]
+    fn stale_handle() {
+        let mut entities = GenArena::new();
+        let a = entities.insert("a");
+        let b = entities.insert("b");
+        assert_eq!(entities.remove(a), Some("a"));
+        assert_eq!(entities.remove(a), None);
+        assert!(!entities.contains(a));
+
+        // The slot of `a` is reused, but the old handle doesn't see the new
+        // value.
+        let c = entities.insert("c");
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c, a);
+        assert_eq!(entities.get(a), None);
+        assert_eq!(entities[c], "c");
+        assert_eq!(entities[b], "b");
+        assert_eq!(entities.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "stale arena handle")]
+    fn index_stale() {
+        let mut arena = GenArena::new();
+        let a = arena.insert(1);
+        arena.remove(a);
+        arena[a] += 1;
+    }
+
+    #[test]
+    fn iter() {
+        let mut arena = GenArena::new();
+        let idx: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        for &i in idx.iter().step_by(2) {
+            arena.remove(i);
+        }
+        assert_eq!(
+            arena.iter().map(|(_, &x)| x).collect::<Vec<_>>(),
+            [1, 3, 5, 7, 9]
+        );
+        for (i, &x) in arena.iter() {
+            assert_eq!(i, idx[x]);
+        }
+    }
+}
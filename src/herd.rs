@@ -0,0 +1,239 @@
+//! A group of bump arenas that threads borrow one at a time.
+
+use alloc::vec::Vec;
+use core::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::bump::Bump;
+use crate::ArenaAllocError;
+
+/// Owns a [`Bump`] per thread that allocates from it, handed out as a
+/// [`Member`].
+///
+/// Values borrow from the herd rather than the member, so they stay valid
+/// after the member is dropped and the worker threads joined. A dropped
+/// member's `Bump` goes back to the herd, to be reused by the next
+/// [`get`](Self::get).
+///
+/// ```
+/// use rusty_arena::herd::Herd;
+///
+/// let herd = Herd::new();
+/// let names: Vec<&str> = std::thread::scope(|s| {
+///     let workers: Vec<_> = (0..4)
+///         .map(|i| {
+///             let herd = &herd;
+///             s.spawn(move || herd.get().alloc_fmt(format_args!("worker {i}")))
+///         })
+///         .collect();
+///     workers.into_iter().map(|w| w.join().unwrap()).collect()
+/// });
+/// assert_eq!(names, ["worker 0", "worker 1", "worker 2", "worker 3"]);
+/// ```
+pub struct Herd {
+    // The arenas not lent out to a member. Moving a `Bump` doesn't move the
+    // blocks holding its values.
+    idle: Mutex<Vec<Bump>>,
+}
+
+impl Herd {
+    pub fn new() -> Self {
+        Self {
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Lends an arena to the calling thread, reusing an idle one if there
+    /// is any.
+    pub fn get(&self) -> Member<'_> {
+        let bump = self.lock().pop().unwrap_or_default();
+        Member {
+            herd: self,
+            bump: Some(bump),
+        }
+    }
+
+    /// Returns the number of arenas that were ever lent out.
+    pub fn len(&mut self) -> usize {
+        self.idle_mut().len()
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        self.idle_mut().is_empty()
+    }
+
+    /// Returns the number of bytes obtained from the system allocator by all
+    /// arenas.
+    pub fn bytes_allocated(&mut self) -> usize {
+        self.idle_mut()
+            .iter()
+            .map(|bump| bump.bytes_allocated())
+            .sum()
+    }
+
+    /// Resets every arena, keeping their blocks for reuse.
+    pub fn reset(&mut self) {
+        for bump in self.idle_mut() {
+            bump.reset();
+        }
+    }
+
+    // No member can be alive while the herd is borrowed mutably, so every
+    // arena is idle.
+    fn idle_mut(&mut self) -> &mut Vec<Bump> {
+        self.idle.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Pushing and popping can't leave the list half updated.
+    fn lock(&self) -> MutexGuard<'_, Vec<Bump>> {
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Herd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An arena lent out by a [`Herd`], created by [`Herd::get`].
+///
+/// Its allocation methods return references that live as long as the herd.
+pub struct Member<'h> {
+    herd: &'h Herd,
+    bump: Option<Bump>,
+}
+
+impl<'h> Member<'h> {
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, data: T) -> &'h mut T {
+        self.bump().alloc(data)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with<T, F: FnOnce() -> T>(&self, f: F) -> &'h mut T {
+        self.bump().alloc_with(f)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, data: T) -> Result<&'h mut T, ArenaAllocError> {
+        self.bump().try_alloc(data)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &'h mut [T] {
+        self.bump().alloc_slice_copy(src)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_clone<T: Clone>(&self, src: &[T]) -> &'h mut [T] {
+        self.bump().alloc_slice_clone(src)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_from_iter<T, I: IntoIterator<Item = T>>(&self, iter: I) -> &'h mut [T] {
+        self.bump().alloc_from_iter(iter)
+    }
+
+    pub fn alloc_str(&self, src: &str) -> &'h str {
+        self.bump().alloc_str(src)
+    }
+
+    pub fn alloc_fmt(&self, args: fmt::Arguments<'_>) -> &'h str {
+        self.bump().alloc_fmt(args)
+    }
+
+    /// Returns the borrowed arena, e.g. to build a [`Vec`](crate::vec::Vec)
+    /// in it. Values allocated through it only live as long as the member
+    /// is borrowed.
+    pub fn as_bump(&self) -> &Bump {
+        self.bump.as_ref().unwrap()
+    }
+
+    pub fn herd(&self) -> &'h Herd {
+        self.herd
+    }
+
+    fn bump(&self) -> &'h Bump {
+        let bump: *const Bump = self.as_bump();
+        // Only used to allocate: the values outlive the member since its
+        // blocks go back to the herd, and are only released through
+        // `&mut Herd`. The `Bump` itself must not escape as `&'h`, as it
+        // moves back to the herd and other threads borrow it next.
+        unsafe { &*bump }
+    }
+}
+
+impl Drop for Member<'_> {
+    fn drop(&mut self) {
+        if let Some(bump) = self.bump.take() {
+            self.herd.lock().push(bump);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::Herd;
+
+    struct Node<'h> {
+        value: u32,
+        children: &'h [&'h Node<'h>],
+    }
+
+    fn sum(node: &Node<'_>) -> u32 {
+        node.value + node.children.iter().map(|c| sum(c)).sum::<u32>()
+    }
+
+    #[test]
+    fn values_outlive_members() {
+        let mut herd = Herd::new();
+        let subtrees: Vec<&Node<'_>> = thread::scope(|s| {
+            let workers: Vec<_> = (0..4)
+                .map(|t| {
+                    let herd = &herd;
+                    s.spawn(move || {
+                        let member = herd.get();
+                        let leaves: Vec<&Node<'_>> = (0..10)
+                            .map(|i| {
+                                &*member.alloc(Node {
+                                    value: t * 10 + i,
+                                    children: &[],
+                                })
+                            })
+                            .collect();
+                        &*member.alloc(Node {
+                            value: 0,
+                            children: member.alloc_slice_copy(&leaves),
+                        })
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        let root = herd.get().alloc(Node {
+            value: 0,
+            children: herd.get().alloc_slice_copy(&subtrees),
+        });
+        assert_eq!(sum(root), (0..40).sum::<u32>());
+        drop(subtrees);
+        assert!(herd.len() <= 5);
+        assert!(herd.bytes_allocated() > 0);
+    }
+
+    #[test]
+    fn members_are_reused() {
+        let mut herd = Herd::new();
+        {
+            let a = herd.get();
+            let b = herd.get();
+            assert_eq!(*a.alloc(1) + *b.alloc(2), 3);
+        }
+        herd.get().alloc_str("again");
+        assert_eq!(herd.len(), 2);
+        herd.reset();
+        assert_eq!(herd.get().as_bump().stats().bytes_used, 0);
+    }
+}
@@ -0,0 +1,219 @@
+//! An arena that hands out typed indices instead of references.
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
+
+/// The index of a value in an [`IdArena<T>`].
+///
+/// Indices are plain numbers: they are `Copy`, can be stored anywhere, and
+/// don't borrow the arena, which makes cyclic graphs easy to build.
+pub struct Idx<T> {
+    index: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Idx<T> {
+    /// Builds the index of the `index`-th allocation, e.g. when reading back
+    /// a serialized graph.
+    pub fn from_u32(index: u32) -> Self {
+        Self {
+            index,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self.index
+    }
+
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+impl<T> Clone for Idx<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Idx<T> {}
+
+impl<T> PartialEq for Idx<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Idx<T> {}
+
+impl<T> PartialOrd for Idx<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Idx<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+impl<T> Hash for Idx<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Idx<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Idx({})", self.index)
+    }
+}
+
+/// An arena addressed by [`Idx<T>`] handles.
+///
+/// Values stay in the arena until it is dropped. Indices of one arena used
+/// with another either panic or refer to an unrelated value.
+pub struct IdArena<T> {
+    items: Vec<T>,
+}
+
+impl<T> IdArena<T> {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Moves `value` into the arena and returns its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena already holds `u32::MAX` values.
+    pub fn alloc(&mut self, value: T) -> Idx<T> {
+        let idx = self.next_idx();
+        self.items.push(value);
+        idx
+    }
+
+    /// Returns the index the next allocation will get, so that a value can
+    /// refer to itself.
+    pub fn next_idx(&self) -> Idx<T> {
+        Idx::from_u32(u32::try_from(self.items.len()).expect("too many values in the arena"))
+    }
+
+    pub fn get(&self, idx: Idx<T>) -> Option<&T> {
+        self.items.get(idx.index())
+    }
+
+    pub fn get_mut(&mut self, idx: Idx<T>) -> Option<&mut T> {
+        self.items.get_mut(idx.index())
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the values in allocation order, the one at position `i` being
+    /// the value of `Idx::from_u32(i)`.
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Iterates over the values with their indices, in allocation order.
+    pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &T)> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (Idx::from_u32(i as u32), x))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Idx<T>, &mut T)> {
+        self.items
+            .iter_mut()
+            .enumerate()
+            .map(|(i, x)| (Idx::from_u32(i as u32), x))
+    }
+}
+
+impl<T> Default for IdArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<Idx<T>> for IdArena<T> {
+    type Output = T;
+
+    fn index(&self, idx: Idx<T>) -> &T {
+        &self.items[idx.index()]
+    }
+}
+
+impl<T> IndexMut<Idx<T>> for IdArena<T> {
+    fn index_mut(&mut self, idx: Idx<T>) -> &mut T {
+        &mut self.items[idx.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdArena, Idx};
+
+    struct Node {
+        value: i32,
+        next: Option<Idx<Node>>,
+    }
+
+    #[test]
+    fn cycle() {
+        let mut nodes = IdArena::new();
+        let first = nodes.next_idx();
+        let a = nodes.alloc(Node {
+            value: 1,
+            next: None,
+        });
+        let b = nodes.alloc(Node {
+            value: 2,
+            next: Some(first),
+        });
+        assert_eq!(first, a);
+        nodes[a].next = Some(b);
+
+        let mut idx = a;
+        let mut sum = 0;
+        for _ in 0..4 {
+            sum += nodes[idx].value;
+            idx = nodes[idx].next.unwrap();
+        }
+        assert_eq!(sum, 6);
+        assert_eq!(nodes.len(), 2);
+    }
+
+    #[test]
+    fn get() {
+        let mut arena = IdArena::new();
+        let idx = arena.alloc("a");
+        assert_eq!(arena.get(idx), Some(&"a"));
+        *arena.get_mut(idx).unwrap() = "b";
+        assert_eq!(arena[Idx::from_u32(idx.as_u32())], "b");
+        assert_eq!(arena.get(Idx::from_u32(1)), None);
+        assert_eq!(
+            arena.iter().map(|(i, _)| i.index()).collect::<Vec<_>>(),
+            [0]
+        );
+    }
+}
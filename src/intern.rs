@@ -0,0 +1,124 @@
+//! Hash-consing on top of [`Arena`]: equal values share one allocation.
+
+use core::borrow::Borrow;
+use core::cell::RefCell;
+use core::hash::{Hash, Hasher};
+use std::collections::HashSet;
+
+use crate::arena::Arena;
+
+// A value in the arena, hashed and compared by what it points to.
+struct Interned<T>(*const T);
+
+impl<T> Interned<T> {
+    fn get(&self) -> &T {
+        // The arena keeps the value alive and never hands out a `&mut`.
+        unsafe { &*self.0 }
+    }
+}
+
+impl<T: Hash> Hash for Interned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Interned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<T: Eq> Eq for Interned<T> {}
+
+impl<T> Borrow<T> for Interned<T> {
+    fn borrow(&self) -> &T {
+        self.get()
+    }
+}
+
+/// An arena that stores each distinct value once.
+///
+/// [`intern`](Self::intern) returns the existing allocation when an equal
+/// value was interned before, so interned values can be compared by address
+/// with [`ptr::eq`](core::ptr::eq).
+pub struct InternArena<T> {
+    arena: Arena<T>,
+    set: RefCell<HashSet<Interned<T>>>,
+}
+
+impl<T: Hash + Eq> InternArena<T> {
+    pub fn new() -> Self {
+        Self {
+            arena: Arena::new(),
+            set: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the interned copy of `value`, allocating it on first sight.
+    pub fn intern(&self, value: T) -> &T {
+        if let Some(found) = self.get(&value) {
+            return found;
+        }
+        let interned = &*self.arena.alloc(value);
+        self.set.borrow_mut().insert(Interned(interned));
+        interned
+    }
+
+    /// Returns the interned value equal to `value`, if there is one.
+    pub fn get(&self, value: &T) -> Option<&T> {
+        let set = self.set.borrow();
+        set.get(value).map(|found| unsafe { &*found.0 })
+    }
+
+    /// Returns the number of distinct values.
+    pub fn len(&self) -> usize {
+        self.set.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Hash + Eq> Default for InternArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::InternArena;
+
+    #[derive(Debug, Hash, PartialEq, Eq)]
+    enum Type {
+        Int,
+        Ptr(&'static str),
+    }
+
+    #[test]
+    fn shares_equal_values() {
+        let types = InternArena::new();
+        let a = types.intern(Type::Ptr("i32"));
+        let b = types.intern(Type::Int);
+        let c = types.intern(Type::Ptr("i32"));
+        assert!(ptr::eq(a, c));
+        assert!(!ptr::eq(a, b));
+        assert_eq!(types.len(), 2);
+        assert!(ptr::eq(types.get(&Type::Int).unwrap(), b));
+        assert!(types.get(&Type::Ptr("u8")).is_none());
+    }
+
+    #[test]
+    fn many() {
+        let strings = InternArena::new();
+        let first: Vec<_> = (0..1000).map(|i| strings.intern(i.to_string())).collect();
+        for (i, s) in first.iter().enumerate() {
+            assert!(ptr::eq(*s, strings.intern(i.to_string())));
+        }
+        assert_eq!(strings.len(), 1000);
+    }
+}
@@ -1,132 +1,94 @@
-pub mod arena {
-    use std::alloc::{alloc, dealloc, Layout};
-    use std::cell::RefCell;
-    use std::marker::PhantomData;
-    use std::marker::Sized;
-    use std::mem;
-    use std::ptr;
-
-    const BLOCK_SIZE: usize = 4096;
-
-    struct Block {
-        ptr: *mut u8,
-        count_of_elements: usize,
-    }
-
-    struct Internal<'a, T: 'a> {
-        blocks: Vec<Block>,
-        bytes: usize,
-        alloc_bytes_remaining: usize,
-        alloc_ptr: *mut u8,
-        _marker: PhantomData<&'a T>,
-    }
-
-    impl<'a, T: Sized> Internal<'a, T> {
-        fn new() -> Self {
-            Self {
-                blocks: Vec::new(),
-                bytes: 0,
-                alloc_bytes_remaining: 0,
-                alloc_ptr: ptr::null_mut(),
-                _marker: PhantomData,
-            }
-        }
-
-        unsafe fn alloc(&mut self, data: T) -> &'a mut T {
-            let mut layout = Layout::new::<T>();
-            if layout.size() > self.alloc_bytes_remaining {
-                self.alloc_bytes_remaining = layout.size();
-                if layout.size() <= BLOCK_SIZE {
-                    layout = Layout::from_size_align_unchecked(BLOCK_SIZE, 0);
-                    self.alloc_bytes_remaining = BLOCK_SIZE;
-                }
-                self.bytes += layout.size();
-                let block_ptr = alloc(layout);
-                self.blocks.push(Block {
-                    ptr: block_ptr,
-                    count_of_elements: 0,
-                });
-                self.alloc_ptr = block_ptr;
-            }
-
-            self.alloc_bytes_remaining -= layout.size();
-            let ptr = self.alloc_ptr;
-            self.alloc_ptr = self.alloc_ptr.add(layout.size());
-            self.blocks.last_mut().map(|b| b.count_of_elements += 1);
-
-            let x = mem::transmute::<*mut u8, &mut T>(ptr);
-            ptr::write(x, data);
-
-            x
-        }
-    }
-
-    impl<'a, T: Sized> Drop for Internal<'a, T> {
-        fn drop(&mut self) {
-            let layout = Layout::new::<T>();
-            unsafe {
-                for block in self.blocks.iter() {
-                    for i in 0..block.count_of_elements {
-                        let offset = layout.size() * i;
-                        let ptr = block.ptr.add(offset);
-                        let x = mem::transmute::<*mut u8, &mut T>(ptr);
-                        std::ptr::drop_in_place(x);
-                    }
-                    dealloc(block.ptr, layout);
-                }
-            }
-        }
-    }
-
-    pub struct Arena<'a, T> {
-        internal: RefCell<Internal<'a, T>>,
-    }
-
-    impl<'a, T: Sized> Arena<'a, T> {
-        pub fn new() -> Self {
-            Self {
-                internal: RefCell::new(Internal::new()),
-            }
-        }
-
-        pub fn alloc(&self, data: T) -> &'a mut T {
-            unsafe { self.internal.borrow_mut().alloc(data) }
-        }
-
-        pub fn bytes_allocated(&self) -> usize {
-            self.internal.borrow().bytes
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::cell::RefCell;
-
-    use super::arena;
-
-    struct X<'a> {
-        drop_cnt: &'a RefCell<i32>,
-    }
-
-    impl<'a> Drop for X<'a> {
-        fn drop(&mut self) {
-            *self.drop_cnt.borrow_mut() += 1;
-        }
-    }
-
-    #[test]
-    fn it_works() {
-        let drop_cnt = RefCell::new(0);
-        {
-            let arena = arena::Arena::new();
-            for _ in 0..1000 {
-                arena.alloc(X {
-                    drop_cnt: &drop_cnt,
-                });
-            }
-            assert!(arena.bytes_allocated() > 0);
-        }
-        assert_eq!(*drop_cnt.borrow(), 1000);
-    }
-}
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly", feature(allocator_api, dropck_eyepatch))]
+#![cfg_attr(feature = "nightly", feature(strict_provenance_lints))]
+#![cfg_attr(
+    feature = "nightly",
+    deny(fuzzy_provenance_casts, lossy_provenance_casts)
+)]
+#![cfg_attr(all(test, feature = "nightly"), feature(btreemap_alloc))]
+
+extern crate alloc;
+// Lets the code generated by the derive macros name this crate from inside it.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as rusty_arena;
+
+#[cfg(any(feature = "nightly", feature = "allocator-api2", feature = "hashbrown"))]
+mod allocator;
+pub mod any;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+pub mod arena;
+mod block;
+pub mod boxed;
+pub mod bump;
+#[cfg(any(feature = "hashbrown", feature = "nightly"))]
+pub mod collections;
+#[cfg(feature = "std")]
+pub mod concurrent;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod double;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod frozen;
+pub mod generational;
+#[cfg(feature = "std")]
+pub mod herd;
+pub mod id;
+#[cfg(feature = "std")]
+pub mod intern;
+#[macro_use]
+mod macros;
+#[cfg(all(feature = "mmap", any(unix, windows)))]
+mod mmap;
+mod observer;
+#[cfg(feature = "std")]
+mod persist;
+pub mod pinned;
+pub mod pool;
+pub mod rc;
+pub mod relative;
+mod sanitizer;
+pub mod scratch;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+pub mod sharded;
+#[cfg(all(feature = "shm", unix))]
+mod shm;
+pub mod snapshot;
+#[cfg(feature = "derive")]
+pub mod soa;
+mod static_arena;
+mod stats;
+pub mod string;
+#[cfg(feature = "std")]
+pub mod symbol;
+#[cfg(feature = "std")]
+pub mod sync;
+mod telemetry;
+#[cfg(feature = "backtrace")]
+mod trace;
+pub mod vec;
+
+pub use block::Global;
+pub use error::ArenaAllocError;
+pub use macros::ArenaAllocatable;
+#[cfg(all(feature = "guard-pages", unix))]
+pub use mmap::GuardedAlloc;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub use mmap::NumaNode;
+#[cfg(all(feature = "mmap", any(unix, windows)))]
+pub use mmap::{HugePages, MmapAlloc};
+#[cfg(all(feature = "secure", unix))]
+pub use mmap::{SecureAlloc, SecureArena};
+pub use observer::ArenaObserver;
+#[cfg(feature = "derive")]
+pub use rusty_arena_derive::SoaArena;
+#[cfg(all(feature = "shm", unix))]
+pub use shm::SharedSegment;
+pub use static_arena::StaticArena;
+pub use stats::{AllocatedSince, ArenaStats};
+#[cfg(feature = "backtrace")]
+pub use trace::{AllocReport, AllocTrace};
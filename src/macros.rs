@@ -0,0 +1,220 @@
+/// Allocation into a multi-type arena declared with [`declare_arena!`]:
+/// implemented for each of its types, and used by its `alloc` method to pick
+/// the typed arena that holds `Self`.
+pub trait ArenaAllocatable<'a, A>: Sized {
+    #[allow(clippy::mut_from_ref)]
+    fn allocate_on(self, arena: &'a A) -> &'a mut Self;
+}
+
+/// Declares a struct holding one typed [`Arena`](crate::arena::Arena) per
+/// listed type, with a single `alloc` entry point.
+///
+/// ```
+/// use rusty_arena::declare_arena;
+///
+/// struct Expr<'a> {
+///     lhs: Option<&'a Expr<'a>>,
+/// }
+/// struct Stmt<'a> {
+///     expr: &'a Expr<'a>,
+/// }
+///
+/// declare_arena! {
+///     pub struct AstArena<'a> {
+///         exprs: Expr<'a>,
+///         stmts: Stmt<'a>,
+///     }
+/// }
+///
+/// let arena = AstArena::new();
+/// let lhs = arena.alloc(Expr { lhs: None });
+/// let expr = arena.alloc(Expr { lhs: Some(lhs) });
+/// arena.alloc(Stmt { expr });
+/// assert_eq!(arena.exprs.len(), 2);
+/// ```
+///
+/// Each field is a public `Arena` of its type, and every listed type
+/// implements [`ArenaAllocatable`] for the struct. Listing a type twice fails
+/// to compile with conflicting implementations.
+#[macro_export]
+macro_rules! declare_arena {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident $(<$lt:lifetime>)? {
+            $($field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        // The lifetime is passed a second time as a single token tree so that
+        // it can be repeated once per field.
+        $crate::declare_arena! {
+            @decl [$($lt)?] [$($lt)?]
+            $(#[$attr])*
+            $vis struct $name {
+                $($field: $ty),*
+            }
+        }
+    };
+    (
+        @decl [$($lt:lifetime)?] $generics:tt
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($field:ident: $ty:ty),*
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name $(<$lt>)? {
+            $(pub $field: $crate::arena::Arena<$ty>,)*
+        }
+
+        impl $(<$lt>)? $name $(<$lt>)? {
+            $vis fn new() -> Self {
+                Self {
+                    $($field: $crate::arena::Arena::new(),)*
+                }
+            }
+
+            /// Allocates `value` in the arena for its type.
+            #[allow(clippy::mut_from_ref)]
+            $vis fn alloc<'s, T: $crate::ArenaAllocatable<'s, Self>>(&'s self, value: T) -> &'s mut T {
+                value.allocate_on(self)
+            }
+        }
+
+        impl $(<$lt>)? ::core::default::Default for $name $(<$lt>)? {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        $($crate::declare_arena!(@impl $name $generics $field: $ty);)*
+    };
+    (@impl $name:ident [$($lt:lifetime)?] $field:ident: $ty:ty) => {
+        impl<'s $(, $lt)?> $crate::ArenaAllocatable<'s, $name $(<$lt>)?> for $ty {
+            fn allocate_on(self, arena: &'s $name $(<$lt>)?) -> &'s mut Self {
+                arena.$field.alloc(self)
+            }
+        }
+    };
+}
+
+/// Allocates a value in a [`Bump`](crate::bump::Bump) and returns it as a
+/// trait object, e.g. `alloc_dyn!(bump, visitor => dyn Visitor)`.
+///
+/// Like everything in a `Bump`, the value is never dropped; use
+/// [`arena_box_dyn!`] for values that own resources.
+#[macro_export]
+macro_rules! alloc_dyn {
+    ($bump:expr, $value:expr => $ty:ty) => {{
+        let value: &mut $ty = $bump.alloc($value);
+        value
+    }};
+}
+
+/// Like [`alloc_dyn!`], but returns an
+/// [`ArenaBox`](crate::boxed::ArenaBox) that drops the value when it is
+/// dropped: `arena_box_dyn!(bump, callback => dyn FnMut())`.
+///
+/// Only a `Bump` is accepted, since a typed arena would drop the value a
+/// second time:
+///
+/// ```compile_fail
+/// use rusty_arena::arena::Arena;
+/// use rusty_arena::arena_box_dyn;
+///
+/// let arena = Arena::new();
+/// let debug = arena_box_dyn!(arena, String::new() => dyn core::fmt::Debug);
+/// ```
+#[macro_export]
+macro_rules! arena_box_dyn {
+    ($bump:expr, $value:expr => $ty:ty) => {{
+        let bump: &$crate::bump::Bump<_> = &$bump;
+        let value: &mut $ty = bump.alloc($value);
+        // Freshly allocated, so nothing else can reach the value.
+        unsafe { $crate::boxed::ArenaBox::from_raw(value) }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    struct Counted<'c>(&'c Cell<usize>);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    declare_arena! {
+        struct Mixed<'c> {
+            ints: u32,
+            names: String,
+            counted: Counted<'c>,
+        }
+    }
+
+    trait Shape {
+        fn area(&self) -> u32;
+    }
+
+    struct Square(u32);
+
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+    }
+
+    impl Shape for Counted<'_> {
+        fn area(&self) -> u32 {
+            0
+        }
+    }
+
+    #[test]
+    fn alloc_dyn() {
+        let bump = crate::bump::Bump::new();
+        let shapes: [&mut dyn Shape; 2] = [
+            alloc_dyn!(bump, Square(3) => dyn Shape),
+            alloc_dyn!(bump, Square(1) => dyn Shape),
+        ];
+        assert_eq!(shapes.iter().map(|s| s.area()).sum::<u32>(), 10);
+
+        let mut calls = 0;
+        let f = alloc_dyn!(bump, || calls += 1 => dyn FnMut());
+        f();
+        f();
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn arena_box_dyn() {
+        let drops = Cell::new(0);
+        let bump = crate::bump::Bump::new();
+        let shape = arena_box_dyn!(bump, Counted(&drops) => dyn Shape);
+        assert_eq!(shape.area(), 0);
+        drop(shape);
+        assert_eq!(drops.get(), 1);
+
+        // Also through a reference to the arena.
+        let bump = &bump;
+        drop(arena_box_dyn!(bump, Counted(&drops) => dyn Shape));
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn alloc_per_type() {
+        let drops = Cell::new(0);
+        let arena = Mixed::new();
+        *arena.alloc(1u32) += 1;
+        arena.alloc(String::from("x"));
+        arena.alloc(Counted(&drops));
+        arena.alloc(Counted(&drops));
+        assert_eq!(arena.ints.len(), 1);
+        assert_eq!(arena.names.len(), 1);
+        assert_eq!(arena.counted.len(), 2);
+        drop(arena);
+        assert_eq!(drops.get(), 2);
+    }
+}
@@ -0,0 +1,577 @@
+use core::alloc::{GlobalAlloc, Layout};
+
+use crate::Global;
+
+/// A block allocator that maps every block with `mmap` and unmaps it as soon
+/// as the arena releases it, e.g. `Bump::new_in(MmapAlloc::new())`.
+///
+/// Blocks bypass `malloc` entirely, so a multi-gigabyte arena doesn't
+/// fragment its heap, and dropping the arena hands the memory straight back
+/// to the OS. Mappings are made of whole pages: use
+/// [`with_min_mapping`](Self::with_min_mapping) to keep small blocks on the
+/// global allocator.
+///
+/// On Windows the blocks are reserved and committed with `VirtualAlloc`, and
+/// released with `VirtualFree`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MmapAlloc {
+    min_mapping: usize,
+    huge_pages: Option<HugePages>,
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    numa_node: Option<NumaNode>,
+}
+
+/// The size of the huge pages requested by [`MmapAlloc::huge_pages`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HugePages {
+    Size2MiB,
+    Size1GiB,
+}
+
+impl HugePages {
+    pub const fn size(self) -> usize {
+        match self {
+            HugePages::Size2MiB => 1 << 21,
+            HugePages::Size1GiB => 1 << 30,
+        }
+    }
+}
+
+/// The NUMA node that [`MmapAlloc::numa_node`] places blocks on.
+#[cfg(all(feature = "numa", target_os = "linux"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumaNode {
+    /// The node of the thread that first touches each page.
+    Local,
+    /// The node with this id.
+    Id(usize),
+}
+
+impl MmapAlloc {
+    pub const fn new() -> Self {
+        Self::with_min_mapping(0)
+    }
+
+    /// Maps only blocks of at least `size` bytes, and takes smaller ones from
+    /// the global allocator.
+    pub const fn with_min_mapping(size: usize) -> Self {
+        Self {
+            min_mapping: size,
+            huge_pages: None,
+            #[cfg(all(feature = "numa", target_os = "linux"))]
+            numa_node: None,
+        }
+    }
+
+    /// Backs the mappings with huge pages, which cuts TLB misses on large
+    /// arenas. Mappings are rounded up to whole huge pages.
+    ///
+    /// On Linux this asks for pages reserved through `hugetlbfs`, and falls
+    /// back to transparent huge pages when none are available. On Windows it
+    /// asks for large pages, which needs the `SeLockMemoryPrivilege`, and
+    /// falls back to regular pages. Elsewhere it only aligns and rounds the
+    /// mappings.
+    pub const fn huge_pages(self, pages: HugePages) -> Self {
+        Self {
+            huge_pages: Some(pages),
+            ..self
+        }
+    }
+
+    /// Binds the memory of every mapping to a NUMA node with `mbind`, so that
+    /// an arena filled and read on one socket stays in its local memory.
+    ///
+    /// Binding is best effort: on a kernel without NUMA support the blocks are
+    /// mapped as usual.
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    pub const fn numa_node(self, node: NumaNode) -> Self {
+        Self {
+            numa_node: Some(node),
+            ..self
+        }
+    }
+
+    fn maps(&self, layout: Layout) -> bool {
+        layout.size() >= self.min_mapping
+    }
+}
+
+unsafe impl GlobalAlloc for MmapAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !self.maps(layout) {
+            return Global.alloc(layout);
+        }
+        let ptr = sys::map(layout, self.huge_pages);
+        #[cfg(all(feature = "numa", target_os = "linux"))]
+        if let Some(node) = self.numa_node {
+            if !ptr.is_null() {
+                let len = sys::mapping_len(layout, self.huge_pages).unwrap();
+                sys::bind(ptr, len, node);
+            }
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if !self.maps(layout) {
+            return Global.dealloc(ptr, layout);
+        }
+        sys::unmap(ptr, layout, self.huge_pages)
+    }
+}
+
+/// A block allocator for debugging unsafe code that writes through arena
+/// pointers: each block gets a mapping of its own, between two inaccessible
+/// guard pages.
+///
+/// Blocks end as close to the trailing guard page as their alignment allows,
+/// so running off the end of a block faults on the spot instead of silently
+/// corrupting its neighbour. It is meant for debug builds only, since every
+/// block costs two extra pages and a few system calls:
+///
+/// ```
+/// # use rusty_arena::{bump::Bump, GuardedAlloc};
+/// #[cfg(debug_assertions)]
+/// let bump = Bump::new_in(GuardedAlloc);
+/// ```
+///
+/// Alignments above the page size are not supported, and fail to allocate.
+#[cfg(all(feature = "guard-pages", unix))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GuardedAlloc;
+
+#[cfg(all(feature = "guard-pages", unix))]
+unsafe impl GlobalAlloc for GuardedAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        sys::map_guarded(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        sys::unmap_guarded(ptr, layout)
+    }
+}
+
+/// A block allocator for secrets such as key material: blocks are locked
+/// into RAM with `mlock`, so they never reach swap, and left out of core
+/// dumps where the platform supports it.
+///
+/// Blocks are wiped before they are unmapped. Resetting an arena keeps its
+/// blocks and doesn't wipe them, so values that hold secrets should wipe
+/// themselves on drop.
+///
+/// A block that can't be locked, e.g. past `RLIMIT_MEMLOCK`, fails to
+/// allocate rather than being handed out unlocked.
+#[cfg(all(feature = "secure", unix))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SecureAlloc;
+
+/// A typed arena whose blocks come from [`SecureAlloc`]:
+/// `let keys: SecureArena<Key> = Arena::new_in(SecureAlloc)`.
+#[cfg(all(feature = "secure", unix))]
+pub type SecureArena<T> = crate::arena::Arena<T, SecureAlloc>;
+
+#[cfg(all(feature = "secure", unix))]
+unsafe impl GlobalAlloc for SecureAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        sys::map_locked(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        sys::unmap_locked(ptr, layout)
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use core::alloc::Layout;
+    use core::ptr;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::HugePages;
+
+    pub(super) fn page_size() -> usize {
+        static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+        let mut size = PAGE_SIZE.load(Ordering::Relaxed);
+        if size == 0 {
+            size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            PAGE_SIZE.store(size, Ordering::Relaxed);
+        }
+        size
+    }
+
+    /// Returns the length of the mapping that holds a block of `layout`.
+    pub(super) fn mapping_len(layout: Layout, huge_pages: Option<HugePages>) -> Option<usize> {
+        let page = huge_pages.map_or_else(page_size, HugePages::size);
+        Some(layout.size().checked_add(page - 1)? & !(page - 1))
+    }
+
+    pub(super) unsafe fn map(layout: Layout, huge_pages: Option<HugePages>) -> *mut u8 {
+        let Some(len) = mapping_len(layout, huge_pages) else {
+            return ptr::null_mut();
+        };
+        let Some(pages) = huge_pages else {
+            return map_aligned(len, layout.align(), 0);
+        };
+        #[cfg(target_os = "linux")]
+        {
+            let size_flag = match pages {
+                HugePages::Size2MiB => libc::MAP_HUGE_2MB,
+                HugePages::Size1GiB => libc::MAP_HUGE_1GB,
+            };
+            // Huge page mappings are aligned to the page size.
+            if layout.align() <= pages.size() {
+                let ptr = map_aligned(len, 1, libc::MAP_HUGETLB | size_flag);
+                if !ptr.is_null() {
+                    return ptr;
+                }
+            }
+        }
+        let ptr = map_aligned(len, layout.align().max(pages.size()), 0);
+        #[cfg(target_os = "linux")]
+        if !ptr.is_null() {
+            libc::madvise(ptr.cast(), len, libc::MADV_HUGEPAGE);
+        }
+        ptr
+    }
+
+    /// Maps `len` bytes aligned to `align`, with `flags` on top of a private
+    /// anonymous mapping.
+    unsafe fn map_aligned(len: usize, align: usize, flags: libc::c_int) -> *mut u8 {
+        // Mappings are only page-aligned, so larger alignments need extra
+        // room to be trimmed off.
+        let extra = align.saturating_sub(page_size());
+        let Some(total) = len.checked_add(extra) else {
+            return ptr::null_mut();
+        };
+        let base = libc::mmap(
+            ptr::null_mut(),
+            total,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+            -1,
+            0,
+        );
+        if base == libc::MAP_FAILED {
+            return ptr::null_mut();
+        }
+        let base = base.cast::<u8>();
+        let offset = base.align_offset(align);
+        if offset > 0 {
+            libc::munmap(base.cast(), offset);
+        }
+        if extra > offset {
+            libc::munmap(base.add(offset + len).cast(), extra - offset);
+        }
+        base.add(offset)
+    }
+
+    pub(super) unsafe fn unmap(ptr: *mut u8, layout: Layout, huge_pages: Option<HugePages>) {
+        // Computed the same way when the block was mapped.
+        let len = mapping_len(layout, huge_pages).unwrap();
+        libc::munmap(ptr.cast(), len);
+    }
+
+    /// Sets the NUMA policy of the `len` bytes at `ptr`, before any of them
+    /// is touched.
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    pub(super) unsafe fn bind(ptr: *mut u8, len: usize, node: super::NumaNode) {
+        const MASK_WORDS: usize = 16;
+        const MASK_BITS: usize = MASK_WORDS * libc::c_ulong::BITS as usize;
+
+        let mut mask = [0 as libc::c_ulong; MASK_WORDS];
+        let (mode, mask, max_node) = match node {
+            super::NumaNode::Local => (libc::MPOL_LOCAL, ptr::null(), 0),
+            super::NumaNode::Id(id) if id < MASK_BITS => {
+                let bits = libc::c_ulong::BITS as usize;
+                mask[id / bits] |= 1 << (id % bits);
+                // The kernel reads one bit less than `max_node`.
+                (libc::MPOL_BIND, mask.as_ptr(), MASK_BITS + 1)
+            }
+            super::NumaNode::Id(_) => return,
+        };
+        libc::syscall(libc::SYS_mbind, ptr, len, mode, mask, max_node, 0);
+    }
+
+    #[cfg(feature = "secure")]
+    pub(super) unsafe fn map_locked(layout: Layout) -> *mut u8 {
+        let ptr = map(layout, None);
+        if ptr.is_null() {
+            return ptr;
+        }
+        let len = mapping_len(layout, None).unwrap();
+        if libc::mlock(ptr.cast(), len) != 0 {
+            libc::munmap(ptr.cast(), len);
+            return ptr::null_mut();
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        libc::madvise(ptr.cast(), len, libc::MADV_DONTDUMP);
+        #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+        libc::madvise(ptr.cast(), len, libc::MADV_NOCORE);
+        ptr
+    }
+
+    #[cfg(feature = "secure")]
+    pub(super) unsafe fn unmap_locked(ptr: *mut u8, layout: Layout) {
+        let len = mapping_len(layout, None).unwrap();
+        crate::block::wipe(ptr, len);
+        libc::munlock(ptr.cast(), len);
+        libc::munmap(ptr.cast(), len);
+    }
+
+    /// Returns the length of the mapping that holds a block of `layout` and
+    /// its guard pages.
+    #[cfg(feature = "guard-pages")]
+    fn guarded_len(layout: Layout) -> Option<usize> {
+        mapping_len(layout, None)?.checked_add(2 * page_size())
+    }
+
+    #[cfg(feature = "guard-pages")]
+    pub(super) unsafe fn map_guarded(layout: Layout) -> *mut u8 {
+        let page = page_size();
+        if layout.align() > page {
+            return ptr::null_mut();
+        }
+        let Some(len) = guarded_len(layout) else {
+            return ptr::null_mut();
+        };
+        let base = map_aligned(len, page, 0);
+        if base.is_null() {
+            return base;
+        }
+        let tail = base.add(len - page);
+        libc::mprotect(base.cast(), page, libc::PROT_NONE);
+        libc::mprotect(tail.cast(), page, libc::PROT_NONE);
+        let start = tail.sub(layout.size());
+        start.sub(start.addr() & (layout.align() - 1))
+    }
+
+    #[cfg(feature = "guard-pages")]
+    pub(super) unsafe fn unmap_guarded(ptr: *mut u8, layout: Layout) {
+        let page = page_size();
+        // The block starts in the first page after the leading guard page.
+        let base = ptr.sub(ptr.addr() & (page - 1)).sub(page);
+        libc::munmap(base.cast(), guarded_len(layout).unwrap());
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use core::alloc::Layout;
+    use core::ffi::c_void;
+    use core::mem::MaybeUninit;
+    use core::ptr;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::HugePages;
+
+    const MEM_COMMIT: u32 = 0x1000;
+    const MEM_RESERVE: u32 = 0x2000;
+    const MEM_RELEASE: u32 = 0x8000;
+    const MEM_LARGE_PAGES: u32 = 0x2000_0000;
+    const PAGE_NOACCESS: u32 = 0x01;
+    const PAGE_READWRITE: u32 = 0x04;
+
+    #[repr(C)]
+    struct SystemInfo {
+        processor_architecture: u16,
+        reserved: u16,
+        page_size: u32,
+        minimum_application_address: *mut c_void,
+        maximum_application_address: *mut c_void,
+        active_processor_mask: usize,
+        number_of_processors: u32,
+        processor_type: u32,
+        allocation_granularity: u32,
+        processor_level: u16,
+        processor_revision: u16,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn VirtualAlloc(
+            address: *mut c_void,
+            size: usize,
+            allocation_type: u32,
+            protect: u32,
+        ) -> *mut c_void;
+        fn VirtualFree(address: *mut c_void, size: usize, free_type: u32) -> i32;
+        fn GetSystemInfo(info: *mut SystemInfo);
+    }
+
+    // The page size and the alignment of every `VirtualAlloc`.
+    fn system_info() -> (usize, usize) {
+        static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+        static GRANULARITY: AtomicUsize = AtomicUsize::new(0);
+        let page = PAGE_SIZE.load(Ordering::Relaxed);
+        if page != 0 {
+            return (page, GRANULARITY.load(Ordering::Relaxed));
+        }
+        let info = unsafe {
+            let mut info = MaybeUninit::<SystemInfo>::uninit();
+            GetSystemInfo(info.as_mut_ptr());
+            info.assume_init()
+        };
+        let (page, granularity) = (
+            info.page_size as usize,
+            info.allocation_granularity as usize,
+        );
+        GRANULARITY.store(granularity, Ordering::Relaxed);
+        PAGE_SIZE.store(page, Ordering::Relaxed);
+        (page, granularity)
+    }
+
+    pub(super) fn page_size() -> usize {
+        system_info().0
+    }
+
+    /// Returns the length of the mapping that holds a block of `layout`.
+    pub(super) fn mapping_len(layout: Layout, huge_pages: Option<HugePages>) -> Option<usize> {
+        let page = huge_pages.map_or_else(page_size, HugePages::size);
+        Some(layout.size().checked_add(page - 1)? & !(page - 1))
+    }
+
+    pub(super) unsafe fn map(layout: Layout, huge_pages: Option<HugePages>) -> *mut u8 {
+        let Some(len) = mapping_len(layout, huge_pages) else {
+            return ptr::null_mut();
+        };
+        let commit = MEM_RESERVE | MEM_COMMIT;
+        if let Some(pages) = huge_pages {
+            // Large pages are aligned to their size.
+            if layout.align() <= pages.size() {
+                let ptr = VirtualAlloc(
+                    ptr::null_mut(),
+                    len,
+                    commit | MEM_LARGE_PAGES,
+                    PAGE_READWRITE,
+                );
+                if !ptr.is_null() {
+                    return ptr.cast();
+                }
+            }
+        }
+        if layout.align() <= system_info().1 {
+            return VirtualAlloc(ptr::null_mut(), len, commit, PAGE_READWRITE).cast();
+        }
+        // A reservation can't be trimmed: find an aligned address in a larger
+        // one, release it and map there. Another thread may take the address
+        // in between, so give it a few tries.
+        let Some(probe_len) = len.checked_add(layout.align()) else {
+            return ptr::null_mut();
+        };
+        for _ in 0..8 {
+            let probe = VirtualAlloc(ptr::null_mut(), probe_len, MEM_RESERVE, PAGE_NOACCESS);
+            if probe.is_null() {
+                return ptr::null_mut();
+            }
+            let aligned = probe.cast::<u8>().add(probe.align_offset(layout.align()));
+            VirtualFree(probe, 0, MEM_RELEASE);
+            let ptr = VirtualAlloc(aligned.cast(), len, commit, PAGE_READWRITE);
+            if !ptr.is_null() {
+                return ptr.cast();
+            }
+        }
+        ptr::null_mut()
+    }
+
+    pub(super) unsafe fn unmap(ptr: *mut u8, _layout: Layout, _huge_pages: Option<HugePages>) {
+        VirtualFree(ptr.cast(), 0, MEM_RELEASE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout};
+
+    use super::{sys, HugePages, MmapAlloc};
+    use crate::arena::Arena;
+    use crate::bump::Bump;
+
+    #[test]
+    fn arena_blocks() {
+        let arena = Arena::new_in(MmapAlloc::new());
+        for i in 0..100_000u64 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.len(), 100_000);
+
+        let bump = Bump::new_in(MmapAlloc::with_min_mapping(1 << 16));
+        let large = bump.alloc_slice_fill_copy(1 << 20, 7u8);
+        assert_eq!(large.as_ptr().addr() % sys::page_size(), 0);
+        assert_eq!(bump.alloc(3u32), &3);
+    }
+
+    #[test]
+    fn large_alignment() {
+        let layout = Layout::from_size_align(10_000, 1 << 20).unwrap();
+        unsafe {
+            let ptr = MmapAlloc::new().alloc(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr.addr() % (1 << 20), 0);
+            ptr.write_bytes(1, layout.size());
+            MmapAlloc::new().dealloc(ptr, layout);
+        }
+    }
+
+    #[cfg(all(feature = "guard-pages", unix))]
+    #[test]
+    fn guard_pages() {
+        use super::GuardedAlloc;
+
+        let page = sys::page_size();
+        for size in [1, 100, page, 3 * page + 5] {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            unsafe {
+                let ptr = GuardedAlloc.alloc(layout);
+                ptr.write_bytes(0xaa, size);
+                let end = ptr.addr() + size;
+                assert!(end.next_multiple_of(page) - end < 8);
+                GuardedAlloc.dealloc(ptr, layout);
+            }
+        }
+
+        let arena = Arena::with_block_growth_in(64, 64, GuardedAlloc);
+        for i in 0..100u64 {
+            arena.alloc(i);
+        }
+        assert_eq!(arena.len(), 100);
+    }
+
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    #[test]
+    fn numa_node() {
+        use super::NumaNode;
+
+        for node in [NumaNode::Local, NumaNode::Id(0), NumaNode::Id(1 << 20)] {
+            let bump = Bump::new_in(MmapAlloc::new().numa_node(node));
+            let values = bump.alloc_slice_fill_copy(1 << 16, 3u32);
+            assert_eq!(values.iter().sum::<u32>(), 3 << 16);
+        }
+    }
+
+    #[cfg(all(feature = "secure", unix))]
+    #[test]
+    fn secure_arena() {
+        use super::{SecureAlloc, SecureArena};
+
+        let keys: SecureArena<[u8; 32]> = Arena::new_in(SecureAlloc);
+        for i in 0..10 {
+            keys.alloc([i; 32]);
+        }
+        assert_eq!(keys.len(), 10);
+
+        let bump = Bump::new_in(SecureAlloc);
+        assert_eq!(bump.alloc_str("secret"), "secret");
+    }
+
+    #[test]
+    fn huge_pages() {
+        let alloc = MmapAlloc::new().huge_pages(HugePages::Size2MiB);
+        let bump = Bump::with_block_growth_in(1 << 20, 1 << 22, alloc);
+        let values = bump.alloc_slice_fill_copy(3 << 20, 1u8);
+        // Windows falls back to regular pages without the privilege.
+        #[cfg(unix)]
+        assert_eq!(values.as_ptr().addr() % HugePages::Size2MiB.size(), 0);
+        assert_eq!(values.iter().map(|&x| x as usize).sum::<usize>(), 3 << 20);
+        assert_eq!(bump.alloc(5u64), &5);
+    }
+}
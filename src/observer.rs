@@ -0,0 +1,13 @@
+/// Receives allocation events from an arena, e.g. to feed a profiler.
+///
+/// Register one with `set_observer`. The callbacks run once the arena is
+/// back in a consistent state, so they are free to allocate from it.
+pub trait ArenaObserver {
+    /// Called after `size` bytes were handed out at `ptr`. A slice is
+    /// reported as a single allocation.
+    fn on_alloc(&self, _ptr: *const u8, _size: usize) {}
+
+    /// Called after a block of `size` bytes at `ptr` was obtained from the
+    /// system allocator.
+    fn on_block(&self, _ptr: *const u8, _size: usize) {}
+}
@@ -0,0 +1,201 @@
+// The file format of `Arena::persist` and `RelArena::persist`: a header
+// followed by the raw bytes of the values.
+//
+// The header records what the payload holds, its length and a checksum, so
+// that a stale, truncated or foreign file is rejected instead of being read
+// as values. It is written in the native byte order, like the payload; the
+// `ENDIAN` marker catches files written on a machine with the other one.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const MAGIC: [u8; 8] = *b"RSARENA\0";
+const VERSION: u32 = 1;
+const ENDIAN: u32 = 0x0102_0304;
+const HEADER_LEN: usize = 48;
+
+/// What the payload of a file holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// `count` values of `size` bytes each, from an `Arena`.
+    Values { size: usize, align: usize },
+    /// `count` bytes of a `RelArena` with offsets of `width` bytes.
+    Relocatable { width: usize },
+}
+
+impl Kind {
+    fn to_raw(self) -> [u32; 3] {
+        match self {
+            Kind::Values { size, align } => [1, size as u32, align as u32],
+            Kind::Relocatable { width } => [2, width as u32, 0],
+        }
+    }
+
+    /// Returns the size of one unit of `count`.
+    fn unit(self) -> usize {
+        match self {
+            Kind::Values { size, .. } => size,
+            Kind::Relocatable { .. } => 1,
+        }
+    }
+}
+
+/// FNV-1a, which is enough to notice truncation and bit rot. It doesn't
+/// protect against deliberate tampering.
+fn checksum<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for chunk in chunks {
+        for &byte in chunk {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes a file holding `count` units of `kind`, whose bytes are yielded by
+/// `chunks`. The chunks are walked twice, once for the checksum.
+pub(crate) fn write<'a, I>(path: &Path, kind: Kind, count: usize, chunks: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a [u8]> + Clone,
+{
+    let mut header = [0u8; HEADER_LEN];
+    let [kind, a, b] = kind.to_raw();
+    header[..8].copy_from_slice(&MAGIC);
+    for (i, field) in [VERSION, ENDIAN, kind, a, b].into_iter().enumerate() {
+        header[8 + 4 * i..12 + 4 * i].copy_from_slice(&field.to_ne_bytes());
+    }
+    header[32..40].copy_from_slice(&(count as u64).to_ne_bytes());
+    header[40..48].copy_from_slice(&checksum(chunks.clone()).to_ne_bytes());
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&header)?;
+    for chunk in chunks {
+        file.write_all(chunk)?;
+    }
+    file.into_inner()?.sync_all()
+}
+
+/// Reads a file written by [`write`] with the same `kind`, returning the
+/// number of units and the file contents, whose payload starts at
+/// [`payload`].
+pub(crate) fn read(path: &Path, kind: Kind) -> io::Result<(usize, Vec<u8>)> {
+    let bytes = fs::read(path)?;
+    if bytes.len() < HEADER_LEN || bytes[..8] != MAGIC {
+        return Err(invalid("not an arena file"));
+    }
+    let field = |i: usize| u32::from_ne_bytes(bytes[8 + 4 * i..12 + 4 * i].try_into().unwrap());
+    if field(1) != ENDIAN {
+        return Err(invalid("arena file was written with another byte order"));
+    }
+    if field(0) != VERSION {
+        return Err(invalid("unsupported arena file version"));
+    }
+    if [field(2), field(3), field(4)] != kind.to_raw() {
+        return Err(invalid("arena file holds another type of values"));
+    }
+    let count = u64::from_ne_bytes(bytes[32..40].try_into().unwrap());
+    let sum = u64::from_ne_bytes(bytes[40..48].try_into().unwrap());
+    let len = usize::try_from(count)
+        .ok()
+        .and_then(|count| count.checked_mul(kind.unit()));
+    if len != Some(bytes.len() - HEADER_LEN) || checksum([payload(&bytes)]) != sum {
+        return Err(invalid("arena file is corrupt"));
+    }
+    Ok((count as usize, bytes))
+}
+
+/// Returns the payload of the contents returned by [`read`].
+pub(crate) fn payload(bytes: &[u8]) -> &[u8] {
+    &bytes[HEADER_LEN..]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::{env, fs, process};
+
+    use crate::arena::Arena;
+    use crate::relative::{Offset, RelArena};
+
+    /// A file in the temporary directory, removed when dropped.
+    ///
+    /// Miri's isolation forbids opening files, so the tests using one are
+    /// ignored under Miri.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("rusty-arena-{}-{}", process::id(), name);
+            Self(env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn arena_round_trip() {
+        let file = TempFile::new("arena_round_trip");
+        let mut arena = Arena::with_block_size(64);
+        for i in 0..1000u64 {
+            arena.alloc([i, i * i]);
+        }
+        arena.persist(&file.0).unwrap();
+
+        let mut loaded = Arena::<[u64; 2]>::load(&file.0).unwrap();
+        assert_eq!(loaded.len(), 1000);
+        assert!(loaded.iter().eq(arena.iter()));
+        assert_eq!(loaded.stats().blocks, 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn rel_arena_round_trip() {
+        let file = TempFile::new("rel_arena_round_trip");
+        let mut arena = RelArena::<u64>::new();
+        let first = arena.alloc(1u8);
+        let last = arena.alloc(Offset::<u8, u64>::from_raw(0));
+        arena.persist(&file.0).unwrap();
+
+        let loaded = RelArena::<u64>::load(&file.0).unwrap();
+        assert_eq!(loaded.as_bytes(), arena.as_bytes());
+        assert_eq!(loaded[loaded[last]], loaded[first]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn rejects_bad_files() {
+        let file = TempFile::new("rejects_bad_files");
+        let mut arena = Arena::new();
+        arena.alloc(7u32);
+        arena.alloc(8u32);
+        arena.persist(&file.0).unwrap();
+
+        let err = Arena::<u64>::load(&file.0).err().unwrap();
+        assert_eq!(err.to_string(), "arena file holds another type of values");
+        assert!(RelArena::<u32>::load(&file.0).is_err());
+
+        let mut bytes = fs::read(&file.0).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(&file.0, &bytes).unwrap();
+        let err = Arena::<u32>::load(&file.0).err().unwrap();
+        assert_eq!(err.to_string(), "arena file is corrupt");
+
+        bytes.pop();
+        fs::write(&file.0, &bytes).unwrap();
+        assert!(Arena::<u32>::load(&file.0).is_err());
+
+        fs::write(&file.0, b"not an arena").unwrap();
+        let err = Arena::<u32>::load(&file.0).err().unwrap();
+        assert_eq!(err.to_string(), "not an arena file");
+    }
+}
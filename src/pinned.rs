@@ -0,0 +1,127 @@
+//! A typed arena whose values are pinned in place.
+
+use core::pin::Pin;
+
+use crate::arena::Arena;
+
+/// An [`Arena`] that hands out `Pin<&mut T>`, so `!Unpin` values such as
+/// futures or intrusive list nodes can live in it.
+///
+/// Only the parts of the `Arena` API that keep the pinning contract are
+/// available: values are never moved out or exposed by `&mut T`, and they
+/// are always dropped before their memory is reused or released.
+pub struct PinnedArena<T> {
+    arena: Arena<T>,
+}
+
+impl<T> PinnedArena<T> {
+    pub fn new() -> Self {
+        Self {
+            arena: Arena::new(),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_pinned(&self, data: T) -> Pin<&mut T> {
+        self.alloc_pinned_with(|| data)
+    }
+
+    /// Like [`alloc_pinned`](Self::alloc_pinned), but writes the result of
+    /// `f` straight into its slot, which lets the optimizer construct large
+    /// values in place. See [`Arena::alloc_with`].
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_pinned_with<F: FnOnce() -> T>(&self, f: F) -> Pin<&mut T> {
+        // The value stays at this address until the arena drops it.
+        unsafe { Pin::new_unchecked(self.arena.alloc_with(f)) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    /// Drops every value, keeping the blocks for reuse.
+    pub fn reset(&mut self) {
+        self.arena.reset();
+    }
+
+    pub fn iter(&mut self) -> impl Iterator<Item = Pin<&T>> {
+        self.arena.iter().map(|x| unsafe { Pin::new_unchecked(x) })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = Pin<&mut T>> {
+        self.arena
+            .iter_mut()
+            .map(|x| unsafe { Pin::new_unchecked(x) })
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.arena.bytes_allocated()
+    }
+}
+
+impl<T> Default for PinnedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::future::Future;
+    use std::marker::PhantomPinned;
+    use std::pin::Pin;
+    use std::ptr;
+    use std::task::{Context, Poll, Waker};
+
+    use super::PinnedArena;
+
+    // Remembers its own address and checks that it never moves.
+    struct Node {
+        this: Cell<*const Node>,
+        _pin: PhantomPinned,
+    }
+
+    impl Node {
+        fn new() -> Self {
+            Self {
+                this: Cell::new(ptr::null()),
+                _pin: PhantomPinned,
+            }
+        }
+
+        fn check(self: Pin<&Self>) {
+            let addr = &*self as *const Node;
+            if self.this.get().is_null() {
+                self.this.set(addr);
+            }
+            assert_eq!(self.this.get(), addr);
+        }
+    }
+
+    #[test]
+    fn stable_addresses() {
+        let mut arena = PinnedArena::new();
+        for _ in 0..1000 {
+            arena.alloc_pinned(Node::new()).as_ref().check();
+        }
+        for node in arena.iter() {
+            node.check();
+        }
+        assert_eq!(arena.len(), 1000);
+        arena.reset();
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn future() {
+        let arena = PinnedArena::new();
+        let mut fut = arena.alloc_pinned_with(|| async { 7 });
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(7));
+    }
+}
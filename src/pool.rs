@@ -0,0 +1,197 @@
+//! An object pool that recycles freed slots through an intrusive free list.
+
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use crate::bump::Bump;
+
+// A free slot stores the link to the next free slot in place of the value.
+union Slot<T> {
+    value: ManuallyDrop<T>,
+    next: Option<NonNull<Slot<T>>>,
+}
+
+/// A pool of `T` slots carved out of arena blocks.
+///
+/// [`alloc`](Self::alloc) reuses the most recently freed slot in O(1), so a
+/// steady churn of objects stays within the blocks the pool already has
+/// instead of fragmenting the general heap.
+pub struct Pool<T> {
+    bump: Bump,
+    free: Cell<Option<NonNull<Slot<T>>>>,
+    slots: Cell<usize>,
+    live: Cell<usize>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Self {
+        Self {
+            bump: Bump::new(),
+            free: Cell::new(None),
+            slots: Cell::new(0),
+            live: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves `value` into a free slot, taking a new one from the arena when
+    /// there is none.
+    pub fn alloc(&self, value: T) -> PoolBox<'_, T> {
+        let slot = match self.free.get() {
+            Some(slot) => {
+                self.free.set(unsafe { slot.as_ref().next });
+                slot
+            }
+            None => {
+                self.slots.set(self.slots.get() + 1);
+                NonNull::from(self.bump.alloc(Slot { next: None }))
+            }
+        };
+        unsafe {
+            ptr::write(
+                slot.as_ptr(),
+                Slot {
+                    value: ManuallyDrop::new(value),
+                },
+            )
+        };
+        self.live.set(self.live.get() + 1);
+        PoolBox { pool: self, slot }
+    }
+
+    /// Returns the number of values currently allocated.
+    pub fn len(&self) -> usize {
+        self.live.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots taken from the arena, free or not.
+    pub fn capacity(&self) -> usize {
+        self.slots.get()
+    }
+
+    fn release(&self, slot: NonNull<Slot<T>>) {
+        unsafe {
+            ptr::write(
+                slot.as_ptr(),
+                Slot {
+                    next: self.free.get(),
+                },
+            )
+        };
+        self.free.set(Some(slot));
+        self.live.set(self.live.get() - 1);
+    }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A value in a [`Pool`]. Dropping it drops the value and frees its slot.
+pub struct PoolBox<'p, T> {
+    pool: &'p Pool<T>,
+    slot: NonNull<Slot<T>>,
+}
+
+impl<T> PoolBox<'_, T> {
+    /// Moves the value out and frees its slot.
+    pub fn into_inner(b: Self) -> T {
+        let b = ManuallyDrop::new(b);
+        let value = unsafe { ptr::read(&*b.slot.as_ref().value) };
+        b.pool.release(b.slot);
+        value
+    }
+}
+
+impl<T> Drop for PoolBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut (*self.slot.as_ptr()).value) };
+        self.pool.release(self.slot);
+    }
+}
+
+impl<T> Deref for PoolBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &self.slot.as_ref().value }
+    }
+}
+
+impl<T> DerefMut for PoolBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.slot.as_ptr()).value }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::{Pool, PoolBox};
+
+    #[test]
+    fn reuses_slots() {
+        let pool = Pool::new();
+        let a = pool.alloc(1u64);
+        let b = pool.alloc(2u64);
+        let a_ptr = &*a as *const u64;
+        assert_eq!(pool.len(), 2);
+        drop(a);
+        assert_eq!(pool.len(), 1);
+
+        let c = pool.alloc(3);
+        assert_eq!(&*c as *const u64, a_ptr);
+        assert_eq!(pool.capacity(), 2);
+        assert_eq!(*b + *c, 5);
+    }
+
+    #[test]
+    fn churn() {
+        let pool = Pool::new();
+        for i in 0..10000 {
+            let conns: Vec<_> = (0..10).map(|j| pool.alloc([i + j; 8])).collect();
+            assert_eq!(conns[9][0], i + 9);
+        }
+        assert_eq!(pool.capacity(), 10);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn drops() {
+        struct D<'c>(&'c Cell<usize>);
+
+        impl Drop for D<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let pool = Pool::new();
+        drop(pool.alloc(D(&drops)));
+        assert_eq!(drops.get(), 1);
+        let d = PoolBox::into_inner(pool.alloc(D(&drops)));
+        assert_eq!(drops.get(), 1);
+        assert!(pool.is_empty());
+        drop(d);
+        assert_eq!(drops.get(), 2);
+    }
+}
@@ -0,0 +1,175 @@
+//! A reference-counted pointer whose count and value live in a [`Bump`].
+
+use core::alloc::GlobalAlloc;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+
+use crate::bump::Bump;
+
+struct RcBox<T> {
+    strong: Cell<usize>,
+    value: ManuallyDrop<T>,
+}
+
+/// A single-threaded shared pointer allocated in a [`Bump`], without touching
+/// the global allocator.
+///
+/// The value is dropped when the last clone is. If a clone is leaked, e.g.
+/// with `mem::forget`, the value is never dropped and its memory is only
+/// released with the arena. The arena can't be reset while clones are alive,
+/// since resetting needs `&mut Bump`.
+pub struct ArenaRc<'a, T> {
+    ptr: NonNull<RcBox<T>>,
+    _marker: PhantomData<(&'a T, T)>,
+}
+
+impl<'a, T> ArenaRc<'a, T> {
+    pub fn new_in<A: GlobalAlloc>(value: T, bump: &'a Bump<A>) -> Self {
+        let rc = bump.alloc(RcBox {
+            strong: Cell::new(1),
+            value: ManuallyDrop::new(value),
+        });
+        Self {
+            ptr: NonNull::from(rc),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    /// Returns whether both pointers refer to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// Returns a mutable reference to the value if there are no other
+    /// pointers to it.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if Self::strong_count(this) == 1 {
+            unsafe { Some(&mut (*this.ptr.as_ptr()).value) }
+        } else {
+            None
+        }
+    }
+
+    /// Moves the value out if this is the only pointer to it.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if Self::strong_count(&this) != 1 {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        unsafe { Ok(ptr::read(&*this.inner().value)) }
+    }
+
+    fn inner(&self) -> &RcBox<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> Clone for ArenaRc<'_, T> {
+    fn clone(&self) -> Self {
+        let strong = &self.inner().strong;
+        strong.set(strong.get() + 1);
+        Self {
+            ptr: self.ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for ArenaRc<'_, T> {
+    fn drop(&mut self) {
+        let strong = &self.inner().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() == 0 {
+            unsafe { ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).value) };
+        }
+    }
+}
+
+impl<T> Deref for ArenaRc<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaRc<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for ArenaRc<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for ArenaRc<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for ArenaRc<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::ArenaRc;
+    use crate::bump::Bump;
+
+    struct D<'c>(&'c Cell<usize>);
+
+    impl Drop for D<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn shared() {
+        let drops = Cell::new(0);
+        let bump = Bump::new();
+        let a = ArenaRc::new_in(D(&drops), &bump);
+        let b = a.clone();
+        assert!(ArenaRc::ptr_eq(&a, &b));
+        assert_eq!(ArenaRc::strong_count(&a), 2);
+        drop(a);
+        assert_eq!(drops.get(), 0);
+        drop(b);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn unique() {
+        let bump = Bump::new();
+        let mut a = ArenaRc::new_in(1, &bump);
+        *ArenaRc::get_mut(&mut a).unwrap() += 1;
+        let b = a.clone();
+        assert!(ArenaRc::get_mut(&mut a).is_none());
+        let a = ArenaRc::try_unwrap(a).unwrap_err();
+        drop(b);
+        assert_eq!(ArenaRc::try_unwrap(a).ok(), Some(2));
+    }
+
+    #[test]
+    fn outlived_by_arena_drop() {
+        let drops = Cell::new(0);
+        let bump = Bump::new();
+        let a = ArenaRc::new_in(D(&drops), &bump);
+        std::mem::forget(a.clone());
+        drop(a);
+        drop(bump);
+        assert_eq!(drops.get(), 0);
+    }
+}
@@ -0,0 +1,643 @@
+//! An arena whose links are offsets from its base, so that its memory can be
+//! moved as a whole.
+//!
+//! A [`RelArena`] keeps its values in one contiguous buffer and hands out
+//! [`Offset`] and [`SliceOffset`] handles instead of references. Values that
+//! link to each other store these handles, which are rebased on the buffer
+//! they are read from. The buffer can thus be copied, written to disk or
+//! memory-mapped at another address, and read back with [`RelView`] or
+//! [`RelArena::from_bytes`].
+//!
+//! Only [`Relocatable`] types can be stored: plain data without pointers or
+//! padding. The bytes use the native endianness, so an arena written on one
+//! machine can be read on another with the same byte order.
+//!
+//! ```
+//! use rusty_arena::relative::{Offset, RelArena, RelView, Relocatable};
+//!
+//! #[derive(Clone, Copy)]
+//! #[repr(C)]
+//! struct Node {
+//!     value: u32,
+//!     next: Offset<Node>,
+//! }
+//!
+//! unsafe impl Relocatable for Node {}
+//!
+//! let mut arena = RelArena::new();
+//! let head = arena.alloc(Node { value: 1, next: Offset::from_raw(0) });
+//! let tail = arena.alloc(Node { value: 2, next: head });
+//! arena[head].next = tail;
+//!
+//! let copy = arena.as_bytes().to_vec();
+//! let copy = RelArena::<u32>::from_bytes(&copy);
+//! assert_eq!(copy[copy[head].next].value, 2);
+//! ```
+
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Index, IndexMut};
+use core::ptr;
+use core::slice;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use crate::persist;
+use crate::ArenaAllocError;
+
+/// The alignment of the buffer, and the largest alignment a value can have.
+pub const ALIGN: usize = 16;
+
+/// Types that can be stored in a [`RelArena`].
+///
+/// # Safety
+///
+/// The type must be plain data: it holds no pointers or references, has no
+/// padding bytes, and every bit pattern is a valid value. Structs should be
+/// `#[repr(C)]` so that their layout doesn't change between builds.
+pub unsafe trait Relocatable: Copy + 'static {}
+
+macro_rules! relocatable {
+    ($($ty:ty)*) => {$(
+        unsafe impl Relocatable for $ty {}
+    )*};
+}
+
+relocatable! {
+    ()
+    u8 u16 u32 u64 u128
+    i8 i16 i32 i64 i128
+    f32 f64
+}
+
+unsafe impl<T: Relocatable, const N: usize> Relocatable for [T; N] {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+}
+
+/// The integer type offsets are stored as: `u32` for arenas of up to 4 GiB,
+/// `u64` beyond that.
+pub trait OffsetWidth: Relocatable + Eq + Hash + fmt::Debug + sealed::Sealed {
+    #[doc(hidden)]
+    fn from_usize(n: usize) -> Option<Self>;
+    #[doc(hidden)]
+    fn to_usize(self) -> usize;
+}
+
+impl OffsetWidth for u32 {
+    fn from_usize(n: usize) -> Option<Self> {
+        u32::try_from(n).ok()
+    }
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl OffsetWidth for u64 {
+    fn from_usize(n: usize) -> Option<Self> {
+        u64::try_from(n).ok()
+    }
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+/// The position of a `T` in a [`RelArena`], counted in bytes from its base.
+///
+/// Offsets stay valid when the arena's bytes are moved. An offset that
+/// doesn't point at a `T` of the arena it is used with yields an unrelated
+/// value or, if it is out of bounds or misaligned, nothing.
+#[repr(transparent)]
+pub struct Offset<T, O: OffsetWidth = u32> {
+    offset: O,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, O: OffsetWidth> Offset<T, O> {
+    /// Builds an offset from its stored form. The first value allocated in
+    /// an arena is at offset zero, which makes it a convenient root.
+    pub fn from_raw(offset: O) -> Self {
+        Self {
+            offset,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn to_raw(self) -> O {
+        self.offset
+    }
+}
+
+impl<T, O: OffsetWidth> Clone for Offset<T, O> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, O: OffsetWidth> Copy for Offset<T, O> {}
+
+impl<T, O: OffsetWidth> PartialEq for Offset<T, O> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T, O: OffsetWidth> Eq for Offset<T, O> {}
+
+impl<T, O: OffsetWidth> Hash for Offset<T, O> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offset.hash(state)
+    }
+}
+
+impl<T, O: OffsetWidth> fmt::Debug for Offset<T, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Offset({:?})", self.offset)
+    }
+}
+
+unsafe impl<T: 'static, O: OffsetWidth> Relocatable for Offset<T, O> {}
+
+/// The position and length of a `[T]` in a [`RelArena`].
+#[repr(C)]
+pub struct SliceOffset<T, O: OffsetWidth = u32> {
+    offset: O,
+    len: O,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, O: OffsetWidth> SliceOffset<T, O> {
+    pub fn len(self) -> usize {
+        self.len.to_usize()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, O: OffsetWidth> Clone for SliceOffset<T, O> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, O: OffsetWidth> Copy for SliceOffset<T, O> {}
+
+impl<T, O: OffsetWidth> PartialEq for SliceOffset<T, O> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.len == other.len
+    }
+}
+
+impl<T, O: OffsetWidth> Eq for SliceOffset<T, O> {}
+
+impl<T, O: OffsetWidth> fmt::Debug for SliceOffset<T, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SliceOffset({:?}, {:?})", self.offset, self.len)
+    }
+}
+
+unsafe impl<T: 'static, O: OffsetWidth> Relocatable for SliceOffset<T, O> {}
+
+/// Returns the range of `len` values of `T` at `offset` if it lies within
+/// `size` bytes and is aligned for `T`.
+fn checked_range<T>(offset: usize, len: usize, size: usize) -> Option<usize> {
+    let end = mem::size_of::<T>()
+        .checked_mul(len)
+        .and_then(|n| n.checked_add(offset))?;
+    (end <= size && offset.is_multiple_of(mem::align_of::<T>())).then_some(offset)
+}
+
+#[derive(Clone, Copy)]
+#[repr(C, align(16))]
+struct Chunk([u8; ALIGN]);
+
+/// An arena of [`Relocatable`] values in one contiguous buffer, addressed by
+/// offsets of width `O`.
+///
+/// Growing the arena moves the buffer, which the offsets don't mind. The
+/// buffer is zeroed before values are written, so [`as_bytes`](Self::as_bytes)
+/// never exposes uninitialized memory.
+pub struct RelArena<O: OffsetWidth = u32> {
+    chunks: Vec<Chunk>,
+    len: usize,
+    _marker: PhantomData<O>,
+}
+
+impl<O: OffsetWidth> RelArena<O> {
+    pub fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates an arena with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut arena = Self::new();
+        arena.chunks.reserve(capacity.div_ceil(ALIGN));
+        arena
+    }
+
+    /// Creates an arena holding a copy of `bytes`, previously returned by
+    /// [`as_bytes`](Self::as_bytes) of an arena with the same offset width.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut arena = Self::new();
+        arena
+            .chunks
+            .resize(bytes.len().div_ceil(ALIGN), Chunk([0; ALIGN]));
+        arena.bytes_mut()[..bytes.len()].copy_from_slice(bytes);
+        arena.len = bytes.len();
+        arena
+    }
+
+    /// Returns the bytes in use, the form in which the arena can be copied
+    /// or written out.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes()[..self.len]
+    }
+
+    /// Returns the number of bytes in use.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn view(&self) -> RelView<'_, O> {
+        RelView {
+            bytes: self.as_bytes(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves `value` into the arena and returns its offset.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset doesn't fit into `O`, or if `T` is aligned to
+    /// more than [`ALIGN`] bytes.
+    pub fn alloc<T: Relocatable>(&mut self, value: T) -> Offset<T, O> {
+        self.try_alloc(value).unwrap_or_else(|e| e.handle())
+    }
+
+    pub fn try_alloc<T: Relocatable>(&mut self, value: T) -> Result<Offset<T, O>, ArenaAllocError> {
+        let offset = self.reserve_for::<T>(1)?;
+        unsafe { ptr::write(self.bytes_mut().as_mut_ptr().add(offset).cast(), value) };
+        Ok(Offset::from_raw(O::from_usize(offset).unwrap()))
+    }
+
+    /// Copies `src` into the arena and returns its offset.
+    ///
+    /// # Panics
+    ///
+    /// See [`alloc`](Self::alloc).
+    pub fn alloc_slice<T: Relocatable>(&mut self, src: &[T]) -> SliceOffset<T, O> {
+        self.try_alloc_slice(src).unwrap_or_else(|e| e.handle())
+    }
+
+    pub fn try_alloc_slice<T: Relocatable>(
+        &mut self,
+        src: &[T],
+    ) -> Result<SliceOffset<T, O>, ArenaAllocError> {
+        let len = O::from_usize(src.len()).ok_or(ArenaAllocError::CapacityOverflow)?;
+        let offset = self.reserve_for::<T>(src.len())?;
+        unsafe {
+            let dst = self.bytes_mut().as_mut_ptr().add(offset).cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+        }
+        Ok(SliceOffset {
+            offset: O::from_usize(offset).unwrap(),
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Copies `s` into the arena and returns the offset of its bytes.
+    pub fn alloc_str(&mut self, s: &str) -> SliceOffset<u8, O> {
+        self.alloc_slice(s.as_bytes())
+    }
+
+    pub fn get<T: Relocatable>(&self, offset: Offset<T, O>) -> Option<&T> {
+        self.view().get(offset)
+    }
+
+    pub fn get_mut<T: Relocatable>(&mut self, offset: Offset<T, O>) -> Option<&mut T> {
+        let offset = checked_range::<T>(offset.offset.to_usize(), 1, self.len)?;
+        Some(unsafe { &mut *self.bytes_mut().as_mut_ptr().add(offset).cast::<T>() })
+    }
+
+    pub fn get_slice<T: Relocatable>(&self, offset: SliceOffset<T, O>) -> Option<&[T]> {
+        self.view().get_slice(offset)
+    }
+
+    pub fn get_slice_mut<T: Relocatable>(&mut self, offset: SliceOffset<T, O>) -> Option<&mut [T]> {
+        let len = offset.len();
+        let offset = checked_range::<T>(offset.offset.to_usize(), len, self.len)?;
+        let ptr = unsafe { self.bytes_mut().as_mut_ptr().add(offset).cast::<T>() };
+        Some(unsafe { slice::from_raw_parts_mut(ptr, len) })
+    }
+
+    /// Makes room for `len` values of `T` past the bytes in use and returns
+    /// their offset.
+    fn reserve_for<T: Relocatable>(&mut self, len: usize) -> Result<usize, ArenaAllocError> {
+        assert!(
+            mem::align_of::<T>() <= ALIGN,
+            "values of a relocatable arena can't be aligned to more than {} bytes",
+            ALIGN
+        );
+        let offset = self.len.next_multiple_of(mem::align_of::<T>());
+        let end = mem::size_of::<T>()
+            .checked_mul(len)
+            .and_then(|n| n.checked_add(offset))
+            .ok_or(ArenaAllocError::CapacityOverflow)?;
+        // The end has to fit too, so that every offset into the value does.
+        O::from_usize(end).ok_or(ArenaAllocError::CapacityOverflow)?;
+        let chunks = end.div_ceil(ALIGN);
+        if chunks > self.chunks.len() {
+            self.chunks
+                .try_reserve(chunks - self.chunks.len())
+                .map_err(|_| match Layout::array::<Chunk>(chunks) {
+                    Ok(layout) => ArenaAllocError::AllocFailed { layout },
+                    Err(_) => ArenaAllocError::CapacityOverflow,
+                })?;
+            self.chunks.resize(chunks, Chunk([0; ALIGN]));
+        }
+        self.len = end;
+        Ok(offset)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.chunks.as_ptr().cast(), self.chunks.len() * ALIGN) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        let len = self.chunks.len() * ALIGN;
+        unsafe { slice::from_raw_parts_mut(self.chunks.as_mut_ptr().cast(), len) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<O: OffsetWidth> RelArena<O> {
+    /// Writes the bytes in use to the file at `path`, so that
+    /// [`load`](Self::load) can bring the arena back in a later run.
+    pub fn persist<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let kind = persist::Kind::Relocatable {
+            width: mem::size_of::<O>(),
+        };
+        persist::write(path.as_ref(), kind, self.len, [self.as_bytes()])
+    }
+
+    /// Reads back an arena written by [`persist`](Self::persist).
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the file wasn't written
+    /// by `persist` with the same offset width, or doesn't match the
+    /// checksum it was written with.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let kind = persist::Kind::Relocatable {
+            width: mem::size_of::<O>(),
+        };
+        let (_, bytes) = persist::read(path.as_ref(), kind)?;
+        Ok(Self::from_bytes(persist::payload(&bytes)))
+    }
+}
+
+impl<O: OffsetWidth> Default for RelArena<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O: OffsetWidth> Clone for RelArena<O> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Relocatable, O: OffsetWidth> Index<Offset<T, O>> for RelArena<O> {
+    type Output = T;
+
+    fn index(&self, offset: Offset<T, O>) -> &T {
+        match self.get(offset) {
+            Some(x) => x,
+            None => out_of_bounds(offset.offset.to_usize(), self.len),
+        }
+    }
+}
+
+impl<T: Relocatable, O: OffsetWidth> IndexMut<Offset<T, O>> for RelArena<O> {
+    fn index_mut(&mut self, offset: Offset<T, O>) -> &mut T {
+        let len = self.len;
+        match self.get_mut(offset) {
+            Some(x) => x,
+            None => out_of_bounds(offset.offset.to_usize(), len),
+        }
+    }
+}
+
+impl<T: Relocatable, O: OffsetWidth> Index<SliceOffset<T, O>> for RelArena<O> {
+    type Output = [T];
+
+    fn index(&self, offset: SliceOffset<T, O>) -> &[T] {
+        match self.get_slice(offset) {
+            Some(x) => x,
+            None => out_of_bounds(offset.offset.to_usize(), self.len),
+        }
+    }
+}
+
+impl<T: Relocatable, O: OffsetWidth> IndexMut<SliceOffset<T, O>> for RelArena<O> {
+    fn index_mut(&mut self, offset: SliceOffset<T, O>) -> &mut [T] {
+        let len = self.len;
+        match self.get_slice_mut(offset) {
+            Some(x) => x,
+            None => out_of_bounds(offset.offset.to_usize(), len),
+        }
+    }
+}
+
+#[cold]
+fn out_of_bounds(offset: usize, len: usize) -> ! {
+    panic!(
+        "offset {} out of bounds or misaligned for a relocatable arena of {} bytes",
+        offset, len
+    )
+}
+
+/// Read access to the bytes of a [`RelArena`] wherever they are, e.g. in a
+/// memory-mapped file, without copying them.
+#[derive(Clone, Copy)]
+pub struct RelView<'a, O: OffsetWidth = u32> {
+    bytes: &'a [u8],
+    _marker: PhantomData<O>,
+}
+
+impl<'a, O: OffsetWidth> RelView<'a, O> {
+    /// Views `bytes` returned by [`RelArena::as_bytes`], or a copy of them.
+    ///
+    /// Returns `None` if `bytes` isn't aligned to [`ALIGN`] bytes. Memory
+    /// maps start at a page boundary and always are.
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        bytes.as_ptr().addr().is_multiple_of(ALIGN).then_some(Self {
+            bytes,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn get<T: Relocatable>(&self, offset: Offset<T, O>) -> Option<&'a T> {
+        let offset = checked_range::<T>(offset.offset.to_usize(), 1, self.bytes.len())?;
+        Some(unsafe { &*self.bytes.as_ptr().add(offset).cast::<T>() })
+    }
+
+    pub fn get_slice<T: Relocatable>(&self, offset: SliceOffset<T, O>) -> Option<&'a [T]> {
+        let len = offset.len();
+        let offset = checked_range::<T>(offset.offset.to_usize(), len, self.bytes.len())?;
+        let ptr = unsafe { self.bytes.as_ptr().add(offset).cast::<T>() };
+        Some(unsafe { slice::from_raw_parts(ptr, len) })
+    }
+}
+
+impl<T: Relocatable, O: OffsetWidth> Index<Offset<T, O>> for RelView<'_, O> {
+    type Output = T;
+
+    fn index(&self, offset: Offset<T, O>) -> &T {
+        match self.get(offset) {
+            Some(x) => x,
+            None => out_of_bounds(offset.offset.to_usize(), self.bytes.len()),
+        }
+    }
+}
+
+impl<T: Relocatable, O: OffsetWidth> Index<SliceOffset<T, O>> for RelView<'_, O> {
+    type Output = [T];
+
+    fn index(&self, offset: SliceOffset<T, O>) -> &[T] {
+        match self.get_slice(offset) {
+            Some(x) => x,
+            None => out_of_bounds(offset.offset.to_usize(), self.bytes.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::str;
+
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Node {
+        name: SliceOffset<u8>,
+        children: SliceOffset<Offset<Node>>,
+    }
+
+    unsafe impl Relocatable for Node {}
+
+    fn tree() -> RelArena {
+        let mut arena = RelArena::new();
+        // Empty slices take no room, so the root lands at offset zero.
+        let name = arena.alloc_str("");
+        let children = arena.alloc_slice(&[]);
+        let root = arena.alloc(Node { name, children });
+        assert_eq!(root.to_raw(), 0);
+        arena[root].name = arena.alloc_str("root");
+        let mut children = Vec::new();
+        for name in ["a", "b", "c"] {
+            let name = arena.alloc_str(name);
+            let empty = arena.alloc_slice(&[]);
+            children.push(arena.alloc(Node {
+                name,
+                children: empty,
+            }));
+        }
+        arena[root].children = arena.alloc_slice(&children);
+        arena
+    }
+
+    fn names(view: RelView<'_>) -> Vec<&str> {
+        let name = |node: &Node| str::from_utf8(view.get_slice(node.name).unwrap()).unwrap();
+        let root = &view[Offset::<Node>::from_raw(0)];
+        let mut names = alloc::vec![name(root)];
+        for &child in &view[root.children] {
+            names.push(name(&view[child]));
+        }
+        names
+    }
+
+    #[test]
+    fn survives_moving() {
+        let arena = tree();
+        assert_eq!(names(arena.view()), ["root", "a", "b", "c"]);
+
+        // A copy at another address, e.g. read back from a file.
+        let mut chunks = alloc::vec![Chunk([0; ALIGN]); arena.len().div_ceil(ALIGN) + 1];
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(chunks.as_mut_ptr().cast::<u8>().add(ALIGN), arena.len())
+        };
+        bytes.copy_from_slice(arena.as_bytes());
+        assert_eq!(names(RelView::new(bytes).unwrap()), ["root", "a", "b", "c"]);
+        assert!(RelView::<u32>::new(&bytes[1..]).is_none());
+
+        let copy = RelArena::<u32>::from_bytes(arena.as_bytes());
+        assert_eq!(names(copy.view()), ["root", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn grows_by_moving() {
+        let mut arena = RelArena::<u64>::with_capacity(16);
+        let first = arena.alloc(7u64);
+        let offsets: Vec<_> = (0..1000u32).map(|i| arena.alloc(i)).collect();
+        assert_eq!(arena[first], 7);
+        for (i, &offset) in offsets.iter().enumerate() {
+            assert_eq!(arena[offset], i as u32);
+        }
+        arena[first] += 1;
+        assert_eq!(arena.get(first), Some(&8));
+    }
+
+    #[test]
+    fn rejects_bad_offsets() {
+        let mut arena = RelArena::<u32>::new();
+        arena.alloc(1u32);
+        assert_eq!(arena.get(Offset::<u32>::from_raw(4)), None);
+        assert_eq!(arena.get(Offset::<u16>::from_raw(1)), None);
+        assert!(arena.get(Offset::<u16>::from_raw(2)).is_some());
+        let slice = SliceOffset::<u8> {
+            offset: 0,
+            len: u32::MAX,
+            _marker: PhantomData,
+        };
+        assert_eq!(arena.get_slice(slice), None);
+    }
+}
@@ -0,0 +1,145 @@
+// Hooks that tell memory checkers which parts of the blocks hold values.
+// Without the matching features they compile to nothing.
+//
+// The `asan` feature calls the manual poisoning interface of
+// AddressSanitizer, so the crate has to be built with
+// `-Zsanitizer=address` for it to link. The `valgrind` feature issues
+// Memcheck client requests, which do nothing unless the program runs under
+// Valgrind.
+
+#[cfg(feature = "asan")]
+extern "C" {
+    fn __asan_poison_memory_region(addr: *const u8, size: usize);
+    fn __asan_unpoison_memory_region(addr: *const u8, size: usize);
+}
+
+/// Marks `len` bytes at `ptr` as off limits: the unused tail of a block, or
+/// memory given up by a reset or rewind.
+#[cfg_attr(
+    not(any(feature = "asan", feature = "valgrind")),
+    allow(unused_variables)
+)]
+#[inline]
+pub(crate) unsafe fn mark_unaddressable(ptr: *const u8, len: usize) {
+    #[cfg(feature = "asan")]
+    if len > 0 {
+        __asan_poison_memory_region(ptr, len);
+    }
+    #[cfg(feature = "valgrind")]
+    if len > 0 {
+        valgrind::request(0, [valgrind::MAKE_MEM_NOACCESS, ptr.addr(), len, 0, 0, 0]);
+    }
+}
+
+/// Marks `len` bytes at `ptr` as usable again, right before a value is
+/// written there or the block is released.
+#[cfg_attr(
+    not(any(feature = "asan", feature = "valgrind")),
+    allow(unused_variables)
+)]
+#[inline]
+pub(crate) unsafe fn mark_addressable(ptr: *const u8, len: usize) {
+    #[cfg(feature = "asan")]
+    if len > 0 {
+        __asan_unpoison_memory_region(ptr, len);
+    }
+    #[cfg(feature = "valgrind")]
+    if len > 0 {
+        valgrind::request(0, [valgrind::MAKE_MEM_UNDEFINED, ptr.addr(), len, 0, 0, 0]);
+    }
+}
+
+/// Reports `len` bytes at `ptr` as one allocation of its own, so that Valgrind
+/// tracks its accesses and leaks separately from the rest of the block.
+#[cfg(feature = "valgrind")]
+#[inline]
+pub(crate) unsafe fn malloclike(ptr: *const u8, len: usize) {
+    valgrind::request(0, [valgrind::MALLOCLIKE_BLOCK, ptr.addr(), len, 0, 0, 0]);
+}
+
+/// Ends the allocation at `ptr` reported by [`malloclike`].
+#[cfg_attr(not(feature = "valgrind"), allow(unused_variables))]
+#[inline]
+pub(crate) unsafe fn freelike(ptr: *const u8) {
+    #[cfg(feature = "valgrind")]
+    valgrind::request(0, [valgrind::FREELIKE_BLOCK, ptr.addr(), 0, 0, 0, 0]);
+}
+
+/// Changes the size of the allocation at `ptr` reported by [`malloclike`].
+#[cfg(feature = "valgrind")]
+#[inline]
+pub(crate) unsafe fn resize_in_place(ptr: *const u8, old_len: usize, new_len: usize) {
+    valgrind::request(
+        0,
+        [
+            valgrind::RESIZEINPLACE_BLOCK,
+            ptr.addr(),
+            old_len,
+            new_len,
+            0,
+            0,
+        ],
+    );
+}
+
+/// Returns whether the program runs under Valgrind, so that bookkeeping that
+/// only serves client requests can be skipped otherwise.
+#[cfg(feature = "valgrind")]
+pub(crate) fn running_on_valgrind() -> bool {
+    unsafe { valgrind::request(0, [valgrind::RUNNING_ON_VALGRIND, 0, 0, 0, 0, 0]) != 0 }
+}
+
+#[cfg(feature = "valgrind")]
+mod valgrind {
+    // Request codes from `valgrind.h` and `memcheck.h`.
+    pub(super) const RUNNING_ON_VALGRIND: usize = 0x1001;
+    pub(super) const MALLOCLIKE_BLOCK: usize = 0x1301;
+    pub(super) const FREELIKE_BLOCK: usize = 0x1302;
+    pub(super) const RESIZEINPLACE_BLOCK: usize = 0x130b;
+    const MEMCHECK: usize = (b'M' as usize) << 24 | (b'C' as usize) << 16;
+    pub(super) const MAKE_MEM_NOACCESS: usize = MEMCHECK;
+    pub(super) const MAKE_MEM_UNDEFINED: usize = MEMCHECK + 1;
+
+    /// Issues a client request: a sequence of no-op rotations that Valgrind
+    /// recognizes and replaces with a call into the tool. Natively it returns
+    /// `default`.
+    #[inline(always)]
+    pub(super) unsafe fn request(default: usize, args: [usize; 6]) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let result;
+            core::arch::asm!(
+                "rol rdi, 3",
+                "rol rdi, 13",
+                "rol rdi, 61",
+                "rol rdi, 51",
+                "xchg rbx, rbx",
+                inout("rdx") default => result,
+                in("rax") args.as_ptr(),
+                options(nostack),
+            );
+            result
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            let result;
+            core::arch::asm!(
+                "ror x12, x12, #3",
+                "ror x12, x12, #13",
+                "ror x12, x12, #51",
+                "ror x12, x12, #61",
+                "orr x10, x10, x10",
+                inout("x3") default => result,
+                in("x4") args.as_ptr(),
+                options(nostack),
+            );
+            result
+        }
+        // Valgrind has no client requests we know of elsewhere.
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            let _ = args;
+            default
+        }
+    }
+}
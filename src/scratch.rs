@@ -0,0 +1,142 @@
+//! Scratch space for short-lived allocations, reset after each use.
+
+use core::cell::{RefCell, RefMut};
+
+use crate::bump::Bump;
+
+/// A [`Bump`] lent out for the duration of a closure and reset afterwards,
+/// e.g. for the temporary data of one request.
+///
+/// The blocks are kept between scopes, so a warmed-up scratch arena serves
+/// each scope without going to the system allocator. [`with_scratch`] uses
+/// one of these per thread.
+pub struct ScratchArena {
+    bump: RefCell<Bump>,
+}
+
+impl ScratchArena {
+    pub fn new() -> Self {
+        Self::from(Bump::new())
+    }
+
+    /// Runs `f` with the arena and resets it once `f` returns or panics.
+    ///
+    /// A nested call, made while the arena is already lent out, gets a
+    /// fresh `Bump` of its own instead.
+    pub fn scope<R, F: FnOnce(&Bump) -> R>(&self, f: F) -> R {
+        match self.bump.try_borrow_mut() {
+            Ok(bump) => {
+                let guard = Reset(bump);
+                f(&guard.0)
+            }
+            Err(_) => f(&Bump::new()),
+        }
+    }
+
+    /// Returns the number of bytes kept between scopes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside [`scope`](Self::scope).
+    pub fn bytes_allocated(&self) -> usize {
+        self.bump.borrow().bytes_allocated()
+    }
+}
+
+impl Default for ScratchArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Bump> for ScratchArena {
+    fn from(bump: Bump) -> Self {
+        Self {
+            bump: RefCell::new(bump),
+        }
+    }
+}
+
+struct Reset<'b>(RefMut<'b, Bump>);
+
+impl Drop for Reset<'_> {
+    fn drop(&mut self) {
+        self.0.reset();
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static SCRATCH: ScratchArena = ScratchArena::new();
+}
+
+/// Runs `f` with the scratch arena of the current thread, created on first
+/// use, and resets it afterwards. See [`ScratchArena::scope`].
+///
+/// ```
+/// use rusty_arena::scratch::with_scratch;
+///
+/// let total: usize = with_scratch(|bump| {
+///     let words = bump.alloc_from_iter("a bb ccc".split(' ').map(str::len));
+///     words.iter().sum()
+/// });
+/// assert_eq!(total, 6);
+/// ```
+#[cfg(feature = "std")]
+pub fn with_scratch<R, F: FnOnce(&Bump) -> R>(f: F) -> R {
+    SCRATCH.with(|scratch| scratch.scope(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::ScratchArena;
+
+    #[test]
+    fn reset_after_scope() {
+        let scratch = ScratchArena::new();
+        let len = scratch.scope(|bump| bump.alloc_str("temporary").len());
+        assert_eq!(len, 9);
+        let (first, second) = (
+            scratch.scope(|bump| bump.alloc(1u64) as *const u64),
+            scratch.scope(|bump| bump.alloc(2u64) as *const u64),
+        );
+        assert_eq!(first, second);
+        assert_eq!(scratch.bytes_allocated(), 4096);
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let scratch = ScratchArena::new();
+        scratch.scope(|outer| {
+            let x = outer.alloc(1);
+            let y = scratch.scope(|inner| *inner.alloc(2));
+            assert_eq!(*x + y, 3);
+        });
+    }
+
+    #[test]
+    fn reset_on_panic() {
+        let scratch = ScratchArena::new();
+        let first = scratch.scope(|bump| bump.alloc(0u8) as *const u8);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            scratch.scope(|bump| {
+                bump.alloc([0u8; 100]);
+                panic!("request failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(scratch.scope(|bump| bump.alloc(0u8) as *const u8), first);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn thread_local() {
+        use super::with_scratch;
+
+        let sum: u32 = with_scratch(|bump| bump.alloc_slice_copy(&[1, 2, 3]).iter().sum());
+        assert_eq!(sum, 6);
+        with_scratch(|bump| assert_eq!(bump.stats().bytes_used, 0));
+    }
+}
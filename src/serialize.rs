@@ -0,0 +1,132 @@
+// `Serialize` implementations for the arenas and their handles.
+//
+// Typed arenas serialize as a sequence of their values in allocation order.
+// An `Arena` can't be read through `&self` while it accepts allocations, so
+// it is serialized through `Arena::iter`, or frozen first. The index-based
+// arenas serialize as a sequence of `(id, value)` pairs, so that the links
+// between values can be followed in the output.
+
+use core::alloc::GlobalAlloc;
+
+use serde::ser::{Serialize, SerializeSeq, SerializeTuple, Serializer};
+
+use crate::arena;
+use crate::frozen::FrozenArena;
+use crate::generational::{GenArena, GenIdx};
+use crate::id::{IdArena, Idx};
+
+/// Serializes the values left in the iterator, e.g.
+/// `serde_json::to_string(&arena.iter())`.
+impl<T: Serialize> Serialize for arena::Iter<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.clone())
+    }
+}
+
+impl<T: Serialize, A: GlobalAlloc> Serialize for FrozenArena<T, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for x in self {
+            seq.serialize_element(x)?;
+        }
+        seq.end()
+    }
+}
+
+/// Serializes as the plain `u32` index.
+impl<T> Serialize for Idx<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.as_u32())
+    }
+}
+
+impl<T: Serialize> Serialize for IdArena<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for entry in self.iter() {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+/// Serializes as an `(index, generation)` pair.
+impl<T> Serialize for GenIdx<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&(self.index() as u32))?;
+        tuple.serialize_element(&self.generation())?;
+        tuple.end()
+    }
+}
+
+/// Serializes the live values only, in slot order.
+impl<T: Serialize> Serialize for GenArena<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for entry in self.iter() {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::Arena;
+    use crate::generational::GenArena;
+    use crate::id::{IdArena, Idx};
+
+    #[test]
+    fn arena() {
+        let mut arena = Arena::with_block_size(16);
+        for i in 0..10u32 {
+            arena.alloc(i);
+        }
+        let mut iter = arena.iter();
+        iter.next();
+        assert_eq!(serde_json::to_string(&iter).unwrap(), "[1,2,3,4,5,6,7,8,9]");
+        let frozen = arena.freeze();
+        assert_eq!(
+            serde_json::to_string(&frozen).unwrap(),
+            "[0,1,2,3,4,5,6,7,8,9]"
+        );
+    }
+
+    #[test]
+    fn id_arena() {
+        #[derive(serde::Serialize)]
+        struct Node {
+            name: &'static str,
+            next: Option<Idx<Node>>,
+        }
+
+        let mut nodes = IdArena::new();
+        let a = nodes.alloc(Node {
+            name: "a",
+            next: None,
+        });
+        let b = nodes.alloc(Node {
+            name: "b",
+            next: Some(a),
+        });
+        nodes[a].next = Some(b);
+        assert_eq!(
+            serde_json::to_string(&nodes).unwrap(),
+            r#"[[0,{"name":"a","next":1}],[1,{"name":"b","next":0}]]"#
+        );
+    }
+
+    #[test]
+    fn gen_arena() {
+        let mut arena = GenArena::new();
+        let a = arena.insert("a");
+        arena.insert("b");
+        arena.remove(a);
+        arena.insert("c");
+        assert_eq!(
+            serde_json::to_string(&arena).unwrap(),
+            r#"[[[0,1],"c"],[[1,0],"b"]]"#
+        );
+    }
+}
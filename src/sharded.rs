@@ -0,0 +1,316 @@
+//! A bump arena where each thread fills a block of its own.
+
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::cmp;
+use core::ptr;
+use core::slice;
+use core::str;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use alloc::vec::Vec;
+
+use crate::block::RawBlock;
+use crate::{telemetry, ArenaAllocError, Global};
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+
+struct Pool {
+    blocks: Vec<RawBlock>,
+    // Size of the next block handed to a shard, doubled after each one up to
+    // `max_block_size`.
+    block_size: usize,
+    max_block_size: usize,
+    bytes: usize,
+}
+
+/// An untyped arena shared between threads, where each thread allocates
+/// through a [`Shard`] of its own.
+///
+/// A shard bump-allocates out of its current block without any atomics.
+/// Only fetching the next block from the shared pool takes a lock. Every
+/// block belongs to the `ShardedBump`, so values outlive the shard that
+/// allocated them. Like with a [`Bump`](crate::bump::Bump), destructors of
+/// allocated values are never run.
+///
+/// ```
+/// use rusty_arena::sharded::ShardedBump;
+///
+/// let arena = ShardedBump::new();
+/// let names = std::thread::scope(|s| {
+///     let workers: Vec<_> = (0..4)
+///         .map(|i| {
+///             let arena = &arena;
+///             s.spawn(move || arena.shard().alloc_str(&format!("worker {i}")))
+///         })
+///         .collect();
+///     workers.into_iter().map(|w| w.join().unwrap()).collect::<Vec<_>>()
+/// });
+/// assert_eq!(names[3], "worker 3");
+/// ```
+pub struct ShardedBump {
+    pool: Mutex<Pool>,
+}
+
+// Each shard hands its values to one thread, and the blocks are only
+// released through `&mut self`.
+unsafe impl Send for ShardedBump {}
+unsafe impl Sync for ShardedBump {}
+
+impl ShardedBump {
+    pub fn new() -> Self {
+        Self::with_block_growth(BLOCK_SIZE, MAX_BLOCK_SIZE)
+    }
+
+    /// Creates an arena whose shards request blocks of `block_size` bytes.
+    /// Values larger than that get a block of their own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    pub fn with_block_size(block_size: usize) -> Self {
+        Self::with_block_growth(block_size, block_size)
+    }
+
+    /// Creates an arena whose first block has `initial` bytes and where each
+    /// following block doubles in size until it reaches `max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero or larger than `max`.
+    pub fn with_block_growth(initial: usize, max: usize) -> Self {
+        assert!(initial > 0, "block size must be non-zero");
+        assert!(initial <= max, "initial block size exceeds the maximum");
+        Self {
+            pool: Mutex::new(Pool {
+                blocks: Vec::new(),
+                block_size: initial,
+                max_block_size: max,
+                bytes: 0,
+            }),
+        }
+    }
+
+    /// Returns a new shard to allocate from on the calling thread. It takes
+    /// its first block on its first allocation.
+    pub fn shard(&self) -> Shard<'_> {
+        Shard {
+            arena: self,
+            alloc_ptr: Cell::new(ptr::null_mut()),
+            alloc_end: Cell::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the number of bytes obtained from the system allocator by all
+    /// shards.
+    pub fn bytes_allocated(&self) -> usize {
+        self.lock().bytes
+    }
+
+    /// Releases every block. No shard can be alive at this point.
+    pub fn reset(&mut self) {
+        let pool = self.pool.get_mut().unwrap_or_else(PoisonError::into_inner);
+        telemetry::reset(pool.bytes);
+        pool.bytes = 0;
+        for block in pool.blocks.drain(..) {
+            unsafe { block.free(&Global) };
+        }
+    }
+
+    /// Takes a block that can hold `layout` from the pool. Values larger
+    /// than the next block size get an exactly-sized block, returned along
+    /// with `true`.
+    fn take_block(&self, layout: Layout) -> Result<(*mut [u8], bool), ArenaAllocError> {
+        let mut pool = self.lock();
+        let large = layout.size() > pool.block_size;
+        let block = RawBlock::try_new_in(
+            cmp::max(layout.size(), pool.block_size),
+            layout.align(),
+            &Global,
+        )?;
+        if !large {
+            pool.block_size = cmp::min(pool.block_size.saturating_mul(2), pool.max_block_size);
+        }
+        pool.bytes += block.size();
+        let block_ptr = ptr::slice_from_raw_parts_mut(block.ptr(), block.size());
+        pool.blocks.push(block);
+        Ok((block_ptr, large))
+    }
+
+    // The pool is only updated after a block was obtained, so a panic can't
+    // leave it half updated.
+    fn lock(&self) -> MutexGuard<'_, Pool> {
+        self.pool.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for ShardedBump {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ShardedBump {
+    fn drop(&mut self) {
+        let pool = self.pool.get_mut().unwrap_or_else(PoisonError::into_inner);
+        telemetry::dropped(pool.bytes);
+        for block in pool.blocks.drain(..) {
+            unsafe { block.free(&Global) };
+        }
+    }
+}
+
+/// One thread's view of a [`ShardedBump`], created by
+/// [`ShardedBump::shard`].
+///
+/// Values live as long as the arena, not the shard. Dropping the shard
+/// abandons the rest of its block.
+pub struct Shard<'a> {
+    arena: &'a ShardedBump,
+    alloc_ptr: Cell<*mut u8>,
+    alloc_end: Cell<*mut u8>,
+}
+
+impl<'a> Shard<'a> {
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, data: T) -> &'a mut T {
+        self.alloc_with(|| data)
+    }
+
+    /// Allocates space for a `T` and writes the result of `f` straight into
+    /// it.
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with<T, F: FnOnce() -> T>(&self, f: F) -> &'a mut T {
+        unsafe {
+            let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+            ptr::write(ptr, f());
+            &mut *ptr
+        }
+    }
+
+    /// Like [`alloc`](Self::alloc), but returns an error instead of aborting
+    /// when a new block cannot be obtained.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_alloc<T>(&self, data: T) -> Result<&'a mut T, ArenaAllocError> {
+        unsafe {
+            let ptr = self.try_alloc_layout(Layout::new::<T>())?.cast::<T>();
+            ptr::write(ptr, data);
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Copies `src` into a contiguous run of arena memory.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &'a mut [T] {
+        unsafe {
+            let ptr = self.alloc_layout(Layout::for_value(src)).cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            slice::from_raw_parts_mut(ptr, src.len())
+        }
+    }
+
+    /// Copies `src` into the arena.
+    pub fn alloc_str(&self, src: &str) -> &'a str {
+        let bytes = self.alloc_slice_copy(src.as_bytes());
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Returns the arena this shard allocates from.
+    pub fn arena(&self) -> &'a ShardedBump {
+        self.arena
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> *mut u8 {
+        match self.try_alloc_layout(layout) {
+            Ok(ptr) => ptr,
+            Err(e) => e.handle(),
+        }
+    }
+
+    #[inline(always)]
+    fn try_alloc_layout(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        if layout.size() == 0 {
+            return Ok(ptr::without_provenance_mut(layout.align()));
+        }
+        let alloc_ptr = self.alloc_ptr.get();
+        if !alloc_ptr.is_null() {
+            let offset = alloc_ptr.align_offset(layout.align());
+            let remaining = self.alloc_end.get().addr() - alloc_ptr.addr();
+            if offset <= remaining && layout.size() <= remaining - offset {
+                unsafe {
+                    let ptr = alloc_ptr.add(offset);
+                    self.alloc_ptr.set(ptr.add(layout.size()));
+                    return Ok(ptr);
+                }
+            }
+        }
+        self.alloc_slow(layout)
+    }
+
+    #[cold]
+    fn alloc_slow(&self, layout: Layout) -> Result<*mut u8, ArenaAllocError> {
+        let (block, large) = self.arena.take_block(layout)?;
+        let ptr = block.cast::<u8>();
+        if !large {
+            unsafe {
+                self.alloc_ptr.set(ptr.add(layout.size()));
+                self.alloc_end.set(ptr.add(block.len()));
+            }
+        }
+        Ok(ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::ShardedBump;
+
+    #[test]
+    fn shard_per_thread() {
+        let arena = ShardedBump::with_block_size(256);
+        let values: Vec<Vec<&mut u64>> = thread::scope(|s| {
+            let workers: Vec<_> = (0..8u64)
+                .map(|t| {
+                    let arena = &arena;
+                    s.spawn(move || {
+                        let shard = arena.shard();
+                        (0..1000).map(|i| shard.alloc(t * 1000 + i)).collect()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        for (t, values) in values.iter().enumerate() {
+            for (i, value) in values.iter().enumerate() {
+                assert_eq!(**value, (t * 1000 + i) as u64);
+            }
+        }
+        assert!(arena.bytes_allocated() >= 8000 * 8);
+    }
+
+    #[test]
+    fn large_values_keep_the_block() {
+        let arena = ShardedBump::with_block_size(128);
+        let shard = arena.shard();
+        let a = shard.alloc(1u32);
+        let large = shard.alloc_slice_copy(&[2u8; 1000]);
+        let b = shard.alloc(3u32);
+        assert_eq!((b as *mut u32).addr() - (a as *mut u32).addr(), 4);
+        assert_eq!(large.len(), 1000);
+        assert_eq!(arena.bytes_allocated(), 1128);
+    }
+
+    #[test]
+    fn reset() {
+        let mut arena = ShardedBump::new();
+        arena.shard().alloc_str("text");
+        assert_eq!(arena.bytes_allocated(), 4096);
+        arena.reset();
+        assert_eq!(arena.bytes_allocated(), 0);
+        assert!(arena.shard().try_alloc(1u8).is_ok());
+    }
+}
@@ -0,0 +1,263 @@
+// Publishing a relocatable arena to other processes through a named POSIX
+// shared-memory segment.
+//
+// The segment starts with a header of `ALIGN` bytes: a magic number, the
+// offset width and the length of the arena's bytes, which follow. The magic
+// number is stored last, so a reader that maps the segment while it is still
+// being filled sees a segment that isn't ready instead of partial values.
+
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::ffi::CString;
+use std::io;
+
+use crate::relative::{OffsetWidth, RelArena, RelView, ALIGN};
+
+const MAGIC: u32 = 0x5241_5348;
+const HEADER_LEN: usize = ALIGN;
+
+/// A [`RelArena`] in a named shared-memory segment, mapped read-only.
+///
+/// One process builds an arena and [`publish`](Self::publish)es it under a
+/// name such as `/routes`; others [`open`](Self::open) the name and read the
+/// values in place through [`view`](Self::view), following the offsets that
+/// link them. The segment is immutable once published: to replace it,
+/// [`unlink`](Self::unlink) the name and publish a new arena under it.
+/// Processes that have the old one mapped keep reading it until they open the
+/// name again.
+///
+/// The segment stays around until the name is unlinked, even after every
+/// process has dropped its mapping.
+pub struct SharedSegment<O: OffsetWidth = u32> {
+    ptr: NonNull<u8>,
+    map_len: usize,
+    _marker: PhantomData<O>,
+}
+
+// The mapping is read-only, and nobody writes to a published segment.
+unsafe impl<O: OffsetWidth> Send for SharedSegment<O> {}
+unsafe impl<O: OffsetWidth> Sync for SharedSegment<O> {}
+
+fn c_name(name: &str) -> io::Result<CString> {
+    CString::new(name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "name contains a nul byte"))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<O: OffsetWidth> SharedSegment<O> {
+    /// Creates a segment named `name` holding a copy of `arena`, readable by
+    /// the processes of the same user.
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if a segment of that name
+    /// exists.
+    pub fn publish(arena: &RelArena<O>, name: &str) -> io::Result<Self> {
+        let c_name = c_name(name)?;
+        let bytes = arena.as_bytes();
+        let map_len = HEADER_LEN + bytes.len();
+        unsafe {
+            let fd = libc::shm_open(
+                c_name.as_ptr(),
+                libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+                0o600 as libc::c_uint,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let ptr = if libc::ftruncate(fd, map_len as libc::off_t) == 0 {
+                libc::mmap(
+                    ptr::null_mut(),
+                    map_len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    fd,
+                    0,
+                )
+            } else {
+                libc::MAP_FAILED
+            };
+            if ptr == libc::MAP_FAILED {
+                let err = io::Error::last_os_error();
+                libc::close(fd);
+                libc::shm_unlink(c_name.as_ptr());
+                return Err(err);
+            }
+            libc::close(fd);
+
+            let ptr = ptr.cast::<u8>();
+            ptr.add(4).cast::<u32>().write(mem::size_of::<O>() as u32);
+            ptr.add(8).cast::<u64>().write(bytes.len() as u64);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.add(HEADER_LEN), bytes.len());
+            AtomicU32::from_ptr(ptr.cast()).store(MAGIC, Ordering::Release);
+            libc::mprotect(ptr.cast(), map_len, libc::PROT_READ);
+            Ok(Self {
+                ptr: NonNull::new_unchecked(ptr),
+                map_len,
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    /// Maps the segment published under `name`.
+    ///
+    /// Fails with [`io::ErrorKind::NotFound`] if there is none, and with
+    /// [`io::ErrorKind::InvalidData`] if it is still being published or was
+    /// published with another offset width.
+    pub fn open(name: &str) -> io::Result<Self> {
+        let c_name = c_name(name)?;
+        let segment = unsafe {
+            let fd = libc::shm_open(c_name.as_ptr(), libc::O_RDONLY, 0 as libc::c_uint);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut stat: libc::stat = mem::zeroed();
+            if libc::fstat(fd, &mut stat) != 0 {
+                let err = io::Error::last_os_error();
+                libc::close(fd);
+                return Err(err);
+            }
+            let map_len = stat.st_size as usize;
+            if map_len < HEADER_LEN {
+                libc::close(fd);
+                return Err(invalid("shared segment isn't ready"));
+            }
+            let ptr = libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            );
+            let err = io::Error::last_os_error();
+            libc::close(fd);
+            if ptr == libc::MAP_FAILED {
+                return Err(err);
+            }
+            Self {
+                ptr: NonNull::new_unchecked(ptr.cast()),
+                map_len,
+                _marker: PhantomData,
+            }
+        };
+        let magic = unsafe { AtomicU32::from_ptr(segment.ptr.as_ptr().cast()) };
+        if magic.load(Ordering::Acquire) != MAGIC {
+            return Err(invalid("shared segment isn't ready"));
+        }
+        let header = segment.header();
+        if header[4..8] != (mem::size_of::<O>() as u32).to_ne_bytes() {
+            return Err(invalid("shared segment has another offset width"));
+        }
+        if segment.len() > segment.map_len - HEADER_LEN {
+            return Err(invalid("shared segment is truncated"));
+        }
+        Ok(segment)
+    }
+
+    /// Removes the name, so that it can be published again. Existing
+    /// mappings stay valid.
+    pub fn unlink(name: &str) -> io::Result<()> {
+        let c_name = c_name(name)?;
+        if unsafe { libc::shm_unlink(c_name.as_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes of the arena.
+    pub fn len(&self) -> usize {
+        u64::from_ne_bytes(self.header()[8..16].try_into().unwrap()) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn view(&self) -> RelView<'_, O> {
+        let bytes = unsafe { slice::from_raw_parts(self.ptr.as_ptr().add(HEADER_LEN), self.len()) };
+        // Mappings start at a page boundary, and the header keeps the
+        // alignment.
+        RelView::new(bytes).unwrap()
+    }
+
+    fn header(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), HEADER_LEN) }
+    }
+}
+
+impl<O: OffsetWidth> Drop for SharedSegment<O> {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.map_len) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, process, str};
+
+    use super::*;
+    use crate::relative::{Offset, SliceOffset};
+
+    /// A segment name of this process, unlinked when dropped.
+    struct Name(String);
+
+    impl Name {
+        fn new(name: &str) -> Self {
+            Self(format!("/rusty-arena-{}-{}", process::id(), name))
+        }
+    }
+
+    impl Drop for Name {
+        fn drop(&mut self) {
+            let _ = SharedSegment::<u32>::unlink(&self.0);
+        }
+    }
+
+    #[test]
+    fn publish_and_open() {
+        let name = Name::new("publish_and_open");
+        let mut arena = RelArena::<u32>::new();
+        let routes: SliceOffset<SliceOffset<u8>> = {
+            let paths: Vec<_> = ["/", "/users", "/users/:id"]
+                .into_iter()
+                .map(|path| arena.alloc_str(path))
+                .collect();
+            arena.alloc_slice(&paths)
+        };
+        let root = arena.alloc(routes);
+
+        let published = SharedSegment::publish(&arena, &name.0).unwrap();
+        assert_eq!(published.len(), arena.len());
+        drop(arena);
+
+        let workers: Vec<_> = (0..2)
+            .map(|_| SharedSegment::<u32>::open(&name.0).unwrap())
+            .collect();
+        for segment in &workers {
+            let view = segment.view();
+            let paths: Vec<_> = view[view[root]]
+                .iter()
+                .map(|&path| str::from_utf8(&view[path]).unwrap())
+                .collect();
+            assert_eq!(paths, ["/", "/users", "/users/:id"]);
+        }
+
+        let err = SharedSegment::publish(&RelArena::<u32>::new(), &name.0)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        let err = SharedSegment::<u64>::open(&name.0).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Unlinking leaves existing mappings alone.
+        SharedSegment::<u32>::unlink(&name.0).unwrap();
+        let err = SharedSegment::<u32>::open(&name.0).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(workers[0].view()[Offset::<u8>::from_raw(0)], b'/');
+    }
+}
@@ -0,0 +1,149 @@
+//! Named snapshots of a typed arena, for undo.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::GlobalAlloc;
+use core::ops::Deref;
+
+use crate::arena::{Arena, Marker};
+use crate::Global;
+
+/// An [`Arena`] with a stack of named snapshots that it can be rolled back
+/// to, e.g. one per edit of a document.
+///
+/// Rolling back to a snapshot drops every value allocated after it was taken
+/// and reclaims their space. Allocation goes through `Deref` to the arena, so
+/// rolling back, which needs `&mut self`, can't drop a value that is still
+/// borrowed.
+pub struct SnapshotArena<T, A: GlobalAlloc = Global> {
+    arena: Arena<T, A>,
+    snapshots: Vec<(String, Marker)>,
+}
+
+impl<T> SnapshotArena<T> {
+    pub fn new() -> Self {
+        Self::from(Arena::new())
+    }
+}
+
+impl<T, A: GlobalAlloc> SnapshotArena<T, A> {
+    /// Records the current position under `name`, on top of the stack.
+    ///
+    /// Names don't have to be unique; rolling back goes to the most recent
+    /// snapshot of a name.
+    pub fn push_snapshot(&mut self, name: impl Into<String>) {
+        self.snapshots.push((name.into(), self.arena.checkpoint()));
+    }
+
+    /// Drops everything allocated after the most recent snapshot named
+    /// `name` was taken, along with the snapshots pushed after it. The
+    /// snapshot itself stays on the stack, so that it can be rolled back to
+    /// again.
+    ///
+    /// Returns `false`, leaving the arena alone, if there is no such
+    /// snapshot.
+    pub fn rollback_to(&mut self, name: &str) -> bool {
+        let Some(i) = self.snapshots.iter().rposition(|(n, _)| n == name) else {
+            return false;
+        };
+        self.arena.rewind(self.snapshots[i].1);
+        self.snapshots.truncate(i + 1);
+        true
+    }
+
+    /// Forgets the most recent snapshot without rolling back, returning its
+    /// name.
+    pub fn pop_snapshot(&mut self) -> Option<String> {
+        self.snapshots.pop().map(|(name, _)| name)
+    }
+
+    /// Returns the names of the snapshots, oldest first.
+    pub fn snapshots(&self) -> impl Iterator<Item = &str> {
+        self.snapshots.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Drops every element and forgets every snapshot, keeping the blocks.
+    pub fn reset(&mut self) {
+        self.snapshots.clear();
+        self.arena.reset();
+    }
+
+    pub fn into_inner(self) -> Arena<T, A> {
+        self.arena
+    }
+}
+
+impl<T> Default for SnapshotArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, A: GlobalAlloc> From<Arena<T, A>> for SnapshotArena<T, A> {
+    fn from(arena: Arena<T, A>) -> Self {
+        Self {
+            arena,
+            snapshots: Vec::new(),
+        }
+    }
+}
+
+impl<T, A: GlobalAlloc> Deref for SnapshotArena<T, A> {
+    type Target = Arena<T, A>;
+
+    fn deref(&self) -> &Arena<T, A> {
+        &self.arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::SnapshotArena;
+
+    #[test]
+    fn undo() {
+        struct Edit(Rc<Cell<usize>>);
+
+        impl Drop for Edit {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let mut doc = SnapshotArena::new();
+        doc.alloc(Edit(drops.clone()));
+        doc.push_snapshot("typed");
+        doc.alloc(Edit(drops.clone()));
+        doc.alloc(Edit(drops.clone()));
+        doc.push_snapshot("pasted");
+        doc.alloc(Edit(drops.clone()));
+        doc.push_snapshot("typed");
+        doc.alloc(Edit(drops.clone()));
+        assert_eq!(doc.len(), 5);
+
+        assert!(doc.rollback_to("pasted"));
+        assert_eq!(doc.len(), 3);
+        assert_eq!(drops.get(), 2);
+        assert!(doc.snapshots().eq(["typed", "pasted"]));
+
+        // Rolling back again to the same snapshot drops what came since.
+        doc.alloc(Edit(drops.clone()));
+        assert!(doc.rollback_to("pasted"));
+        assert_eq!(doc.len(), 3);
+        assert_eq!(drops.get(), 3);
+
+        assert!(!doc.rollback_to("saved"));
+        assert_eq!(doc.pop_snapshot().as_deref(), Some("pasted"));
+        assert!(doc.rollback_to("typed"));
+        assert_eq!(doc.len(), 1);
+        assert_eq!(drops.get(), 5);
+
+        doc.reset();
+        assert_eq!(drops.get(), 6);
+        assert_eq!(doc.snapshots().count(), 0);
+    }
+}
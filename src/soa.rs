@@ -0,0 +1,308 @@
+//! Struct-of-arrays arenas, generated with `#[derive(SoaArena)]`.
+//!
+//! ```
+//! use rusty_arena::soa::Soa;
+//! use rusty_arena::SoaArena;
+//!
+//! #[derive(SoaArena)]
+//! struct Particle {
+//!     pos: [f32; 2],
+//!     mass: f32,
+//! }
+//!
+//! let mut particles = <Particle as Soa>::Arena::new();
+//! let p = particles.alloc(Particle { pos: [0.0, 1.0], mass: 2.0 });
+//! for chunk in particles.mass_column_mut().chunks_mut() {
+//!     for m in chunk {
+//!         *m *= 2.0;
+//!     }
+//! }
+//! assert_eq!(*particles.get(p).unwrap().mass, 4.0);
+//! ```
+//!
+//! Each field lives in its own [`Column`] of arena blocks, so a pass over one
+//! field touches only that field's memory, one contiguous chunk at a time.
+//! Rows are addressed with the [`Idx`](crate::id::Idx) handles of
+//! [`IdArena`](crate::id::IdArena).
+
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Index, IndexMut};
+use core::ptr::{self, NonNull};
+use core::slice;
+
+use crate::block::RawBlock;
+use crate::{ArenaAllocError, Global};
+
+const BLOCK_SIZE: usize = 4096;
+
+/// Implemented by `#[derive(SoaArena)]`, naming the generated arena.
+pub trait Soa {
+    type Arena;
+}
+
+/// The values of one field of a struct-of-arrays arena, in allocation order.
+///
+/// They are stored in fixed-size blocks of about 4 KiB each, which are never
+/// moved or grown, so pushing a row doesn't copy the rows before it. Passes
+/// over the column go through [`chunks`](Self::chunks), one slice per block.
+pub struct Column<T> {
+    blocks: Vec<RawBlock>,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+// The column owns its values like a `Vec` does.
+unsafe impl<T: Send> Send for Column<T> {}
+unsafe impl<T: Sync> Sync for Column<T> {}
+
+impl<T> Column<T> {
+    /// Creates an empty column. No block is allocated until the first push.
+    pub const fn new() -> Self {
+        Self {
+            blocks: Vec::new(),
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of values per block. Zero-sized values all fit in
+    /// one chunk and never allocate.
+    pub const fn chunk_len() -> usize {
+        match mem::size_of::<T>() {
+            0 => usize::MAX,
+            size if size >= BLOCK_SIZE => 1,
+            size => BLOCK_SIZE / size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, value: T) {
+        let (block, offset) = (self.len / Self::chunk_len(), self.len % Self::chunk_len());
+        if mem::size_of::<T>() != 0 && block == self.blocks.len() {
+            self.new_block();
+        }
+        unsafe { self.chunk_ptr(block).add(offset).write(value) };
+        self.len += 1;
+    }
+
+    #[cold]
+    fn new_block(&mut self) {
+        let layout = match Layout::array::<T>(Self::chunk_len()) {
+            Ok(layout) => layout,
+            Err(_) => ArenaAllocError::CapacityOverflow.handle(),
+        };
+        match RawBlock::try_new_in(layout.size(), layout.align(), &Global) {
+            Ok(block) => self.blocks.push(block),
+            Err(e) => e.handle(),
+        }
+    }
+
+    fn chunk_ptr(&self, block: usize) -> *mut T {
+        match self.blocks.get(block) {
+            Some(block) => block.ptr().cast(),
+            None => NonNull::dangling().as_ptr(),
+        }
+    }
+
+    fn chunk_count(&self) -> usize {
+        self.len.div_ceil(Self::chunk_len())
+    }
+
+    // The number of values in the `block`-th chunk.
+    fn chunk_size(&self, block: usize) -> usize {
+        (self.len - block * Self::chunk_len()).min(Self::chunk_len())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let (block, offset) = (index / Self::chunk_len(), index % Self::chunk_len());
+        unsafe { Some(&*self.chunk_ptr(block).add(offset)) }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let (block, offset) = (index / Self::chunk_len(), index % Self::chunk_len());
+        unsafe { Some(&mut *self.chunk_ptr(block).add(offset)) }
+    }
+
+    /// Returns the values block by block, each chunk a contiguous slice.
+    pub fn chunks(&self) -> impl ExactSizeIterator<Item = &[T]> {
+        (0..self.chunk_count())
+            .map(|i| unsafe { slice::from_raw_parts(self.chunk_ptr(i), self.chunk_size(i)) })
+    }
+
+    /// Like [`chunks`](Self::chunks), with mutable slices.
+    pub fn chunks_mut(&mut self) -> impl ExactSizeIterator<Item = &mut [T]> {
+        let this = &*self;
+        (0..this.chunk_count())
+            .map(|i| unsafe { slice::from_raw_parts_mut(this.chunk_ptr(i), this.chunk_size(i)) })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks().flatten()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.chunks_mut().flatten()
+    }
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Column<T> {
+    fn drop(&mut self) {
+        for chunk in self.chunks_mut() {
+            unsafe { ptr::drop_in_place(chunk) };
+        }
+        for block in self.blocks.drain(..) {
+            unsafe { block.free(&Global) };
+        }
+    }
+}
+
+impl<T> Index<usize> for Column<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(x) => x,
+            None => panic!(
+                "index {} out of bounds for a column of length {}",
+                index, self.len
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for Column<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(x) => x,
+            None => panic!("index {index} out of bounds for a column of length {len}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Soa;
+    use crate::SoaArena;
+
+    #[derive(Clone, Copy, Debug, PartialEq, SoaArena)]
+    struct Body {
+        pos: [f32; 3],
+        vel: [f32; 3],
+        mass: f32,
+    }
+
+    #[test]
+    fn columns() {
+        let mut bodies = BodyArena::new();
+        let ids: Vec<_> = (0..100)
+            .map(|i| {
+                bodies.alloc(Body {
+                    pos: [i as f32; 3],
+                    vel: [1.0; 3],
+                    mass: 1.0,
+                })
+            })
+            .collect();
+        assert_eq!(bodies.len(), 100);
+
+        let columns = bodies.columns_mut();
+        for (pos, vel) in columns.pos.iter_mut().zip(columns.vel.iter()) {
+            pos[0] += vel[0];
+        }
+        let row = bodies.get(ids[9]).unwrap();
+        assert_eq!(row.pos, &[10.0, 9.0, 9.0]);
+        assert_eq!(bodies.mass_column().iter().sum::<f32>(), 100.0);
+
+        *bodies.get_mut(ids[0]).unwrap().mass = 5.0;
+        assert_eq!(bodies.mass_column()[0], 5.0);
+        assert!(bodies.get(crate::id::Idx::from_u32(100)).is_none());
+    }
+
+    #[test]
+    fn arena_type() {
+        let mut arena = <Body as Soa>::Arena::default();
+        assert!(arena.is_empty());
+        arena.alloc(Body {
+            pos: [0.0; 3],
+            vel: [0.0; 3],
+            mass: 0.0,
+        });
+        assert_eq!(arena.pos_column_mut().len(), 1);
+    }
+
+    // Fields named after the methods of the generated arena.
+    #[derive(SoaArena)]
+    struct Entry {
+        len: usize,
+        get: String,
+        alloc: bool,
+    }
+
+    #[test]
+    fn field_names() {
+        let mut entries = EntryArena::new();
+        let ids: Vec<_> = (0..2000)
+            .map(|i| {
+                entries.alloc(Entry {
+                    len: i,
+                    get: i.to_string(),
+                    alloc: i % 2 == 0,
+                })
+            })
+            .collect();
+        assert_eq!(entries.len(), 2000);
+        assert_eq!(entries.len_column()[1999], 1999);
+        assert_eq!(entries.get(ids[7]).unwrap().get, "7");
+        assert!(!*entries.get(ids[7]).unwrap().alloc);
+        entries.get_column_mut()[0].push('!');
+        assert_eq!(entries.columns_mut().get[0], "0!");
+    }
+
+    #[test]
+    fn chunks_stay_in_place() {
+        let mut entries = EntryArena::new();
+        let first = entries.alloc(Entry {
+            len: 0,
+            get: String::new(),
+            alloc: true,
+        });
+        let row = entries.get(first).unwrap().len as *const usize;
+        for i in 1..2000 {
+            entries.alloc(Entry {
+                len: i,
+                get: String::new(),
+                alloc: false,
+            });
+        }
+        // 512 `usize`s to a block.
+        assert_eq!(entries.get(first).unwrap().len as *const usize, row);
+        let chunks: Vec<_> = entries.len_column().chunks().map(<[_]>::len).collect();
+        assert_eq!(chunks, [512, 512, 512, 464]);
+        assert_eq!(entries.alloc_column().chunks().len(), 1);
+        assert!(entries.len_column().iter().copied().eq(0..2000));
+        assert!(entries.len_column().get(2000).is_none());
+    }
+}
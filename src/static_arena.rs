@@ -0,0 +1,179 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A bump allocator over a fixed region of `N` bytes, usable as the
+/// `#[global_allocator]` of builds where the whole heap is one arena.
+///
+/// ```no_run
+/// use rusty_arena::StaticArena;
+///
+/// #[global_allocator]
+/// static HEAP: StaticArena<{ 1 << 20 }> = StaticArena::new();
+/// # fn main() {}
+/// ```
+///
+/// Memory is never reused, except that the most recent allocation can be
+/// freed or resized in place. Allocations past the end of the region return
+/// null.
+pub struct StaticArena<const N: usize> {
+    region: UnsafeCell<[MaybeUninit<u8>; N]>,
+    // Bytes handed out from the start of `region`.
+    used: AtomicUsize,
+}
+
+// The region is only written through pointers carved out by `used`, which is
+// updated atomically.
+unsafe impl<const N: usize> Sync for StaticArena<N> {}
+
+impl<const N: usize> StaticArena<N> {
+    pub const fn new() -> Self {
+        Self {
+            region: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of bytes handed out, including alignment padding.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes left in the region.
+    pub fn remaining(&self) -> usize {
+        N - self.used()
+    }
+
+    fn start(&self) -> *mut u8 {
+        self.region.get().cast()
+    }
+}
+
+impl<const N: usize> Default for StaticArena<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const N: usize> GlobalAlloc for StaticArena<N> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let start = self.start();
+        let mut used = self.used.load(Ordering::Relaxed);
+        loop {
+            let offset = used + start.add(used).align_offset(layout.align());
+            let end = match offset.checked_add(layout.size()) {
+                Some(end) if end <= N => end,
+                _ => return ptr::null_mut(),
+            };
+            match self
+                .used
+                .compare_exchange_weak(used, end, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return start.add(offset),
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Only the most recent allocation can be given back.
+        let end = ptr.offset_from_unsigned(self.start()) + layout.size();
+        let _ = self.used.compare_exchange(
+            end,
+            end - layout.size(),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let offset = ptr.offset_from_unsigned(self.start());
+        let old_end = offset + layout.size();
+        if new_size <= layout.size() {
+            let _ = self.used.compare_exchange(
+                old_end,
+                offset + new_size,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+            return ptr;
+        }
+        if new_size <= N - offset
+            && self
+                .used
+                .compare_exchange(
+                    old_end,
+                    offset + new_size,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+        {
+            return ptr;
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size());
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout};
+
+    use super::StaticArena;
+
+    #[test]
+    fn alloc() {
+        static HEAP: StaticArena<256> = StaticArena::new();
+        let a = unsafe { HEAP.alloc(Layout::new::<u8>()) };
+        let b = unsafe { HEAP.alloc(Layout::new::<u64>()) };
+        assert!(!a.is_null());
+        assert_eq!(b.addr() % 8, 0);
+        assert!(b > a);
+        assert!(HEAP.used() >= 9);
+
+        let huge = unsafe { HEAP.alloc(Layout::from_size_align(512, 1).unwrap()) };
+        assert!(huge.is_null());
+        assert_eq!(HEAP.used() + HEAP.remaining(), 256);
+    }
+
+    #[test]
+    fn dealloc_last() {
+        let heap = StaticArena::<64>::new();
+        let layout = Layout::new::<u32>();
+        let a = unsafe { heap.alloc(layout) };
+        let b = unsafe { heap.alloc(layout) };
+        let used = heap.used();
+
+        // Freeing anything but the last allocation is a no-op.
+        unsafe { heap.dealloc(a, layout) };
+        assert_eq!(heap.used(), used);
+        unsafe { heap.dealloc(b, layout) };
+        assert_eq!(heap.used(), used - 4);
+        assert_eq!(unsafe { heap.alloc(layout) }, b);
+    }
+
+    #[test]
+    fn realloc() {
+        let heap = StaticArena::<64>::new();
+        let layout = Layout::from_size_align(8, 1).unwrap();
+        let a = unsafe { heap.alloc(layout) };
+        unsafe { a.write_bytes(7, 8) };
+
+        // The last allocation grows in place.
+        assert_eq!(unsafe { heap.realloc(a, layout, 16) }, a);
+        let grown = Layout::from_size_align(16, 1).unwrap();
+        let b = unsafe { heap.alloc(layout) };
+        let moved = unsafe { heap.realloc(a, grown, 32) };
+        assert_ne!(moved, a);
+        assert!(moved > b);
+        assert_eq!(unsafe { *moved.add(7) }, 7);
+        assert!(unsafe { heap.realloc(moved, grown, 64) }.is_null());
+    }
+}
@@ -0,0 +1,31 @@
+/// A snapshot of the memory usage of an arena, returned by `stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArenaStats {
+    /// Number of blocks held, including spare blocks kept for reuse.
+    pub blocks: usize,
+    /// Bytes obtained from the system allocator.
+    pub bytes_reserved: usize,
+    /// Bytes taken up by the values currently in the arena.
+    pub bytes_used: usize,
+    /// Bytes left over at the end of blocks that the arena moved past.
+    pub wasted_bytes: usize,
+    /// The largest `bytes_used` seen since the arena was created.
+    pub high_water_mark: usize,
+}
+
+/// What an arena allocated after a checkpoint, returned by `allocated_since`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AllocatedSince {
+    /// Number of values allocated.
+    pub elements: usize,
+    /// Bytes taken up by those values.
+    pub bytes_used: usize,
+    /// Number of blocks the arena moved into, fresh or reused.
+    pub blocks: usize,
+    /// Size of the blocks the arena moved into.
+    pub bytes_reserved: usize,
+    /// Bytes left over at the end of blocks that the arena moved past.
+    pub wasted_bytes: usize,
+}
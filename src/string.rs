@@ -0,0 +1,184 @@
+//! A growable string whose buffer lives in a [`Bump`].
+
+use core::alloc::GlobalAlloc;
+use core::fmt;
+use core::ops::Deref;
+use core::str;
+
+use crate::bump::Bump;
+use crate::vec::Vec;
+use crate::Global;
+
+/// A `String`-like buffer allocated in a [`Bump`], built up with
+/// [`push_str`](Self::push_str) or `write!` and finished with
+/// [`into_bump_str`](Self::into_bump_str).
+///
+/// It grows in place like [`Vec`](crate::vec::Vec) while it is the most recent
+/// allocation of the arena.
+pub struct String<'a, A: GlobalAlloc = Global> {
+    vec: Vec<'a, u8, A>,
+}
+
+impl<'a, A: GlobalAlloc> String<'a, A> {
+    pub fn new_in(bump: &'a Bump<A>) -> Self {
+        Self {
+            vec: Vec::new_in(bump),
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, bump: &'a Bump<A>) -> Self {
+        Self {
+            vec: Vec::with_capacity_in(capacity, bump),
+        }
+    }
+
+    pub fn from_str_in(s: &str, bump: &'a Bump<A>) -> Self {
+        let mut string = Self::with_capacity_in(s.len(), bump);
+        string.push_str(s);
+        string
+    }
+
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(&self.vec) }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.vec.extend_from_slice(s.as_bytes());
+    }
+
+    /// Shortens the string to `len` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not lie on a `char` boundary.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            assert!(
+                self.as_str().is_char_boundary(len),
+                "truncate at a non-char boundary"
+            );
+            self.vec.truncate(len);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+
+    /// Converts the string into a `&str` that lives as long as the arena,
+    /// giving back unused capacity when it is the most recent allocation.
+    pub fn into_bump_str(self) -> &'a str {
+        unsafe { str::from_utf8_unchecked(self.vec.into_bump_slice()) }
+    }
+}
+
+impl<A: GlobalAlloc> Deref for String<'_, A> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<A: GlobalAlloc> fmt::Write for String<'_, A> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.push(c);
+        Ok(())
+    }
+}
+
+impl<A: GlobalAlloc> fmt::Display for String<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<A: GlobalAlloc> fmt::Debug for String<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<A: GlobalAlloc> PartialEq for String<'_, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<A: GlobalAlloc> Eq for String<'_, A> {}
+
+impl<A: GlobalAlloc> PartialEq<str> for String<'_, A> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<A: GlobalAlloc> PartialEq<&str> for String<'_, A> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::String;
+    use crate::bump::Bump;
+
+    #[test]
+    fn push() {
+        let bump = Bump::new();
+        let mut s = String::new_in(&bump);
+        s.push_str("foo");
+        s.push('→');
+        s.push_str("bar");
+        assert_eq!(s, "foo→bar");
+        assert_eq!(s.len(), 9);
+        s.truncate(3);
+        assert_eq!(s.as_str(), "foo");
+    }
+
+    #[test]
+    #[should_panic(expected = "non-char boundary")]
+    fn truncate_inside_char() {
+        let bump = Bump::new();
+        let mut s = String::from_str_in("→", &bump);
+        s.truncate(1);
+    }
+
+    #[test]
+    fn write() {
+        let bump = Bump::new();
+        let mut s = String::new_in(&bump);
+        for i in 0..3 {
+            write!(s, "{}/", i).unwrap();
+        }
+        let path = s.into_bump_str();
+        assert_eq!(path, "0/1/2/");
+
+        let next = bump.alloc_str("x");
+        assert_eq!(next.as_ptr(), unsafe { path.as_ptr().add(path.len()) });
+    }
+}
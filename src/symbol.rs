@@ -0,0 +1,126 @@
+//! A string interner that hands out compact [`Symbol`] IDs.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::bump::Bump;
+
+/// The ID of a string in an [`Interner`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the index of the symbol. Symbols are numbered from zero in
+    /// the order their strings were first interned.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+/// Maps strings to [`Symbol`]s and back, storing the bytes of each string
+/// once in arena blocks.
+pub struct Interner {
+    // The strings point into `bump`; their lifetime is erased to `'static`
+    // and never escapes a borrow of the interner.
+    map: RefCell<HashMap<&'static str, Symbol>>,
+    strings: RefCell<Vec<&'static str>>,
+    bump: Bump,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self {
+            map: RefCell::new(HashMap::new()),
+            strings: RefCell::new(Vec::new()),
+            bump: Bump::new(),
+        }
+    }
+
+    /// Returns the symbol of `s`, interning it on first sight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `u32::MAX` distinct strings are interned.
+    pub fn intern(&self, s: &str) -> Symbol {
+        if let Some(symbol) = self.get(s) {
+            return symbol;
+        }
+        let mut strings = self.strings.borrow_mut();
+        let symbol = Symbol(u32::try_from(strings.len()).expect("too many symbols"));
+        let s: &'static str = unsafe { &*(self.bump.alloc_str(s) as *const str) };
+        strings.push(s);
+        self.map.borrow_mut().insert(s, symbol);
+        symbol
+    }
+
+    /// Returns the symbol of `s` if it was interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.map.borrow().get(s).copied()
+    }
+
+    /// Returns the string of `symbol`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `symbol` comes from another interner and is out of range.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.strings.borrow()[symbol.0 as usize]
+    }
+
+    /// Returns the number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+
+    #[test]
+    fn intern_resolve() {
+        let interner = Interner::new();
+        let main = interner.intern("main");
+        let x = interner.intern("x");
+        assert_eq!(interner.intern("main"), main);
+        assert_ne!(main, x);
+        assert_eq!(main.as_u32(), 0);
+        assert_eq!(x.as_u32(), 1);
+        assert_eq!(interner.resolve(main), "main");
+        assert_eq!(interner.resolve(x), "x");
+        assert_eq!(interner.get("y"), None);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn many() {
+        let interner = Interner::new();
+        let symbols: Vec<_> = (0..10000)
+            .map(|i| interner.intern(&format!("v{}", i)))
+            .collect();
+        for (i, &symbol) in symbols.iter().enumerate() {
+            assert_eq!(interner.resolve(symbol), format!("v{}", i));
+        }
+        assert!(interner.bump.bytes_allocated() < 2 * 1024 * 1024);
+    }
+
+    #[test]
+    #[should_panic]
+    fn foreign_symbol() {
+        let a = Interner::new();
+        let b = Interner::new();
+        a.intern("a");
+        b.resolve(a.intern("b"));
+    }
+}
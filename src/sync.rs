@@ -0,0 +1,129 @@
+//! A typed arena that can be shared between threads.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::arena::Arena;
+use crate::ArenaAllocError;
+
+/// An [`Arena`] behind a lock, so that several threads can allocate into it
+/// through a shared reference, e.g. from a thread pool.
+///
+/// Allocation hands out `&T` rather than `&mut T`. Each allocation takes the
+/// lock for as long as it takes to move the value in. The lock is still held
+/// while an [`ArenaObserver`](crate::ArenaObserver) or error handler set on
+/// the arena runs, and while `try_alloc` drops a value it failed to
+/// allocate, so those must not use this `SyncArena`.
+pub struct SyncArena<T> {
+    // `SyncArena<T>` is `Sync` when `T: Send`, not only when `T: Sync`: each
+    // thread only gets references to the values it allocated itself, and
+    // those can only leave the thread if `T: Sync`.
+    arena: Mutex<Arena<T>>,
+}
+
+impl<T> SyncArena<T> {
+    pub fn new() -> Self {
+        Self::from(Arena::new())
+    }
+
+    pub fn alloc(&self, data: T) -> &T {
+        let value: *const T = self.lock().alloc(data);
+        // Values never move and live until the arena is dropped or reset,
+        // which both need `&mut self`.
+        unsafe { &*value }
+    }
+
+    pub fn try_alloc(&self, data: T) -> Result<&T, ArenaAllocError> {
+        let value: *const T = self.lock().try_alloc(data)?;
+        Ok(unsafe { &*value })
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.lock().bytes_allocated()
+    }
+
+    /// Returns the inner arena, e.g. to iterate over the values, without
+    /// taking the lock.
+    pub fn get_mut(&mut self) -> &mut Arena<T> {
+        self.arena.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn into_inner(self) -> Arena<T> {
+        self.arena
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // A panic can't leave the arena half updated, so poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, Arena<T>> {
+        self.arena.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Default for SyncArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Arena<T>> for SyncArena<T> {
+    fn from(arena: Arena<T>) -> Self {
+        Self {
+            arena: Mutex::new(arena),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::SyncArena;
+    use crate::arena::Arena;
+
+    #[test]
+    fn alloc_from_threads() {
+        let arena = SyncArena::new();
+        thread::scope(|s| {
+            for t in 0..8 {
+                let arena = &arena;
+                s.spawn(move || {
+                    let values: Vec<&String> =
+                        (0..500).map(|i| arena.alloc(format!("{t}-{i}"))).collect();
+                    for (i, value) in values.iter().enumerate() {
+                        assert_eq!(**value, format!("{t}-{i}"));
+                    }
+                });
+            }
+        });
+        assert_eq!(arena.len(), 4000);
+        let mut values: Vec<String> = arena.into_inner().into_iter().collect();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), 4000);
+    }
+
+    #[test]
+    fn try_alloc() {
+        let arena = SyncArena::from(Arena::with_limit(0));
+        assert!(arena.try_alloc(1u64).is_err());
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut arena = SyncArena::new();
+        arena.alloc(1u64);
+        for x in arena.get_mut().iter_mut() {
+            *x += 1;
+        }
+        assert_eq!(arena.into_inner().into_vec(), [2]);
+    }
+}
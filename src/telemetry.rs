@@ -0,0 +1,39 @@
+// Hooks for the optional telemetry integrations. Without the matching
+// features they compile to nothing.
+
+#[cfg_attr(
+    not(any(feature = "metrics", feature = "tracing")),
+    allow(unused_variables)
+)]
+pub(crate) fn block_allocated(size: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!("rusty_arena.blocks_created").increment(1);
+        metrics::counter!("rusty_arena.bytes_allocated").increment(size as u64);
+        metrics::gauge!("rusty_arena.bytes_reserved").increment(size as f64);
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(size, "arena block allocated");
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn block_released(size: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!("rusty_arena.bytes_reserved").decrement(size as f64);
+}
+
+/// `bytes` is what the arena keeps for reuse.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn reset(bytes: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("rusty_arena.resets").increment(1);
+    #[cfg(feature = "tracing")]
+    tracing::debug!(bytes, "arena reset");
+}
+
+/// `bytes` is what the arena releases.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn dropped(bytes: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(bytes, "arena dropped");
+}
@@ -0,0 +1,151 @@
+// Per-allocation backtraces, recorded by `Bump::set_backtraces`.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp;
+use core::fmt;
+use std::backtrace::Backtrace;
+use std::sync::Arc;
+
+/// Where and for what an allocation was made.
+#[derive(Clone, Debug)]
+pub struct AllocTrace {
+    ptr: *const u8,
+    size: usize,
+    type_name: &'static str,
+    backtrace: Arc<Backtrace>,
+}
+
+// The pointer is only an identity, it is never read through.
+unsafe impl Send for AllocTrace {}
+unsafe impl Sync for AllocTrace {}
+
+impl AllocTrace {
+    pub fn ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    /// Returns the size in bytes, after any in-place growth.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the allocated type as given by [`core::any::type_name`].
+    /// Memory requested through an allocator interface has no type and
+    /// shows up as `"<untyped>"`.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+/// The allocations an arena holds, returned by
+/// [`Bump::report`](crate::bump::Bump::report).
+///
+/// `Display` prints the bytes per type, largest first, followed by the
+/// backtraces of the largest allocations.
+#[derive(Clone, Debug, Default)]
+pub struct AllocReport {
+    traces: Vec<AllocTrace>,
+}
+
+impl AllocReport {
+    /// Returns the recorded allocations in allocation order.
+    pub fn traces(&self) -> &[AllocTrace] {
+        &self.traces
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.traces.iter().map(|t| t.size).sum()
+    }
+
+    /// Returns the type names with their allocation count and bytes,
+    /// ordered by decreasing bytes.
+    pub fn by_type(&self) -> Vec<(&'static str, usize, usize)> {
+        let mut types: Vec<(&'static str, usize, usize)> = Vec::new();
+        for trace in &self.traces {
+            match types.iter_mut().find(|(name, ..)| *name == trace.type_name) {
+                Some((_, count, bytes)) => {
+                    *count += 1;
+                    *bytes += trace.size;
+                }
+                None => types.push((trace.type_name, 1, trace.size)),
+            }
+        }
+        types.sort_by_key(|&(_, _, bytes)| cmp::Reverse(bytes));
+        types
+    }
+}
+
+impl fmt::Display for AllocReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const LARGEST: usize = 10;
+
+        writeln!(
+            f,
+            "{} bytes in {} allocations",
+            self.total_bytes(),
+            self.traces.len()
+        )?;
+        for (name, count, bytes) in self.by_type() {
+            writeln!(f, "{bytes:>12} bytes {count:>8}x {name}")?;
+        }
+        let mut largest: Vec<&AllocTrace> = self.traces.iter().collect();
+        largest.sort_by_key(|trace| cmp::Reverse(trace.size));
+        for trace in largest.into_iter().take(LARGEST) {
+            writeln!(
+                f,
+                "\n{} bytes of {} at {:p}:\n{}",
+                trace.size, trace.type_name, trace.ptr, trace.backtrace
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The traces recorded so far by an arena.
+#[derive(Default)]
+pub(crate) struct Traces(RefCell<Vec<AllocTrace>>);
+
+impl Traces {
+    pub(crate) fn record(&self, ptr: *const u8, size: usize, type_name: &'static str) {
+        self.0.borrow_mut().push(AllocTrace {
+            ptr,
+            size,
+            type_name,
+            backtrace: Arc::new(Backtrace::force_capture()),
+        });
+    }
+
+    /// Updates the size of the most recent allocation if it starts at `ptr`,
+    /// forgetting it when it shrinks to nothing.
+    pub(crate) fn resize_last(&self, ptr: *const u8, size: usize) {
+        let mut traces = self.0.borrow_mut();
+        let Some(last) = traces.last_mut().filter(|last| last.ptr == ptr) else {
+            return;
+        };
+        if size > 0 {
+            last.size = size;
+        } else {
+            traces.pop();
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Forgets the traces past the first `len`.
+    pub(crate) fn truncate(&self, len: usize) {
+        self.0.borrow_mut().truncate(len);
+    }
+
+    pub(crate) fn report(&self) -> AllocReport {
+        AllocReport {
+            traces: self.0.borrow().clone(),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! A growable vector whose buffer lives in a [`Bump`].
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::any;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use core::slice;
+
+use crate::bump::Bump;
+use crate::{ArenaAllocError, Global};
+
+/// A `Vec`-like container allocated in a [`Bump`].
+///
+/// While the buffer is the most recent allocation of the arena it grows in
+/// place. Otherwise growing moves the elements to a new buffer and leaves the
+/// old one behind until the arena is reset.
+///
+/// The vector drops its elements when it is dropped, unless it was turned
+/// into an arena slice with [`into_bump_slice`](Self::into_bump_slice).
+pub struct Vec<'a, T, A: GlobalAlloc = Global> {
+    bump: &'a Bump<A>,
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T, A: GlobalAlloc> Vec<'a, T, A> {
+    /// Creates an empty vector. Nothing is allocated until the first push.
+    pub fn new_in(bump: &'a Bump<A>) -> Self {
+        Self {
+            bump,
+            ptr: NonNull::dangling(),
+            len: 0,
+            cap: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates an empty vector with room for `capacity` elements.
+    pub fn with_capacity_in(capacity: usize, bump: &'a Bump<A>) -> Self {
+        let mut vec = Self::new_in(bump);
+        vec.reserve(capacity);
+        vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Makes room for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = match self.len.checked_add(additional) {
+            Some(needed) => needed,
+            None => self.bump.fail(ArenaAllocError::CapacityOverflow),
+        };
+        if needed > self.cap {
+            self.grow(needed);
+        }
+    }
+
+    #[cold]
+    fn grow(&mut self, needed: usize) {
+        let new_cap = needed.max(self.cap.saturating_mul(2)).max(4);
+        let new_layout = match Layout::array::<T>(new_cap) {
+            Ok(layout) => layout,
+            Err(_) => self.bump.fail(ArenaAllocError::CapacityOverflow),
+        };
+        let size = mem::size_of::<T>();
+        unsafe {
+            let old = self.ptr.as_ptr().cast::<u8>();
+            if self.cap == 0
+                || !self
+                    .bump
+                    .resize_last_raw(old, self.cap * size, new_layout.size())
+            {
+                let new = self
+                    .bump
+                    .alloc_layout_raw(new_layout, any::type_name::<[T]>())
+                    .cast::<T>();
+                ptr::copy_nonoverlapping(self.ptr.as_ptr(), new, self.len);
+                self.ptr = NonNull::new_unchecked(new);
+            }
+        }
+        self.cap = new_cap;
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(self.ptr.as_ptr().add(self.len).read()) }
+    }
+
+    /// Drops the elements past the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.ptr.as_ptr().add(len) }, self.len - len);
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn extend_from_slice(&mut self, src: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(src.len());
+        for x in src {
+            self.push(x.clone());
+        }
+    }
+
+    /// Converts the vector into a slice that lives as long as the arena.
+    ///
+    /// Unused capacity is given back when the buffer is the most recent
+    /// allocation. Like every other value in a [`Bump`], the elements are
+    /// never dropped.
+    pub fn into_bump_slice(self) -> &'a mut [T] {
+        let this = mem::ManuallyDrop::new(self);
+        let size = mem::size_of::<T>();
+        if size != 0 && this.cap > this.len {
+            unsafe {
+                this.bump.resize_last_raw(
+                    this.ptr.as_ptr().cast(),
+                    this.cap * size,
+                    this.len * size,
+                );
+            }
+        }
+        unsafe { slice::from_raw_parts_mut(this.ptr.as_ptr(), this.len) }
+    }
+}
+
+impl<T, A: GlobalAlloc> Drop for Vec<'_, T, A> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) };
+    }
+}
+
+impl<T, A: GlobalAlloc> Deref for Vec<'_, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, A: GlobalAlloc> DerefMut for Vec<'_, T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, A: GlobalAlloc> Extend<T> for Vec<'_, T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for x in iter {
+            self.push(x);
+        }
+    }
+}
+
+impl<'v, T, A: GlobalAlloc> IntoIterator for &'v Vec<'_, T, A> {
+    type Item = &'v T;
+    type IntoIter = slice::Iter<'v, T>;
+
+    fn into_iter(self) -> slice::Iter<'v, T> {
+        self.iter()
+    }
+}
+
+impl<'v, T, A: GlobalAlloc> IntoIterator for &'v mut Vec<'_, T, A> {
+    type Item = &'v mut T;
+    type IntoIter = slice::IterMut<'v, T>;
+
+    fn into_iter(self) -> slice::IterMut<'v, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T: fmt::Debug, A: GlobalAlloc> fmt::Debug for Vec<'_, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<T: PartialEq, A: GlobalAlloc> PartialEq for Vec<'_, T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, A: GlobalAlloc> Eq for Vec<'_, T, A> {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::Vec;
+    use crate::bump::Bump;
+
+    #[test]
+    fn push_pop() {
+        let bump = Bump::new();
+        let mut v = Vec::new_in(&bump);
+        assert!(v.is_empty());
+        for i in 0..100 {
+            v.push(i);
+        }
+        assert_eq!(v.len(), 100);
+        assert_eq!(v[42], 42);
+        assert_eq!(v.pop(), Some(99));
+        assert_eq!(v.iter().sum::<i32>(), (0..99).sum::<i32>());
+    }
+
+    #[test]
+    fn grows_in_place() {
+        let bump = Bump::new();
+        let mut v = Vec::with_capacity_in(4, &bump);
+        v.extend(0..4u32);
+        let ptr = v.as_ptr();
+        v.push(4);
+        assert_eq!(v.as_ptr(), ptr);
+        assert!(v.capacity() >= 5);
+
+        // Another allocation in between forces a move.
+        bump.alloc(0u8);
+        v.extend(5..100);
+        assert_ne!(v.as_ptr(), ptr);
+        assert_eq!(v.as_slice(), (0..100).collect::<std::vec::Vec<_>>());
+    }
+
+    #[test]
+    fn into_bump_slice() {
+        let bump = Bump::new();
+        let mut v = Vec::with_capacity_in(16, &bump);
+        v.extend_from_slice(&[1u64, 2, 3]);
+        let slice = v.into_bump_slice();
+        assert_eq!(slice, [1, 2, 3]);
+
+        // The spare capacity was given back.
+        let next = bump.alloc(4u64) as *mut u64;
+        assert_eq!(next, unsafe { slice.as_mut_ptr().add(3) });
+    }
+
+    #[test]
+    fn drops_elements() {
+        struct D<'c>(&'c Cell<usize>);
+
+        impl Drop for D<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let bump = Bump::new();
+        let mut v = Vec::new_in(&bump);
+        for _ in 0..10 {
+            v.push(D(&drops));
+        }
+        v.truncate(7);
+        assert_eq!(drops.get(), 3);
+        drop(v);
+        assert_eq!(drops.get(), 10);
+    }
+
+    #[test]
+    fn zero_sized() {
+        let bump = Bump::new();
+        let mut v = Vec::new_in(&bump);
+        for _ in 0..1000 {
+            v.push(());
+        }
+        assert_eq!(v.len(), 1000);
+        assert_eq!(bump.bytes_allocated(), 0);
+    }
+}